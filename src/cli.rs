@@ -0,0 +1,78 @@
+//! Command line options
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Usage text printed for `--help` and invalid options
+pub const USAGE: &str = "Usage: recipier [OPTIONS] [FILES]...
+
+Files are opened in the running instance if Recipier is already running, and
+the recipe folders of other cookbooks are merged into this one
+
+Options:
+    --data-dir <DIR>    Keep all recipes, settings, and logs in DIR
+    --portable          Keep all files next to the executable, also enabled by
+                        placing a file named `portable` next to the executable
+    --check             Check saved recipes for problems, print them, and exit
+    --repair            Check saved recipes and repair any problems found, moving
+                        broken recipes to the quarantine folder, and exit
+    -h, --help          Print this message and exit";
+
+/// Options parsed from the command line
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    /// Directory to keep all files in instead of the per-user directories
+    pub data_dir: Option<PathBuf>,
+    /// If files should be kept next to the executable
+    pub portable: bool,
+    /// If the recipe database should be checked instead of starting the GUI
+    pub check: bool,
+    /// If problems found when checking the recipe database should be repaired
+    pub repair: bool,
+    /// If the usage text should be printed
+    pub help: bool,
+    /// Files to open
+    pub files: Vec<PathBuf>,
+}
+
+/// Enumeration defining all errors that can occur when parsing command line options
+#[derive(Debug, Error)]
+pub enum ArgsError {
+    #[error("Option {} requires a value", .0)]
+    MissingValue(&'static str),
+
+    #[error("Unknown option {}", .0)]
+    Unknown(String),
+}
+
+impl Args {
+    /// Parse options from the arguments the program was started with
+    pub fn from_env() -> Result<Self, ArgsError> {
+        Self::parse(std::env::args_os().skip(1).map(PathBuf::from))
+    }
+
+    /// Parse options from a list of arguments, not including the program name
+    pub fn parse(args: impl IntoIterator<Item = PathBuf>) -> Result<Self, ArgsError> {
+        let mut this = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.to_str() {
+                Some("--data-dir") => {
+                    this.data_dir = Some(args.next().ok_or(ArgsError::MissingValue("--data-dir"))?)
+                }
+                Some("--portable") => this.portable = true,
+                Some("--check") => this.check = true,
+                Some("--repair") => this.repair = true,
+                Some("-h" | "--help") => this.help = true,
+                Some(option) if option.starts_with('-') => {
+                    return Err(ArgsError::Unknown(option.to_owned()))
+                }
+                _ => this.files.push(arg),
+            }
+        }
+
+        Ok(this)
+    }
+}
@@ -0,0 +1,162 @@
+//! State for the backups screen
+
+use std::{path::Path, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use druid::{im::Vector, Data, ExtEventSink, Lens, Target};
+
+use crate::{
+    gui::BACKUP_FINISHED,
+    paths::Paths,
+    recipes::{
+        db::{
+            backup::{self as snapshot, BackupError, Snapshot},
+            Database,
+        },
+        recipe::Recipe,
+    },
+};
+
+/// Snapshots listed on the backups screen and the recipes in the snapshot being browsed
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct BackupState {
+    /// Every snapshot in the backup directory, newest first
+    pub snapshots: Vector<SnapshotItem>,
+    /// The snapshot whose recipes are shown to restore one at a time
+    pub opened: Option<OpenedSnapshot>,
+    /// The result of the last backup or restore
+    pub message: Option<Arc<str>>,
+    /// If a backup or restore is running in the background
+    pub working: bool,
+}
+
+/// A snapshot in the backup directory
+#[derive(Clone, Debug, Data, Lens)]
+pub struct SnapshotItem {
+    /// Path to the snapshot archive
+    pub path: Arc<Path>,
+    /// When the snapshot was created
+    #[data(same_fn = "PartialEq::eq")]
+    pub created: DateTime<Utc>,
+    /// How many recipes the snapshot holds
+    pub recipes: usize,
+}
+
+/// A snapshot opened to list the recipes in it
+#[derive(Clone, Debug, Data, Lens)]
+pub struct OpenedSnapshot {
+    /// When the snapshot was created
+    #[data(same_fn = "PartialEq::eq")]
+    pub created: DateTime<Utc>,
+    /// Every recipe in the snapshot that could be read
+    pub recipes: Vector<BackupRecipe>,
+}
+
+/// A recipe saved in a snapshot that can be restored
+#[derive(Clone, Debug, Data, Lens)]
+pub struct BackupRecipe {
+    /// Path to the snapshot archive the recipe is in
+    pub snapshot: Arc<Path>,
+    /// The recipe as it was saved in the snapshot
+    pub recipe: Arc<Recipe>,
+}
+
+impl SnapshotItem {
+    /// List every snapshot in a backup directory, newest first
+    pub fn list(dir: &Path) -> Vector<Self> {
+        match Snapshot::list(dir) {
+            Ok(snapshots) => snapshots.iter().map(Self::from).collect(),
+            Err(e) => {
+                log::error!("Failed to list backups in {}: {}", dir.display(), e);
+                Vector::new()
+            }
+        }
+    }
+}
+
+impl From<&Snapshot> for SnapshotItem {
+    fn from(snapshot: &Snapshot) -> Self {
+        let recipes = snapshot.ids().map(|ids| ids.len()).unwrap_or_else(|e| {
+            log::warn!("Failed to read backup {}: {}", snapshot.path.display(), e);
+            0
+        });
+        Self {
+            path: Arc::from(snapshot.path.as_path()),
+            created: snapshot.created,
+            recipes,
+        }
+    }
+}
+
+impl OpenedSnapshot {
+    /// Read every recipe saved in a snapshot, skipping recipes that can't be read
+    pub fn open(db: &Database, snapshot: &Snapshot) -> Result<Self, BackupError> {
+        let path = Arc::<Path>::from(snapshot.path.as_path());
+        let mut recipes = db
+            .backup_recipes(snapshot)?
+            .into_iter()
+            .map(|recipe| BackupRecipe {
+                snapshot: path.clone(),
+                recipe: Arc::new(recipe),
+            })
+            .collect::<Vec<_>>();
+        recipes.sort_by(|a, b| a.recipe.name.cmp(&b.recipe.name));
+
+        Ok(Self {
+            created: snapshot.created,
+            recipes: recipes.into_iter().collect(),
+        })
+    }
+}
+
+/// Back up every recipe and the given saved app state on a background thread, then replace every
+/// recipe with the recipes in `restore` if a snapshot is given. The oldest backups are deleted so
+/// that at most `keep` are left, and a message describing the result is sent to the main window
+pub fn spawn(
+    recipes: Database,
+    state: Vec<u8>,
+    keep: usize,
+    restore: Option<Snapshot>,
+    sink: ExtEventSink,
+) {
+    std::thread::spawn(move || {
+        let dir = Paths::get().backup_dir();
+        let backed_up = recipes.backup(&state, &dir);
+        let message = match restore {
+            None => match backed_up.and_then(|snapshot| {
+                snapshot::rotate(&dir, keep)?;
+                Ok(snapshot)
+            }) {
+                Ok(snapshot) => format!(
+                    "Backed up {} recipes to {}",
+                    recipes.len(),
+                    snapshot.path.display()
+                ),
+                Err(e) => {
+                    log::error!("Failed to back up recipes: {}", e);
+                    format!("Failed to back up recipes: {}", e)
+                }
+            },
+            //The recipes being replaced are backed up first so the restore can be undone
+            Some(restore) => match backed_up
+                .and_then(|_| recipes.restore_all_backup(&restore))
+                .and_then(|restored| {
+                    snapshot::rotate(&dir, keep)?;
+                    Ok(restored)
+                }) {
+                Ok(restored) => format!(
+                    "Restored {} recipes, the replaced recipes were backed up first",
+                    restored
+                ),
+                Err(e) => {
+                    log::error!("Failed to restore backup {}: {}", restore.path.display(), e);
+                    format!("Failed to restore backup: {}", e)
+                }
+            },
+        };
+
+        if let Err(e) = sink.submit_command(BACKUP_FINISHED, Arc::from(message), Target::Global) {
+            log::error!("Failed to send backup result to the main window: {}", e);
+        }
+    });
+}
@@ -3,6 +3,8 @@
 use druid::{Data, Lens};
 use serde::{Deserialize, Serialize};
 
+use crate::recipes::{db::storage::StorageConfig, format::UnitFormat, measure::VolumeSystem};
+
 /// Application configuration data contained in the [AppState](super::AppState) structure
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
 pub struct Config {
@@ -10,4 +12,36 @@ pub struct Config {
     pub window_size: (f64, f64),
     /// If we should periodically check for application updates
     pub no_update_check: bool,
+    /// How measurements are displayed
+    pub units: UnitFormat,
+    /// The measurement system that spoons, fluid ounces, cups, pints, quarts, and gallons use when
+    /// no system is chosen
+    pub volume_system: VolumeSystem,
+    /// The backend that recipes are saved to
+    #[data(same_fn = "PartialEq::eq")]
+    pub storage: StorageConfig,
+    /// How many days deleted recipes are kept in the trash before they are permanently deleted,
+    /// or 0 to keep them until they are deleted by hand
+    pub trash_days: u32,
+    /// How many hours to wait between automatic backups, or 0 to only back up by hand
+    pub backup_hours: u32,
+    /// How many backups to keep before the oldest are deleted
+    pub backup_count: u32,
+    /// If recipes are encrypted with a passphrase that is asked for at startup
+    pub encrypted: bool,
+}
+
+/// Keep deleted recipes for a month by default
+pub const fn default_trash_days() -> u32 {
+    30
+}
+
+/// Back up once a day by default
+pub const fn default_backup_hours() -> u32 {
+    24
+}
+
+/// Keep a week and a half of daily backups by default
+pub const fn default_backup_count() -> u32 {
+    10
 }
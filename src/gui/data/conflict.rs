@@ -0,0 +1,52 @@
+//! State for the conflict resolution screen
+
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, Lens};
+
+use crate::recipes::{
+    db::{
+        conflict::{Conflict, ConflictSource},
+        Database, RecipeId,
+    },
+    recipe::Recipe,
+};
+
+/// Both copies of a recipe that was changed in two places, displayed side by side
+#[derive(Clone, Debug, Data, Lens)]
+pub struct ConflictItem {
+    /// The ID of the conflicting recipe
+    pub id: RecipeId,
+    /// The copy of the recipe in the database, or `None` if it was removed
+    pub mine: Option<Arc<Recipe>>,
+    /// The other copy of the recipe, or `None` if it was deleted
+    pub theirs: Option<Arc<Recipe>>,
+    /// A description of where the other copy came from
+    pub source: &'static str,
+}
+
+impl ConflictItem {
+    /// Get both copies of every conflicting recipe in a database
+    pub fn list(db: &Database) -> Vector<Self> {
+        let mut conflicts = db
+            .conflicts()
+            .into_iter()
+            .map(|conflict| Self::new(db, conflict))
+            .collect::<Vec<_>>();
+        conflicts.sort_by_key(|conflict| conflict.id);
+        conflicts.into()
+    }
+
+    /// Look up the database's copy of a conflicting recipe
+    fn new(db: &Database, conflict: Conflict) -> Self {
+        Self {
+            id: conflict.id,
+            mine: db.get(conflict.id),
+            theirs: conflict.theirs,
+            source: match conflict.source {
+                ConflictSource::Storage => "Changed on disk",
+                ConflictSource::Import => "Imported",
+            },
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! State for the duplicate recipes screen
+
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, ExtEventSink, Lens, Target};
+
+use crate::{
+    gui::DUPLICATES_FOUND,
+    recipes::{
+        db::{
+            duplicates::{Duplicate, DEFAULT_THRESHOLD},
+            Database,
+        },
+        recipe::Recipe,
+    },
+};
+
+/// Pairs of recipes that are likely copies of each other, and how similar recipes must be to be
+/// listed
+#[derive(Clone, Debug, Data, Lens)]
+pub struct DuplicateState {
+    /// Lowest similarity score of listed pairs, from 0 to 1
+    pub threshold: f64,
+    /// Every pair of likely duplicates, most similar first
+    pub pairs: Vector<DuplicatePair>,
+    /// If recipes are being compared in the background
+    pub searching: bool,
+}
+
+impl Default for DuplicateState {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD as f64,
+            pairs: Vector::new(),
+            searching: false,
+        }
+    }
+}
+
+/// Two recipes that are likely copies of each other, displayed side by side
+#[derive(Clone, Debug, Data, Lens)]
+pub struct DuplicatePair {
+    /// The older recipe
+    pub first: Arc<Recipe>,
+    /// The newer recipe
+    pub second: Arc<Recipe>,
+    /// How similar the recipes are, from 0 to 1
+    pub score: f32,
+}
+
+impl DuplicatePair {
+    /// Get the recipes of every pair of likely duplicates found in a database
+    pub fn list(db: &Database, duplicates: &[Duplicate]) -> Vector<Self> {
+        duplicates
+            .iter()
+            .filter_map(|duplicate| {
+                Some(Self {
+                    first: db.get(duplicate.first)?,
+                    second: db.get(duplicate.second)?,
+                    score: duplicate.score,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Find likely duplicate recipes in a database on a background thread, then tell the main window
+/// that they can be [listed](super::AppState::refresh_duplicates)
+pub fn spawn(recipes: Database, threshold: f32, sink: ExtEventSink) {
+    std::thread::spawn(move || {
+        recipes.duplicates(threshold);
+        if let Err(e) = sink.submit_command(DUPLICATES_FOUND, (), Target::Global) {
+            log::error!("Failed to send found duplicates to the main window: {}", e);
+        }
+    });
+}
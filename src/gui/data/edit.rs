@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::recipes::{
-    db::{Database, RecipeId},
+    db::RecipeId,
     measure::{AmountUnit, Mass, Volume},
     recipe::{Ingredient, IngredientAmount, Recipe},
 };
@@ -32,6 +32,14 @@ pub struct EditState {
     /// Optional image data
     #[serde(skip)]
     pub image: Option<ImageBuf>,
+    /// If the recipe's image is being decoded in the background and will be put in
+    /// [image](Self::image) once it is ready
+    #[serde(skip)]
+    pub image_loading: bool,
+    /// If an image was chosen or removed since editing started, so that the image is only
+    /// encoded and written again when it changed
+    #[serde(skip)]
+    pub image_changed: bool,
     /// The screen to return to after editing is over
     pub return_to: AppScreen,
 }
@@ -51,6 +59,8 @@ impl Data for EditState {
                     Arc::ptr_eq(&img1.raw_pixels_shared(), &img2.raw_pixels_shared())
                 }
             }
+            && self.image_loading.same(&other.image_loading)
+            && self.image_changed.same(&other.image_changed)
             && self.return_to.same(&other.return_to)
     }
 }
@@ -147,7 +157,9 @@ impl EditedIngredient {
 }
 
 impl EditState {
-    pub fn from_recipe(db: &Database, recipe: &Recipe) -> Self {
+    /// Create edit state for an existing recipe without its image, which is decoded in the
+    /// background
+    pub fn from_recipe(recipe: &Recipe) -> Self {
         Self {
             id: Some(recipe.id),
             title: recipe.name.deref().to_owned(),
@@ -163,7 +175,9 @@ impl EditState {
             servings: recipe.servings,
             time: recipe.time.map(From::from),
             return_to: AppScreen::Home,
-            image: db.get_image(recipe.id),
+            image: None,
+            image_loading: false,
+            image_changed: false,
         }
     }
 }
@@ -179,6 +193,8 @@ impl Default for EditState {
             time: None,
             return_to: AppScreen::Home,
             image: None,
+            image_loading: false,
+            image_changed: false,
         }
     }
 }
@@ -3,7 +3,10 @@
 use druid::{im::Vector, Data, Lens};
 use serde::{Deserialize, Serialize};
 
-use crate::recipes::db::RecipeId;
+use crate::recipes::db::{
+    query::{Cursor, Query, SortKey},
+    RecipeId,
+};
 
 /// Structure holding all state needed in the home screen widget
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
@@ -11,12 +14,30 @@ pub struct HomeState {
     /// How many recipes are currently loaded in view
     #[serde(skip)]
     pub loaded: Vector<RecipeId>,
+    /// Where the next page of recipes starts, `None` if every recipe is loaded
+    #[serde(skip)]
+    #[data(same_fn = "PartialEq::eq")]
+    pub next: Option<Cursor>,
+    /// What the recipes are sorted by
+    pub sort: SortKey,
+    /// If the recipes are sorted in reverse
+    pub descending: bool,
+}
+
+impl HomeState {
+    /// Build a database query for the recipes shown on the home screen
+    pub fn query(&self) -> Query {
+        Query::new().sort_by(self.sort).descending(self.descending)
+    }
 }
 
 impl Default for HomeState {
     fn default() -> Self {
         Self {
             loaded: Vector::default(),
+            next: None,
+            sort: SortKey::default(),
+            descending: false,
         }
     }
 }
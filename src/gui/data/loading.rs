@@ -0,0 +1,98 @@
+//! Progress of loading recipes in the background at startup
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use druid::{im::Vector, Data, ExtEventSink, Lens, Target};
+
+use super::screen::AppScreen;
+use crate::{
+    gui::{LOAD_PROGRESS, RECIPES_LOADED},
+    recipes::db::{crypt::CryptError, storage::StorageError, Database},
+};
+
+/// Least time between two progress updates sent to the main window
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// State of the loading screen shown while recipes are loaded
+#[derive(Clone, Debug, Data, Lens)]
+pub struct LoadingState {
+    /// The number of recipes loaded so far
+    pub loaded: usize,
+    /// The number of recipes to load, or 0 if it is not known yet
+    pub total: usize,
+    /// The screen to show once recipes are loaded
+    pub return_to: AppScreen,
+    /// Files that were opened before recipes could be changed, opened once recipes are loaded
+    /// and unlocked
+    pub pending: Vector<Arc<Path>>,
+}
+
+impl LoadingState {
+    /// Create a loading state that returns to the given screen once recipes are loaded, or to
+    /// the home screen if the screen can't be shown before recipes are loaded
+    pub fn new(return_to: AppScreen) -> Self {
+        Self {
+            loaded: 0,
+            total: 0,
+            return_to: match return_to {
+                AppScreen::Loading | AppScreen::Unlock => AppScreen::Home,
+                screen => screen,
+            },
+            pending: Vector::new(),
+        }
+    }
+
+    /// Get the fraction of recipes loaded so far
+    pub fn fraction(&self) -> f64 {
+        match self.total {
+            0 => 0.,
+            total => self.loaded as f64 / total as f64,
+        }
+    }
+}
+
+impl Default for LoadingState {
+    fn default() -> Self {
+        Self::new(AppScreen::Home)
+    }
+}
+
+/// Purge old recipes from the trash and load recipes into a database on a background thread,
+/// sending progress and then messages for recipes that could not be loaded to the main window
+pub fn spawn(recipes: Database, trash_days: u32, sink: ExtEventSink) {
+    std::thread::spawn(move || {
+        if trash_days > 0 {
+            if let Err(e) = recipes.purge_older_than(trash_days) {
+                log::error!("Failed to purge old recipes from the trash: {}", e);
+            }
+        }
+
+        let mut last_sent = Instant::now();
+        let loaded = recipes.load_with_progress(|loaded, total| {
+            if loaded < total && last_sent.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            last_sent = Instant::now();
+            if let Err(e) = sink.submit_command(LOAD_PROGRESS, (loaded, total), Target::Global) {
+                log::error!("Failed to send loading progress to the main window: {}", e);
+            }
+        });
+
+        let result = match loaded {
+            Ok(errors) => Ok(errors.iter().map(|e| Arc::from(e.to_string())).collect()),
+            //The unlock screen loads encrypted recipes once the passphrase is entered
+            Err(StorageError::Crypt(CryptError::Locked)) => Ok(vec![]),
+            Err(e) => {
+                log::error!("Failed to load recipes: {}", e);
+                Err(Arc::from(format!("Failed to load recipes: {}", e)))
+            }
+        };
+        if let Err(e) = sink.submit_command(RECIPES_LOADED, result, Target::Global) {
+            log::error!("Failed to send loaded recipes to the main window: {}", e);
+        }
+    });
+}
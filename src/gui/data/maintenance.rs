@@ -0,0 +1,32 @@
+//! State for the maintenance screen
+
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, ExtEventSink, Lens, Target};
+
+use crate::{gui::DATABASE_CHECKED, recipes::db::Database};
+
+/// Results of the last maintenance action run from the maintenance screen
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct MaintenanceState {
+    /// A summary of the last integrity check or import, or `None` if neither has run
+    pub summary: Option<Arc<str>>,
+    /// Every problem found by the last integrity check
+    pub findings: Vector<Arc<str>>,
+    /// If an integrity check is running in the background
+    pub checking: bool,
+}
+
+/// Check the recipe database for problems on a background thread, repairing them if `repair` is
+/// true, and send every problem found to the main window
+pub fn spawn_check(recipes: Database, repair: bool, sink: ExtEventSink) {
+    std::thread::spawn(move || {
+        let findings = recipes.check(repair);
+        if let Err(e) = sink.submit_command(DATABASE_CHECKED, findings, Target::Global) {
+            log::error!(
+                "Failed to send database check results to the main window: {}",
+                e
+            );
+        }
+    });
+}
@@ -1,21 +1,62 @@
+pub mod backup;
 pub mod config;
+pub mod conflict;
+pub mod duplicate;
 pub mod edit;
 pub mod home;
+pub mod loading;
+pub mod maintenance;
+pub mod passphrase;
 pub mod remove;
+pub mod save;
 pub mod screen;
 pub mod search;
+pub mod trash;
 pub mod view;
 
-use std::{ops::Deref, path::Path, sync::Arc};
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use druid::{widget::ListIter, Data, Lens};
+use chrono::Utc;
+use druid::{im::Vector, widget::ListIter, Data, ExtEventSink, Lens, Target};
 use serde::{Deserialize, Serialize};
 
-use crate::recipes::{db::Database, recipe::Recipe};
+use crate::recipes::{
+    db::{
+        atomic,
+        backup::Snapshot,
+        migrate::{self, MigrationError, Versioned, STATE_VERSION},
+        stats::Stats,
+        storage::{StorageConfig, StorageError},
+        Database,
+    },
+    format::UnitFormat,
+    measure::VolumeSystem,
+    recipe::Recipe,
+};
+use crate::{gui::STATS_COLLECTED, paths::Paths};
 
 use self::{
-    config::Config, edit::EditState, home::HomeState, remove::RemoveState, screen::AppScreen,
-    search::SearchState, view::ViewState,
+    backup::{BackupState, SnapshotItem},
+    config::Config,
+    conflict::ConflictItem,
+    duplicate::{DuplicatePair, DuplicateState},
+    edit::EditState,
+    home::HomeState,
+    loading::LoadingState,
+    maintenance::MaintenanceState,
+    passphrase::PassphraseState,
+    remove::RemoveState,
+    save::SaveStatus,
+    screen::AppScreen,
+    search::SearchState,
+    trash::TrashItem,
+    view::ViewState,
 };
 
 /// Structure holding all state information, must be easily cloneable and comparable or performance will
@@ -25,8 +66,8 @@ pub struct AppState {
     /// The configuration data for this state
     pub config: Config,
 
-    /// The central database of recipes
-    #[serde(serialize_with = "Database::save", deserialize_with = "Database::load")]
+    /// The central database of recipes, opened from the storage backend in the config
+    #[serde(skip)]
     pub recipes: Database,
 
     /// State for querying the database of recipes
@@ -47,44 +88,361 @@ pub struct AppState {
 
     /// The optional state for the delete recipe screen
     pub remove: Option<RemoveState>,
+
+    /// The result of the last save of app state or recipe data
+    #[serde(skip)]
+    pub save_status: SaveStatus,
+
+    /// Recipes that were changed in two places and need to be resolved
+    #[serde(skip)]
+    pub conflicts: Vector<ConflictItem>,
+
+    /// Recipes in the trash, shown on the recently deleted screen
+    #[serde(skip)]
+    pub trash: Vector<TrashItem>,
+
+    /// Results shown on the maintenance screen
+    #[serde(skip)]
+    pub maintenance: MaintenanceState,
+
+    /// Backups listed on the backups screen
+    #[serde(skip)]
+    pub backups: BackupState,
+
+    /// Passphrase entered to unlock or encrypt recipes, never saved
+    #[serde(skip)]
+    pub passphrase: PassphraseState,
+
+    /// Progress of loading recipes at startup
+    #[serde(skip)]
+    pub loading: LoadingState,
+
+    /// Errors for saved data that could not be loaded at startup, shown on the home screen
+    #[serde(skip)]
+    pub load_errors: Vector<Arc<str>>,
+
+    /// Statistics shown on the statistics screen
+    #[serde(skip)]
+    pub stats: Arc<Stats>,
+
+    /// If statistics are being collected in the background
+    #[serde(skip)]
+    pub collecting_stats: bool,
+
+    /// Likely duplicate recipes shown on the duplicates screen
+    #[serde(skip)]
+    pub duplicates: DuplicateState,
 }
 
 impl AppState {
-    /// Initialize an appstate from a file or the default state if the file was not found
+    /// Initialize an appstate from a file, or its last good copy if the file is corrupted, or the
+    /// default state if neither can be loaded. State saved by older versions is upgraded, keeping
+    /// a copy of the original file
     pub fn init(path: impl AsRef<Path>) -> Self {
-        match std::fs::File::open(&path) {
-            Ok(file) => match serde_json::from_reader::<_, Self>(file) {
-                Ok(mut me) => {
-                    let ids = me.recipes.ids();
-                    me.home.loaded = druid::im::Vector::from(&ids[0..ids.len().min(10)]);
-                    me
-                }
-                Err(e) => {
-                    log::error!(
-                        "Failed to deserialize app state from {}: {}, returning default...",
-                        path.as_ref().display(),
-                        e
-                    );
+        let path = path.as_ref();
+        let mut load_errors = Vector::new();
+        let mut me = match Self::load(path) {
+            Ok(me) => me,
+            Err(e) => {
+                log::error!(
+                    "Failed to load app state from {}: {}, returning default...",
+                    path.display(),
+                    e
+                );
+                //Don't lose state saved by a newer version when the default state is saved over it
+                if let Some(MigrationError::TooNew { found, .. }) =
+                    e.get_ref().and_then(|e| e.downcast_ref::<MigrationError>())
+                {
+                    Self::backup_version(path, *found);
+                    load_errors
+                        .push_back(Arc::from(format!("Settings could not be loaded: {}", e)));
                     Self::default()
+                } else {
+                    Self::recover(&mut load_errors)
                 }
-            },
+            }
+        };
+
+        let (recipes, error) = Self::open_recipes(&me.config.storage);
+        me.recipes = recipes;
+        load_errors.extend(error);
+        me.load_errors = load_errors;
+
+        //Recipes are loaded in the background once the window is shown, see loading::spawn
+        me.loading = LoadingState::new(me.screen);
+        me.screen = AppScreen::Loading;
+        me
+    }
+
+    /// Read, upgrade, and deserialize app state from a file
+    fn load(path: &Path) -> io::Result<Self> {
+        let value = atomic::read(path, serde_json::from_reader::<_, serde_json::Value>)?;
+        let migrated = migrate::migrate_state(value).map_err(io::Error::other)?;
+        if migrated.from_version != STATE_VERSION {
+            log::info!(
+                "Upgrading app state from format version {} to {}",
+                migrated.from_version,
+                STATE_VERSION
+            );
+            Self::backup_version(path, migrated.from_version);
+        }
+
+        Ok(serde_json::from_value(migrated.value)?)
+    }
+
+    /// Load the app state saved in the most recent backup after the state file and its last
+    /// good copy could not be loaded, or the default state if there is no usable backup
+    fn recover(load_errors: &mut Vector<Arc<str>>) -> Self {
+        let latest = match Snapshot::latest(&Paths::get().backup_dir()) {
+            Some(latest) => latest,
+            None => return Self::default(),
+        };
+
+        let recovered = latest.state().map_err(|e| e.to_string()).and_then(|state| {
+            let value =
+                serde_json::from_slice(&state.unwrap_or_default()).map_err(|e| e.to_string())?;
+            let migrated = migrate::migrate_state(value).map_err(|e| e.to_string())?;
+            serde_json::from_value::<Self>(migrated.value).map_err(|e| e.to_string())
+        });
+        match recovered {
+            Ok(me) => {
+                log::warn!("Recovered app state from backup {}", latest.path.display());
+                load_errors.push_back(Arc::from(format!(
+                    "Settings could not be loaded and were restored from the backup made {}",
+                    latest
+                        .created
+                        .with_timezone(&chrono::Local)
+                        .format("%B %e, %Y at %I:%M %p")
+                )));
+                me
+            }
             Err(e) => {
-                log::warn!(
-                    "Failed to open file {}: {} to load stored app state, returning default",
-                    path.as_ref().display(),
+                log::error!(
+                    "Failed to recover app state from backup {}: {}",
+                    latest.path.display(),
                     e
                 );
                 Self::default()
             }
         }
     }
+
+    /// Copy a state file to a backup named after the format version it was saved with
+    fn backup_version(path: &Path, version: u64) {
+        let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+        name.push(format!(".v{}", version));
+        let backup = PathBuf::from(path).with_file_name(name);
+        if let Err(e) = fs::copy(path, &backup) {
+            log::error!(
+                "Failed to back up app state {} to {}: {}",
+                path.display(),
+                backup.display(),
+                e
+            );
+        }
+    }
+
+    /// Open the recipe database from the configured storage without loading any recipes, or an
+    /// empty in-memory database if the storage can't be opened along with a message for the
+    /// error. Recipes are loaded with [loading::spawn]
+    fn open_recipes(storage: &StorageConfig) -> (Database, Option<Arc<str>>) {
+        match Database::open(storage) {
+            Ok(db) => (db, None),
+            Err(e) => {
+                log::error!(
+                    "Failed to open recipe storage {:?}: {}, changes will not be saved",
+                    storage,
+                    e
+                );
+                (
+                    Database::default(),
+                    Some(Arc::from(format!(
+                        "Failed to open recipe storage, changes will not be saved: {}",
+                        e
+                    ))),
+                )
+            }
+        }
+    }
+
+    /// Reload the list of recipes on the home screen after recipes were added or removed, keeping
+    /// the same number of recipes loaded
+    pub fn refresh_loaded(&mut self) {
+        let len = self.home.loaded.len().max(10);
+        let page = self.recipes.query(&self.home.query().limit(len));
+        self.home.loaded = page.ids.into_iter().collect();
+        self.home.next = page.next;
+    }
+
+    /// Reload the recipes shown in search results after recipes were changed, keeping the same
+    /// number of results loaded
+    pub fn refresh_search(&mut self) {
+        let recipes = &self.recipes;
+        if let Some(results) = self.search.results.as_mut() {
+            let len = results.loaded.len().max(10);
+            let page = recipes.query(&results.query.build().limit(len));
+            results.loaded = page
+                .ids
+                .into_iter()
+                .filter_map(|id| recipes.get(id))
+                .collect();
+            results.next = page.next;
+        }
+    }
+
+    /// Reload the list of recipes in the trash
+    pub fn refresh_trash(&mut self) {
+        self.trash = match self.recipes.trash() {
+            Ok(trash) => trash.into_iter().map(TrashItem::from).collect(),
+            Err(e) => {
+                log::error!("Failed to list recipes in the trash: {}", e);
+                Vector::new()
+            }
+        };
+    }
+
+    /// Show the statistics collected since recipes last changed, collecting them again on a
+    /// background thread if recipes changed
+    pub fn refresh_stats(&mut self, sink: &ExtEventSink) {
+        match self.recipes.collected_stats() {
+            Some(stats) => self.stats = stats,
+            None if !self.collecting_stats => {
+                self.collecting_stats = true;
+                let recipes = self.recipes.clone();
+                let sink = sink.clone();
+                std::thread::spawn(move || {
+                    recipes.stats();
+                    if let Err(e) = sink.submit_command(STATS_COLLECTED, (), Target::Global) {
+                        log::error!("Failed to send statistics to the main window: {}", e);
+                    }
+                });
+            }
+            None => (),
+        }
+    }
+
+    /// List the likely duplicate recipes found with the current threshold, comparing recipes
+    /// again on a background thread if the threshold or recipes changed since they were compared
+    pub fn refresh_duplicates(&mut self, sink: &ExtEventSink) {
+        let threshold = self.duplicates.threshold as f32;
+        match self.recipes.found_duplicates(threshold) {
+            Some(found) => self.duplicates.pairs = DuplicatePair::list(&self.recipes, &found),
+            None if !self.duplicates.searching => {
+                self.duplicates.searching = true;
+                duplicate::spawn(self.recipes.clone(), threshold, sink.clone());
+            }
+            None => (),
+        }
+    }
+
+    /// Back up every recipe and this state on a background thread, then replace every recipe
+    /// with the recipes in `restore` if a snapshot is given. Nothing is done if a backup or
+    /// restore is already running
+    pub fn backup(&mut self, restore: Option<Snapshot>, sink: ExtEventSink) {
+        if self.backups.working {
+            log::warn!("Not starting a backup while another backup or restore is running");
+            return;
+        }
+        let state = match serde_json::to_vec(&Versioned::state(&*self.saved())) {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("Failed to serialize app state to back up: {}", e);
+                self.backups.message = Some(Arc::from(format!("Failed to back up recipes: {}", e)));
+                return;
+            }
+        };
+
+        self.backups.working = true;
+        self.backups.message = Some(Arc::from(match restore {
+            Some(_) => "Restoring backup...",
+            None => "Backing up recipes...",
+        }));
+        backup::spawn(
+            self.recipes.clone(),
+            state,
+            self.config.backup_count.max(1) as usize,
+            restore,
+            sink,
+        );
+    }
+
+    /// Check if automatic backups are enabled and the last backup is older than the configured
+    /// interval
+    pub fn backup_due(&self) -> bool {
+        if self.config.backup_hours == 0 {
+            return false;
+        }
+        let interval = chrono::Duration::hours(self.config.backup_hours.into());
+        Snapshot::latest(&Paths::get().backup_dir())
+            .is_none_or(|latest| Utc::now() - latest.created >= interval)
+    }
+
+    /// Reload the list of backups in the backup directory
+    pub fn refresh_backups(&mut self) {
+        self.backups.snapshots = SnapshotItem::list(&Paths::get().backup_dir());
+    }
+
+    /// Get the part of this state that is saved. While recipes are encrypted, the edited and
+    /// deleted recipes are left out because the state is saved as plain text
+    fn saved(&self) -> Cow<'_, Self> {
+        if !self.config.encrypted && !self.recipes.is_encrypted() {
+            return Cow::Borrowed(self);
+        }
+
+        let mut saved = self.clone();
+        saved.edit = EditState::default();
+        saved.remove = None;
+        if matches!(saved.screen, AppScreen::Edit | AppScreen::Delete) {
+            saved.screen = AppScreen::Home;
+        }
+        Cow::Owned(saved)
+    }
+
+    /// Save all recipes and this state, replacing the file at `path` only once the new state is
+    /// completely written
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StorageError> {
+        let recipes = self.recipes.save();
+        let saved = self.saved();
+        let valid = |data: &[u8]| serde_json::from_slice::<serde_json::Value>(data).is_ok();
+        atomic::write(path.as_ref(), valid, |file| {
+            serde_json::to_writer(file, &Versioned::state(&*saved)).map_err(Into::into)
+        })?;
+        recipes
+    }
+
+    /// Save this state after recipes were encrypted and securely remove the copies of the state
+    /// file kept by older saves and upgrades, which can hold recipes as plain text
+    pub fn save_encrypted(&self, path: impl AsRef<Path>) -> Result<(), StorageError> {
+        let path = path.as_ref();
+        self.save(path)?;
+        atomic::remove_backup(path)?;
+
+        let versions = match path.file_name() {
+            Some(name) => format!("{}.v", name.to_string_lossy()),
+            None => return Ok(()),
+        };
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        for item in fs::read_dir(dir)?.flatten() {
+            let name = item.file_name().to_string_lossy().into_owned();
+            if name
+                .strip_prefix(&versions)
+                .is_some_and(|version| version.parse::<u64>().is_ok())
+            {
+                atomic::shred(&item.path())?;
+            }
+        }
+        Ok(())
+    }
 }
 
-impl ListIter<Recipe> for AppState {
-    fn for_each(&self, mut cb: impl FnMut(&Recipe, usize)) {
+impl ListIter<(Database, Arc<Recipe>)> for AppState {
+    fn for_each(&self, mut cb: impl FnMut(&(Database, Arc<Recipe>), usize)) {
         for (i, id) in self.home.loaded.iter().enumerate() {
             match self.recipes.get(*id) {
-                Some(recipe) => cb(recipe.deref(), i),
+                Some(recipe) => cb(&(self.recipes.clone(), recipe), i),
                 None => {
                     log::trace!("Loaded recipes contains recipe ID that does not exist");
                 }
@@ -92,13 +450,16 @@ impl ListIter<Recipe> for AppState {
         }
     }
 
-    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut Recipe, usize)) {
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut (Database, Arc<Recipe>), usize)) {
         for (i, id) in self.home.loaded.iter().enumerate() {
             match self.recipes.get(*id) {
-                Some(mut recipe) => {
-                    let recipe_ref = Arc::make_mut(&mut recipe);
-                    cb(recipe_ref, i);
-                    self.recipes.update(recipe);
+                Some(recipe) => {
+                    let mut item = (self.recipes.clone(), recipe.clone());
+                    cb(&mut item, i);
+                    if !item.1.same(&recipe) {
+                        self.save_status = SaveStatus::from_result(self.recipes.update(item.1));
+                        self.recipes.sync();
+                    }
                 }
                 None => {
                     log::trace!("Loaded recipes contains recipe ID that does not exist");
@@ -113,25 +474,39 @@ impl ListIter<Recipe> for AppState {
 }
 
 impl Default for AppState {
+    /// Create the default state with an empty in-memory database, the configured storage is only
+    /// opened by [init](AppState::init)
     fn default() -> Self {
-        let recipes = Database::new("recipes");
         Self {
             config: Config {
                 window_size: (480., 700.),
                 no_update_check: false,
+                units: UnitFormat::system(),
+                volume_system: VolumeSystem::default(),
+                storage: StorageConfig::default(),
+                trash_days: config::default_trash_days(),
+                backup_hours: config::default_backup_hours(),
+                backup_count: config::default_backup_count(),
+                encrypted: false,
             },
             search: SearchState::default(),
             screen: AppScreen::Home,
-            home: HomeState {
-                loaded: {
-                    let ids = recipes.ids();
-                    druid::im::Vector::from(&ids[0..ids.len().min(10)])
-                },
-            },
-            recipes,
+            home: HomeState::default(),
+            recipes: Database::default(),
             view: ViewState::default(),
             edit: EditState::default(),
             remove: Option::None,
+            save_status: SaveStatus::default(),
+            conflicts: Vector::new(),
+            trash: Vector::new(),
+            maintenance: MaintenanceState::default(),
+            backups: BackupState::default(),
+            passphrase: PassphraseState::default(),
+            loading: LoadingState::default(),
+            load_errors: Vector::new(),
+            stats: Arc::default(),
+            collecting_stats: false,
+            duplicates: DuplicateState::default(),
         }
     }
 }
@@ -0,0 +1,96 @@
+//! State for entering the passphrase that recipes are encrypted with
+
+use std::sync::Arc;
+
+use druid::{Data, ExtEventSink, Lens, Target};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    gui::{PASSPHRASE_CHANGED, UNLOCKED},
+    paths::Paths,
+    recipes::db::{Database, PlainCopies},
+};
+
+/// Passphrase entered on the unlock or maintenance screen
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct PassphraseState {
+    /// The entered passphrase
+    pub passphrase: String,
+    /// The passphrase entered again when choosing a new passphrase
+    pub confirm: String,
+    /// The result of the last attempt to unlock or encrypt recipes
+    pub status: Option<Arc<str>>,
+    /// If recipes are being unlocked, encrypted, or decrypted in the background
+    pub working: bool,
+}
+
+impl PassphraseState {
+    /// Overwrite the entered passphrases once they were used and show the result
+    pub fn clear(&mut self, status: Option<Arc<str>>) {
+        self.passphrase.zeroize();
+        self.confirm.zeroize();
+        self.status = status;
+    }
+
+    /// Take the entered passphrase to use it on another thread, overwriting the entered
+    /// passphrases and showing the given status until the work is done
+    pub fn take(&mut self, status: &str) -> Zeroizing<String> {
+        let passphrase = Zeroizing::new(self.passphrase.clone());
+        self.clear(Some(Arc::from(status)));
+        self.working = true;
+        passphrase
+    }
+}
+
+/// The result of encrypting or decrypting every recipe in the background, sent with
+/// [PASSPHRASE_CHANGED]
+#[derive(Debug)]
+pub struct PassphraseChanged {
+    /// If recipes were encrypted with a new passphrase instead of decrypted
+    pub encrypted: bool,
+    /// Copies of recipes that are still plain data, or the error that kept recipes from being
+    /// rewritten
+    pub result: Result<PlainCopies, Arc<str>>,
+}
+
+/// Decrypt and load recipes with a passphrase on a background thread, sending messages for
+/// recipes that could not be loaded or the error that kept recipes from being unlocked to the
+/// main window
+pub fn spawn_unlock(recipes: Database, passphrase: Zeroizing<String>, sink: ExtEventSink) {
+    std::thread::spawn(move || {
+        let result = match recipes.unlock(&passphrase) {
+            Ok(errors) => Ok(errors.iter().map(|e| Arc::from(e.to_string())).collect()),
+            Err(e) => {
+                log::warn!("Failed to unlock recipes: {}", e);
+                Err(Arc::from(e.to_string()))
+            }
+        };
+        if let Err(e) = sink.submit_command(UNLOCKED, result, Target::Global) {
+            log::error!("Failed to send unlocked recipes to the main window: {}", e);
+        }
+    });
+}
+
+/// Encrypt every saved recipe with a new passphrase, or decrypt them if no passphrase is given,
+/// on a background thread and send the result to the main window
+pub fn spawn_change(recipes: Database, passphrase: Option<Zeroizing<String>>, sink: ExtEventSink) {
+    std::thread::spawn(move || {
+        let encrypted = passphrase.is_some();
+        let result = recipes
+            .set_passphrase(
+                passphrase.as_deref().map(String::as_str),
+                &Paths::get().backup_dir(),
+            )
+            .map_err(|e| {
+                match encrypted {
+                    true => log::error!("Failed to encrypt recipes: {}", e),
+                    false => log::error!("Failed to decrypt recipes: {}", e),
+                }
+                Arc::from(e.to_string())
+            });
+        let changed = PassphraseChanged { encrypted, result };
+        if let Err(e) = sink.submit_command(PASSPHRASE_CHANGED, changed, Target::Global) {
+            log::error!("Failed to send encrypted recipes to the main window: {}", e);
+        }
+    });
+}
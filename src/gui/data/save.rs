@@ -0,0 +1,47 @@
+//! State describing if the application's data has been saved
+
+use std::{fmt, sync::Arc};
+
+use chrono::{DateTime, Local};
+use druid::Data;
+
+/// The result of the most recent attempt to save app state or recipe data
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SaveStatus {
+    /// Nothing has changed since the application started
+    #[default]
+    Unchanged,
+    /// There are changes that have not been saved yet
+    Pending,
+    /// All changes were saved at the given time
+    Saved(DateTime<Local>),
+    /// The last save failed with the given error message
+    Failed(Arc<str>),
+}
+
+impl SaveStatus {
+    /// Create a status from the result of a save operation
+    pub fn from_result<E: fmt::Display>(result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => Self::Saved(Local::now()),
+            Err(e) => Self::Failed(Arc::from(e.to_string())),
+        }
+    }
+}
+
+impl Data for SaveStatus {
+    fn same(&self, other: &Self) -> bool {
+        self.eq(other)
+    }
+}
+
+impl fmt::Display for SaveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unchanged => write!(f, "No unsaved changes"),
+            Self::Pending => write!(f, "Unsaved changes"),
+            Self::Saved(time) => write!(f, "Saved at {}", time.format("%I:%M:%S %p")),
+            Self::Failed(e) => write!(f, "Failed to save: {}", e),
+        }
+    }
+}
@@ -16,4 +16,20 @@ pub enum AppScreen {
     Edit,
     /// Screen displaying a simple are you sure prompt
     Delete,
+    /// Screen with actions to repair and rewrite the recipe database
+    Maintenance,
+    /// Screen showing recipes changed in two places side by side to choose which to keep
+    Conflicts,
+    /// Screen listing deleted recipes that can be restored or permanently deleted
+    Trash,
+    /// Screen listing backups that recipes can be restored from
+    Backups,
+    /// Screen showing statistics about every recipe
+    Stats,
+    /// Screen showing pairs of recipes that are likely copies of each other to merge them
+    Duplicates,
+    /// Screen asking for the passphrase that recipes are encrypted with
+    Unlock,
+    /// Screen showing progress while recipes are loaded at startup
+    Loading,
 }
@@ -1,10 +1,16 @@
 //! Search query and results state
 
-use std::sync::Arc;
+use std::{borrow::Borrow, sync::Arc, time::Duration};
 
-use druid::{im::OrdMap, widget::ListIter, Data, Lens};
+use druid::{im::Vector, Data, Lens};
 
-use crate::recipes::recipe::Recipe;
+use crate::recipes::{
+    db::query::{self, Cursor},
+    recipe::Recipe,
+};
+
+/// Longest time that a recipe can take to make to be shown when only quick recipes are searched
+const QUICK_TIME: Duration = Duration::from_secs(30 * 60);
 
 /// Search state containing optional list of search query results and search query details
 #[derive(Clone, Debug, Data, Lens)]
@@ -18,36 +24,13 @@ pub struct SearchState {
 /// The result of searching for a query
 #[derive(Clone, Debug, Data, Lens)]
 pub struct SearchResults {
-    /// List of recipes matching the query
-    pub recipes: OrdMap<isize, Arc<Recipe>>,
-    /// The number of recipes that should be loaded onscreen
-    pub loaded_recipes: usize,
-    /// The original search term
-    pub term: Arc<str>,
-}
-
-impl ListIter<Arc<Recipe>> for SearchResults {
-    fn for_each(&self, mut cb: impl FnMut(&Arc<Recipe>, usize)) {
-        for (idx, (_, i)) in self.recipes.iter().take(self.loaded_recipes).enumerate() {
-            (cb)(i, idx)
-        }
-    }
-
-    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut Arc<Recipe>, usize)) {
-        let mut recipes = self.recipes.clone();
-        for (idx, (score, i)) in self.recipes.iter().take(self.loaded_recipes).enumerate() {
-            let mut recipe = i.clone();
-            (cb)(&mut recipe, idx);
-            if !Arc::ptr_eq(&recipe, i) {
-                recipes = self.recipes.alter(|_| Some(recipe), *score);
-            }
-        }
-        self.recipes = recipes;
-    }
-
-    fn data_len(&self) -> usize {
-        self.recipes.len().min(self.loaded_recipes)
-    }
+    /// The recipes that are loaded onscreen
+    pub loaded: Vector<Arc<Recipe>>,
+    /// Where the next page of results starts, `None` if every result is loaded
+    #[data(same_fn = "PartialEq::eq")]
+    pub next: Option<Cursor>,
+    /// The original search query
+    pub query: Query,
 }
 
 impl Default for SearchState {
@@ -55,6 +38,7 @@ impl Default for SearchState {
         Self {
             query: Query {
                 term: String::new(),
+                quick: false,
             },
             results: None,
         }
@@ -66,4 +50,26 @@ impl Default for SearchState {
 pub struct Query {
     /// General search term
     pub term: String,
+    /// If only recipes that can be made quickly are shown
+    pub quick: bool,
+}
+
+impl Query {
+    /// Build a database query for recipes matching this search, with the best matches first.
+    /// Recipe bodies are only loaded for recipes with names that don't match
+    pub fn build(&self) -> query::Query {
+        let term = self.term.clone();
+        let query = query::Query::new().rank(
+            self.term.as_str(),
+            Arc::new(move |entry, load| {
+                sublime_fuzzy::best_match(entry.name.borrow(), &term)
+                    .or_else(|| sublime_fuzzy::best_match(load()?.body.borrow(), &term))
+                    .map(|score| score.score())
+            }),
+        );
+        match self.quick {
+            true => query.filter(|entry| entry.time.is_some_and(|time| time <= QUICK_TIME)),
+            false => query,
+        }
+    }
 }
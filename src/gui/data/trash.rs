@@ -0,0 +1,27 @@
+//! State for the recently deleted recipes screen
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use druid::{Data, Lens};
+
+use crate::recipes::{db::TrashedRecipe, recipe::Recipe};
+
+/// A recipe in the trash that can be restored or permanently deleted
+#[derive(Clone, Debug, Data, Lens)]
+pub struct TrashItem {
+    /// The deleted recipe
+    pub recipe: Arc<Recipe>,
+    /// When the recipe was deleted
+    #[data(same_fn = "PartialEq::eq")]
+    pub deleted_on: DateTime<Utc>,
+}
+
+impl From<TrashedRecipe> for TrashItem {
+    fn from(trashed: TrashedRecipe) -> Self {
+        Self {
+            recipe: trashed.recipe,
+            deleted_on: trashed.deleted_on,
+        }
+    }
+}
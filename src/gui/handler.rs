@@ -1,58 +1,62 @@
 //! Application command handler
 
-use std::{borrow::Borrow, ops::Deref, sync::Arc, time::Duration};
+use std::{ops::Deref, path::Path, sync::Arc, time::Duration};
 
 use chrono::Utc;
 use druid::{
-    commands::{CLOSE_WINDOW, OPEN_FILE, SHOW_WINDOW},
+    commands::{CLOSE_WINDOW, OPEN_FILE, SAVE_FILE_AS, SHOW_WINDOW},
     piet::TextStorage,
     widget::{Button, Flex, Label},
-    AppDelegate, Command, DelegateCtx, Env, Handled, ImageBuf, Target, WindowDesc,
+    AppDelegate, Command, DelegateCtx, Env, ExtEventSink, Handled, Target, WindowDesc,
 };
 
 use crate::{
-    gui::data::edit::EditState,
-    recipes::{db::RecipeId, recipe::Recipe},
-    SAVE_FILE,
+    gui::{data::edit::EditState, widgets::recipe_image::load_image},
+    paths::Paths,
+    recipes::{
+        db::{
+            backup::Snapshot,
+            images::{self, ImageSize, ImageState},
+            RecipeId,
+        },
+        jsonld,
+        recipe::Recipe,
+    },
 };
 
 use super::{
-    data::{remove::RemoveState, search::SearchResults, AppState},
-    CHANGE_INGREDIENT_UNIT, CHANGE_SCREEN, CREATE_RECIPE, EDIT_RECIPE, LOAD_MORE_RECIPES,
-    POPULATE_RESULTS, REMOVE_EDITED_INGREDIENT, REMOVE_RECIPE, SAVE_EDITED_RECIPE,
-    SHOW_UPDATE_DIALOG, VIEW_RECIPE,
+    data::{
+        backup::OpenedSnapshot, conflict::ConflictItem, maintenance, passphrase,
+        remove::RemoveState, save::SaveStatus, screen::AppScreen, search::SearchResults, AppState,
+    },
+    BACKUP_FINISHED, CHANGE_INGREDIENT_UNIT, CHANGE_SCREEN, CHECK_DATABASE, CREATE_BACKUP,
+    CREATE_RECIPE, DATABASE_CHECKED, DUPLICATES_FOUND, EDIT_RECIPE, EMPTY_TRASH, FIND_DUPLICATES,
+    IMAGE_LOADED, LOAD_MORE_RECIPES, LOAD_MORE_RESULTS, LOAD_PROGRESS, MERGE_DUPLICATES,
+    OPEN_BACKUP, OPEN_PATH, PASSPHRASE_CHANGED, POPULATE_RESULTS, PURGE_RECIPE, RECIPES_CHANGED,
+    RECIPES_LOADED, REMOVE_EDITED_INGREDIENT, REMOVE_PASSPHRASE, REMOVE_RECIPE, RESOLVE_CONFLICT,
+    RESTORE_BACKUP, RESTORE_BACKUP_RECIPE, RESTORE_RECIPE, REWRITE_ALL_RECIPES, SAVE_EDITED_RECIPE,
+    SET_PASSPHRASE, SET_SAVE_STATUS, SHOW_UPDATE_DIALOG, SORT_RECIPES, STATS_COLLECTED, UNLOCK,
+    UNLOCKED, VIEW_RECIPE,
 };
 
 /// Structure that handles top-level events and commands in the application
-pub struct RecipierDelegate;
+pub struct RecipierDelegate {
+    /// Handle used to send commands from background threads
+    sink: ExtEventSink,
+}
 
-impl AppDelegate<AppState> for RecipierDelegate {
-    fn window_removed(
-        &mut self,
-        _id: druid::WindowId,
-        data: &mut AppState,
-        _env: &Env,
-        _ctx: &mut druid::DelegateCtx,
-    ) {
-        match std::fs::File::create(SAVE_FILE) {
-            Ok(file) => {
-                if let Err(e) = serde_json::to_writer(file, &data) {
-                    log::error!("Failed to serialize app state: {}", e);
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to open save file: {}", e);
-            }
-        }
+impl RecipierDelegate {
+    /// Create a new delegate that sends commands from background threads with the given handle
+    pub fn new(sink: ExtEventSink) -> Self {
+        Self { sink }
     }
 
-    fn command(
+    /// Handle a command sent to the delegate, changing app state
+    fn handle_command(
         &mut self,
         ctx: &mut DelegateCtx,
-        _target: Target,
         cmd: &Command,
         data: &mut AppState,
-        _env: &Env,
     ) -> Handled {
         if let Some((channel, version)) = cmd.get(SHOW_UPDATE_DIALOG) {
             use druid::WidgetExt;
@@ -115,32 +119,49 @@ impl AppDelegate<AppState> for RecipierDelegate {
             }
 
             data.screen = *screen;
+            match data.screen {
+                AppScreen::Trash => data.refresh_trash(),
+                AppScreen::Backups => data.refresh_backups(),
+                AppScreen::Stats => data.refresh_stats(&self.sink),
+                AppScreen::Duplicates => data.refresh_duplicates(&self.sink),
+                _ => (),
+            }
 
             Handled::Yes
         } else if let Some(()) = cmd.get(POPULATE_RESULTS) {
             log::trace!("Populating search results for query");
 
+            let query = data.search.query.clone();
+            let page = data.recipes.query(&query.build());
+            let loaded = page
+                .ids
+                .into_iter()
+                .filter_map(|id| data.recipes.get(id))
+                .collect();
             data.search.results = Some(SearchResults {
-                recipes: data.recipes.search(|recipe| {
-                    if let Some(score) = sublime_fuzzy::best_match(
-                        recipe.name.borrow(),
-                        data.search.query.term.as_str(),
-                    )
-                    .or_else(|| {
-                        sublime_fuzzy::best_match(
-                            recipe.body.borrow(),
-                            data.search.query.term.as_str(),
-                        )
-                    }) {
-                        score.score()
-                    } else {
-                        isize::MIN
-                    }
-                }),
-                loaded_recipes: 10,
-                term: Arc::from(data.search.query.term.as_str()),
+                loaded,
+                next: page.next,
+                query,
             });
             Handled::Yes
+        } else if let Some(()) = cmd.get(LOAD_MORE_RESULTS) {
+            log::trace!("Loading more search results...");
+
+            let recipes = &data.recipes;
+            let results = data
+                .search
+                .results
+                .as_mut()
+                .filter(|results| results.next.is_some());
+            if let Some(results) = results {
+                let query = results.query.build().after(results.next.clone());
+                let page = recipes.query(&query);
+                results
+                    .loaded
+                    .extend(page.ids.into_iter().filter_map(|id| recipes.get(id)));
+                results.next = page.next;
+            }
+            Handled::Yes
         } else if let Some(recipe) = cmd.get(VIEW_RECIPE) {
             log::trace!("Viewing recipe {}...", recipe);
 
@@ -149,15 +170,32 @@ impl AppDelegate<AppState> for RecipierDelegate {
         } else if let Some(()) = cmd.get(LOAD_MORE_RECIPES) {
             log::trace!("Loading more recipe results...");
 
-            let ids = data.recipes.ids();
-            data.home.loaded =
-                druid::im::Vector::from(&ids[0..ids.len().min(data.home.loaded.len() + 10)]);
+            if let Some(next) = data.home.next.clone() {
+                let page = data.recipes.query(&data.home.query().after(Some(next)));
+                data.home.loaded.extend(page.ids);
+                data.home.next = page.next;
+            }
+            Handled::Yes
+        } else if let Some((sort, descending)) = cmd.get(SORT_RECIPES) {
+            log::trace!("Sorting recipes by {}...", sort);
+
+            data.home.sort = *sort;
+            data.home.descending = *descending;
+            data.home.loaded.clear();
+            data.refresh_loaded();
             Handled::Yes
         } else if let Some((id, return_to)) = cmd.get(EDIT_RECIPE) {
             log::trace!("Populating edit data with recipe {}", id);
             data.edit.return_to = *return_to;
             match data.recipes.get(*id) {
-                Some(recipe) => data.edit = EditState::from_recipe(&data.recipes, recipe.deref()),
+                Some(recipe) => {
+                    data.edit = EditState::from_recipe(recipe.deref());
+                    match load_image(&data.recipes, *id, ImageSize::Full, self.sink.clone()) {
+                        ImageState::Ready(buf) => data.edit.image = Some(buf),
+                        ImageState::Missing => (),
+                        ImageState::Loading => data.edit.image_loading = true,
+                    }
+                }
                 None => log::warn!(
                     "Edit recipe command received with ID {} that does not exist",
                     id
@@ -195,10 +233,16 @@ impl AppDelegate<AppState> for RecipierDelegate {
             Handled::Yes
         } else if let Some(()) = cmd.get(SAVE_EDITED_RECIPE) {
             let recipe_id = data.edit.id.unwrap_or_else(RecipeId::new);
+            //Edited recipes keep the time they were first created
+            let created_on = data
+                .edit
+                .id
+                .and_then(|id| data.recipes.get(id))
+                .map_or_else(Utc::now, |recipe| recipe.created_on);
 
             let recipe = Recipe {
                 name: Arc::from(data.edit.title.as_str()),
-                created_on: Utc::now(),
+                created_on,
                 ingredients: data
                     .edit
                     .ingredients
@@ -216,43 +260,330 @@ impl AppDelegate<AppState> for RecipierDelegate {
                 }),
                 id: recipe_id,
             };
-            data.recipes.insert(recipe);
+            let mut saved = data.recipes.insert(recipe);
 
-            if let Some(ref img) = data.edit.image {
-                data.recipes.set_image(recipe_id, img.clone())
+            //Images are encoded lossily, so they are only written again when they changed
+            if data.edit.image_changed {
+                let written = match data.edit.image {
+                    Some(ref img) => data.recipes.set_image(recipe_id, img.clone()),
+                    None => data.recipes.delete_image(recipe_id),
+                };
+                data.edit.image_changed = written.is_err();
+                saved = saved.and(written);
             }
+            data.save_status = SaveStatus::from_result(saved);
 
             Handled::Yes
-        } else if let Some(info) = cmd.get(OPEN_FILE) {
-            use druid::image;
-            match image::open(info.path()) {
-                Ok(img) => {
-                    let img = img.to_rgba8();
-                    let width = img.width() as usize;
-                    let height = img.height() as usize;
-                    let buf = ImageBuf::from_raw(
-                        img.into_raw(),
-                        druid::piet::ImageFormat::RgbaSeparate,
-                        width,
-                        height,
-                    );
-
-                    data.edit.image = Some(buf);
+        } else if let Some(()) = cmd.get(REWRITE_ALL_RECIPES) {
+            data.save_status = SaveStatus::from_result(data.recipes.rewrite_all());
+            Handled::Yes
+        } else if let Some(repair) = cmd.get(CHECK_DATABASE) {
+            if !data.maintenance.checking {
+                data.maintenance.checking = true;
+                data.maintenance.summary = Some(Arc::from(match repair {
+                    true => "Checking and repairing recipes...",
+                    false => "Checking recipes...",
+                }));
+                data.maintenance.findings.clear();
+                maintenance::spawn_check(data.recipes.clone(), *repair, self.sink.clone());
+            }
+            Handled::Yes
+        } else if let Some(findings) = cmd.get(DATABASE_CHECKED) {
+            data.maintenance.checking = false;
+            let repaired = findings
+                .iter()
+                .filter(|finding| finding.repair.is_repaired())
+                .count();
+            data.maintenance.summary = Some(Arc::from(match findings.len() {
+                0 => "No problems found".to_owned(),
+                len => format!("{} problems found, {} repaired", len, repaired),
+            }));
+            data.maintenance.findings = findings
+                .iter()
+                .map(|finding| Arc::from(finding.to_string()))
+                .collect();
+            data.conflicts = ConflictItem::list(&data.recipes);
+            data.refresh_loaded();
+            Handled::Yes
+        } else if let Some(ids) = cmd.get(RECIPES_CHANGED) {
+            let conflicts = data.recipes.reload(ids);
+            if conflicts > 0 {
+                log::warn!("{} recipes changed on disk have unsaved changes", conflicts);
+            }
+            data.conflicts = ConflictItem::list(&data.recipes);
+            data.refresh_loaded();
+            Handled::Yes
+        } else if let Some((id, resolution)) = cmd.get(RESOLVE_CONFLICT) {
+            data.save_status = SaveStatus::from_result(data.recipes.resolve(*id, *resolution));
+            data.conflicts = ConflictItem::list(&data.recipes);
+            data.refresh_loaded();
+            if data.conflicts.is_empty() && data.screen == AppScreen::Conflicts {
+                data.screen = AppScreen::Home;
+            }
+            Handled::Yes
+        } else if let Some(()) = cmd.get(FIND_DUPLICATES) {
+            data.refresh_duplicates(&self.sink);
+            Handled::Yes
+        } else if let Some(()) = cmd.get(STATS_COLLECTED) {
+            data.collecting_stats = false;
+            if data.screen == AppScreen::Stats {
+                data.refresh_stats(&self.sink);
+            }
+            Handled::Yes
+        } else if let Some(()) = cmd.get(DUPLICATES_FOUND) {
+            data.duplicates.searching = false;
+            if data.screen == AppScreen::Duplicates {
+                data.refresh_duplicates(&self.sink);
+            }
+            Handled::Yes
+        } else if let Some((keep, other)) = cmd.get(MERGE_DUPLICATES) {
+            log::trace!("Merging recipe {} into {}", other, keep);
+
+            //Duplicates are found again once the change to the database is synced
+            data.save_status = SaveStatus::from_result(data.recipes.merge(*keep, *other));
+            Handled::Yes
+        } else if let Some(id) = cmd.get(RESTORE_RECIPE) {
+            data.save_status = SaveStatus::from_result(data.recipes.restore(*id));
+            data.refresh_trash();
+            data.refresh_loaded();
+            Handled::Yes
+        } else if let Some(id) = cmd.get(PURGE_RECIPE) {
+            data.save_status = SaveStatus::from_result(data.recipes.purge(*id));
+            data.refresh_trash();
+            Handled::Yes
+        } else if let Some(()) = cmd.get(EMPTY_TRASH) {
+            let purged = data
+                .trash
+                .iter()
+                .map(|item| data.recipes.purge(item.recipe.id))
+                .fold(Ok(()), Result::and);
+            data.save_status = SaveStatus::from_result(purged);
+            data.refresh_trash();
+            Handled::Yes
+        } else if let Some(()) = cmd.get(CREATE_BACKUP) {
+            data.backup(None, self.sink.clone());
+            Handled::Yes
+        } else if let Some(message) = cmd.get(BACKUP_FINISHED) {
+            data.backups.working = false;
+            data.backups.message = Some(message.clone());
+            data.refresh_loaded();
+            data.refresh_backups();
+            Handled::Yes
+        } else if let Some(path) = cmd.get(OPEN_BACKUP) {
+            let opened = Snapshot::from_path(path.to_path_buf())
+                .map(|snapshot| OpenedSnapshot::open(&data.recipes, &snapshot));
+            match opened {
+                Some(Ok(opened)) => data.backups.opened = Some(opened),
+                Some(Err(e)) => {
+                    log::error!("Failed to open backup {}: {}", path.display(), e);
+                    data.backups.message = Some(Arc::from(format!("Failed to open backup: {}", e)));
                 }
+                None => log::warn!(
+                    "Open backup command received with invalid path {}",
+                    path.display()
+                ),
+            }
+            Handled::Yes
+        } else if let Some(path) = cmd.get(RESTORE_BACKUP) {
+            match Snapshot::from_path(path.to_path_buf()) {
+                Some(snapshot) => data.backup(Some(snapshot), self.sink.clone()),
+                None => log::warn!(
+                    "Restore backup command received with invalid path {}",
+                    path.display()
+                ),
+            }
+            Handled::Yes
+        } else if let Some((path, id)) = cmd.get(RESTORE_BACKUP_RECIPE) {
+            let restored = match Snapshot::from_path(path.to_path_buf()) {
+                Some(snapshot) => data.recipes.restore_backup(&snapshot, *id),
+                None => Ok(()),
+            };
+            data.backups.message = Some(Arc::from(match restored {
+                Ok(()) => match data.recipes.get(*id) {
+                    Some(recipe) => format!("Restored {}", recipe.name),
+                    None => "Restored recipe".to_owned(),
+                },
                 Err(e) => {
-                    log::trace!(
-                        "File {} can not be used as an image: {}",
-                        info.path().display(),
-                        e
-                    );
+                    log::error!("Failed to restore recipe {} from backup: {}", id, e);
+                    format!("Failed to restore recipe: {}", e)
+                }
+            }));
+            data.refresh_loaded();
+            Handled::Yes
+        } else if let Some(()) = cmd.get(UNLOCK) {
+            if !data.passphrase.working {
+                let passphrase = data.passphrase.take("Unlocking recipes...");
+                passphrase::spawn_unlock(data.recipes.clone(), passphrase, self.sink.clone());
+            }
+            Handled::Yes
+        } else if let Some(result) = cmd.get(UNLOCKED) {
+            data.passphrase.working = false;
+            match result {
+                Ok(errors) => {
+                    data.load_errors.extend(errors.iter().cloned());
+                    data.passphrase.status = None;
+                    data.config.encrypted = true;
+                    data.refresh_loaded();
+                    data.screen = AppScreen::Home;
+                    open_pending(data);
+                }
+                Err(e) => data.passphrase.status = Some(e.clone()),
+            }
+            Handled::Yes
+        } else if let Some(()) = cmd.get(SET_PASSPHRASE) {
+            let passphrase = &data.passphrase;
+            if passphrase.working {
+                return Handled::Yes;
+            }
+            if passphrase.passphrase.is_empty() {
+                data.passphrase.clear(Some(Arc::from(
+                    "Enter a passphrase to encrypt recipes with",
+                )));
+            } else if passphrase.passphrase != passphrase.confirm {
+                data.passphrase
+                    .clear(Some(Arc::from("The passphrases don't match")));
+            } else {
+                let passphrase = data.passphrase.take("Encrypting recipes...");
+                passphrase::spawn_change(data.recipes.clone(), Some(passphrase), self.sink.clone());
+            }
+            Handled::Yes
+        } else if let Some(()) = cmd.get(REMOVE_PASSPHRASE) {
+            if !data.passphrase.working {
+                data.passphrase.take("Decrypting recipes...");
+                passphrase::spawn_change(data.recipes.clone(), None, self.sink.clone());
+            }
+            Handled::Yes
+        } else if let Some(changed) = cmd.get(PASSPHRASE_CHANGED) {
+            data.passphrase.working = false;
+            let status = match (&changed.result, changed.encrypted) {
+                (Ok(plain), true) => {
+                    data.config.encrypted = true;
+                    let save_file = Paths::get().save_file();
+                    let status = match data.save_encrypted(&save_file) {
+                        Ok(()) => "Recipes are encrypted with the new passphrase".to_owned(),
+                        Err(e) => {
+                            log::error!(
+                                "Failed to remove plain copies of {}: {}",
+                                save_file.display(),
+                                e
+                            );
+                            format!(
+                                "Recipes are encrypted, but old copies of the settings could not be removed: {}",
+                                e
+                            )
+                        }
+                    };
+                    if plain.is_empty() {
+                        status
+                    } else {
+                        format!("{}. {}", status, plain)
+                    }
+                }
+                (Ok(_), false) => {
+                    data.config.encrypted = false;
+                    "Recipes are saved as plain files".to_owned()
+                }
+                (Err(e), true) => format!("Failed to encrypt recipes: {}", e),
+                (Err(e), false) => format!("Failed to decrypt recipes: {}", e),
+            };
+            data.passphrase.status = Some(Arc::from(status));
+            Handled::Yes
+        } else if let Some(status) = cmd.get(SET_SAVE_STATUS) {
+            data.save_status = status.clone();
+            Handled::Yes
+        } else if let Some(info) = cmd.get(OPEN_FILE) {
+            open_file(data, info.path());
+            Handled::Yes
+        } else if let Some(info) = cmd.get(SAVE_FILE_AS) {
+            if let Some(recipe) = data.view.viewed.and_then(|id| data.recipes.get(id)) {
+                log::trace!(
+                    "Exporting recipe {} to {}",
+                    recipe.id,
+                    info.path().display()
+                );
+                data.save_status = SaveStatus::from_result(jsonld::export(&recipe, info.path()));
+            }
+            Handled::Yes
+        } else if let Some(path) = cmd.get(OPEN_PATH) {
+            match data.screen {
+                AppScreen::Loading | AppScreen::Unlock => {
+                    data.loading.pending.push_back(Arc::from(path.as_path()))
                 }
+                _ => open_file(data, path),
+            }
+            Handled::Yes
+        } else if let Some(&(loaded, total)) = cmd.get(LOAD_PROGRESS) {
+            data.loading.loaded = loaded;
+            data.loading.total = total;
+            Handled::Yes
+        } else if let Some(result) = cmd.get(RECIPES_LOADED) {
+            match result {
+                Ok(errors) => data.load_errors.extend(errors.iter().cloned()),
+                //Keep the database opened from the configured storage so that changes made
+                //after this are still saved to it
+                Err(e) => data.load_errors.push_back(e.clone()),
             }
 
+            if data.config.encrypted {
+                data.recipes.require_passphrase();
+            }
+            data.refresh_loaded();
+            match data.recipes.is_locked() {
+                true => data.screen = AppScreen::Unlock,
+                false => {
+                    data.screen = data.loading.return_to;
+                    open_pending(data);
+                }
+            }
             Handled::Yes
+        } else if let Some((id, size)) = cmd.get(IMAGE_LOADED) {
+            if *size == ImageSize::Full && data.edit.image_loading && data.edit.id == Some(*id) {
+                data.edit.image_loading = false;
+                data.edit.image = data.recipes.get_image(*id);
+            }
+            //Image widgets showing the recipe also handle this command
+            Handled::No
         } else {
             Handled::No
         }
     }
+}
+
+impl AppDelegate<AppState> for RecipierDelegate {
+    fn window_removed(
+        &mut self,
+        _id: druid::WindowId,
+        data: &mut AppState,
+        _env: &Env,
+        _ctx: &mut druid::DelegateCtx,
+    ) {
+        let save_file = Paths::get().save_file();
+        if let Err(e) = data.save(&save_file) {
+            log::error!("Failed to save app state to {}: {}", save_file.display(), e);
+        }
+    }
+
+    fn command(
+        &mut self,
+        ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        data: &mut AppState,
+        _env: &Env,
+    ) -> Handled {
+        let handled = self.handle_command(ctx, cmd, data);
+        //Show changes made to recipes by the command or by background threads on every screen
+        if data.recipes.sync() {
+            data.refresh_loaded();
+            data.refresh_search();
+            match data.screen {
+                AppScreen::Stats => data.refresh_stats(&self.sink),
+                AppScreen::Duplicates => data.refresh_duplicates(&self.sink),
+                _ => (),
+            }
+        }
+        handled
+    }
 
     fn event(
         &mut self,
@@ -268,3 +599,82 @@ impl AppDelegate<AppState> for RecipierDelegate {
         Some(event)
     }
 }
+
+/// Open the files that were opened while recipes were loading or locked
+fn open_pending(data: &mut AppState) {
+    for path in std::mem::take(&mut data.loading.pending) {
+        open_file(data, &path);
+    }
+}
+
+/// Open a file chosen in a file dialog or given on the command line, importing the recipes in
+/// it if it is a cookbook directory or a JSON-LD recipe
+fn open_file(data: &mut AppState, path: &Path) {
+    if path.is_dir() {
+        import_cookbook(data, path);
+        return;
+    }
+    if jsonld::is_json_ld(path) {
+        import_recipe(data, path);
+        return;
+    }
+
+    match images::import(path) {
+        Ok(buf) => {
+            data.edit.image = Some(buf);
+            data.edit.image_loading = false;
+            data.edit.image_changed = true;
+        }
+        Err(e) => {
+            log::trace!("File {} can not be used as an image: {}", path.display(), e);
+        }
+    }
+}
+
+/// Add the recipe in a schema.org JSON-LD file and view it, showing the maintenance screen with
+/// the error if the file can't be imported
+fn import_recipe(data: &mut AppState, path: &Path) {
+    let imported = jsonld::import(path)
+        .map_err(|e| e.to_string())
+        .and_then(|recipe| {
+            let id = recipe.id;
+            data.recipes
+                .insert(recipe)
+                .map(|()| id)
+                .map_err(|e| e.to_string())
+        });
+
+    match imported {
+        Ok(id) => {
+            data.refresh_loaded();
+            data.view.viewed = Some(id);
+            data.screen = AppScreen::View;
+        }
+        Err(e) => {
+            log::error!("Failed to import recipe from {}: {}", path.display(), e);
+            data.maintenance.summary = Some(Arc::from(format!("Failed to import recipe: {}", e)));
+            data.maintenance.findings.clear();
+            data.screen = AppScreen::Maintenance;
+        }
+    }
+}
+
+/// Merge the recipes in another cookbook directory into ours, showing the conflict resolution
+/// screen if any imported recipes differ from ours
+fn import_cookbook(data: &mut AppState, path: &Path) {
+    let summary = match data.recipes.import(path) {
+        Ok(summary) => summary.to_string(),
+        Err(e) => {
+            log::error!("Failed to import recipes from {}: {}", path.display(), e);
+            format!("Failed to import recipes: {}", e)
+        }
+    };
+    data.maintenance.summary = Some(Arc::from(summary));
+    data.maintenance.findings.clear();
+    data.conflicts = ConflictItem::list(&data.recipes);
+    data.refresh_loaded();
+    data.screen = match data.conflicts.is_empty() {
+        true => AppScreen::Maintenance,
+        false => AppScreen::Conflicts,
+    };
+}
@@ -5,16 +5,25 @@ pub mod theme;
 pub mod ui;
 pub mod widgets;
 
-use std::sync::mpsc::Sender;
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+};
 
-use crate::recipes::{db::RecipeId, measure::AmountUnit};
+use crate::recipes::{
+    db::{check::Finding, conflict::Resolution, images::ImageSize, query::SortKey, RecipeId},
+    measure::AmountUnit,
+};
 
-use self::data::screen::AppScreen;
-use druid::Selector;
+use self::data::{passphrase::PassphraseChanged, save::SaveStatus, screen::AppScreen};
+use druid::{FileSpec, Selector};
 use semver::Version;
 pub use ui::root_widget;
 use uuid::Uuid;
 
+/// Files that recipes are imported from and exported to as schema.org JSON-LD
+pub const JSON_LD: FileSpec = FileSpec::new("JSON-LD Recipe", &["json", "jsonld"]);
+
 /// The visually-appealing golden ratio
 pub const GOLDEN_RATIO: f64 = 1.61803;
 
@@ -34,6 +43,25 @@ pub const VIEW_RECIPE: Selector<RecipeId> = Selector::new("recipier.view-recipe"
 /// Load more recipes into the recipes home screen
 pub const LOAD_MORE_RECIPES: Selector = Selector::new("recipeier.load-more-recipes");
 
+/// Load the next page of search results into the search screen
+pub const LOAD_MORE_RESULTS: Selector = Selector::new("recipier.load-more-results");
+
+/// Find likely duplicate recipes with the threshold set on the duplicates screen
+pub const FIND_DUPLICATES: Selector = Selector::new("recipier.find-duplicates");
+
+/// Statistics about every recipe were collected in the background and can be shown
+pub const STATS_COLLECTED: Selector = Selector::new("recipier.stats-collected");
+
+/// Likely duplicate recipes were found in the background and can be listed
+pub const DUPLICATES_FOUND: Selector = Selector::new("recipier.duplicates-found");
+
+/// Merge the second recipe into the first recipe and move the second recipe to the trash
+pub const MERGE_DUPLICATES: Selector<(RecipeId, RecipeId)> =
+    Selector::new("recipier.merge-duplicates");
+
+/// Sort the recipes on the home screen by the given key, in reverse if the flag is set
+pub const SORT_RECIPES: Selector<(SortKey, bool)> = Selector::new("recipier.sort-recipes");
+
 /// Copy the given recipe by ID to the edit state data and return to the given screen when editing is finished
 pub const EDIT_RECIPE: Selector<(RecipeId, AppScreen)> = Selector::new("recipier.edit-recipe");
 
@@ -54,3 +82,83 @@ pub const REMOVE_EDITED_INGREDIENT: Selector<Uuid> =
 
 /// Save the currently edited recipe, either updating the original recipe or creating a new one
 pub const SAVE_EDITED_RECIPE: Selector = Selector::new("recipier.save-edited-recipe");
+
+/// Set the displayed status of the last save
+pub const SET_SAVE_STATUS: Selector<SaveStatus> = Selector::new("recipier.set-save-status");
+
+/// Write every recipe to storage, even recipes that have not changed
+pub const REWRITE_ALL_RECIPES: Selector = Selector::new("recipier.rewrite-all-recipes");
+
+/// Check the recipe database for problems, repairing them if the payload is true
+pub const CHECK_DATABASE: Selector<bool> = Selector::new("recipier.check-database");
+
+/// An integrity check started by [CHECK_DATABASE] finished in the background, with every problem
+/// that was found
+pub const DATABASE_CHECKED: Selector<Vec<Finding>> = Selector::new("recipier.database-checked");
+
+/// Reload the given recipes after they were changed in storage by another program
+pub const RECIPES_CHANGED: Selector<Vec<RecipeId>> = Selector::new("recipier.recipes-changed");
+
+/// Resolve the conflict for the given recipe by keeping one or both copies
+pub const RESOLVE_CONFLICT: Selector<(RecipeId, Resolution)> =
+    Selector::new("recipier.resolve-conflict");
+
+/// Open a file given on the command line or forwarded from another instance
+pub const OPEN_PATH: Selector<PathBuf> = Selector::new("recipier.open-path");
+
+/// Move the recipe with the given ID out of the trash
+pub const RESTORE_RECIPE: Selector<RecipeId> = Selector::new("recipier.restore-recipe");
+
+/// Permanently delete the recipe in the trash with the given ID
+pub const PURGE_RECIPE: Selector<RecipeId> = Selector::new("recipier.purge-recipe");
+
+/// Permanently delete every recipe in the trash
+pub const EMPTY_TRASH: Selector = Selector::new("recipier.empty-trash");
+
+/// Back up every recipe and the app state now, deleting the oldest backups over the limit
+pub const CREATE_BACKUP: Selector = Selector::new("recipier.create-backup");
+
+/// List the recipes in the backup at the given path so they can be restored one at a time
+pub const OPEN_BACKUP: Selector<Arc<Path>> = Selector::new("recipier.open-backup");
+
+/// Replace every recipe with the recipes in the backup at the given path
+pub const RESTORE_BACKUP: Selector<Arc<Path>> = Selector::new("recipier.restore-backup");
+
+/// Restore one recipe from the backup at the given path
+pub const RESTORE_BACKUP_RECIPE: Selector<(Arc<Path>, RecipeId)> =
+    Selector::new("recipier.restore-backup-recipe");
+
+/// A backup or restore started by [CREATE_BACKUP] or [RESTORE_BACKUP] finished in the background,
+/// with a message describing the result
+pub const BACKUP_FINISHED: Selector<Arc<str>> = Selector::new("recipier.backup-finished");
+
+/// Decrypt and load recipes with the passphrase entered on the unlock screen
+pub const UNLOCK: Selector = Selector::new("recipier.unlock");
+
+/// Encrypt all recipes with the passphrase entered on the maintenance screen
+pub const SET_PASSPHRASE: Selector = Selector::new("recipier.set-passphrase");
+
+/// Decrypt all recipes back to plain files
+pub const REMOVE_PASSPHRASE: Selector = Selector::new("recipier.remove-passphrase");
+
+/// Recipes were unlocked in the background after [UNLOCK], with messages for recipes that could
+/// not be loaded or the error that kept recipes from being unlocked
+pub const UNLOCKED: Selector<Result<Vec<Arc<str>>, Arc<str>>> = Selector::new("recipier.unlocked");
+
+/// Recipes were encrypted or decrypted in the background after [SET_PASSPHRASE] or
+/// [REMOVE_PASSPHRASE]
+pub const PASSPHRASE_CHANGED: Selector<PassphraseChanged> =
+    Selector::new("recipier.passphrase-changed");
+
+/// Number of recipes loaded so far and number of recipes to load, sent while recipes are loaded
+/// in the background
+pub const LOAD_PROGRESS: Selector<(usize, usize)> = Selector::new("recipier.load-progress");
+
+/// Recipes finished loading in the background, with messages for recipes that could not be
+/// loaded or the error that kept any recipes from being loaded
+pub const RECIPES_LOADED: Selector<Result<Vec<Arc<str>>, Arc<str>>> =
+    Selector::new("recipier.recipes-loaded");
+
+/// A recipe's image or thumbnail finished decoding in the background and is cached, see
+/// [widgets::recipe_image]
+pub const IMAGE_LOADED: Selector<(RecipeId, ImageSize)> = Selector::new("recipier.image-loaded");
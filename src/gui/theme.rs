@@ -2,6 +2,11 @@
 
 use druid::{theme::*, Color, Env, FontDescriptor, FontFamily, FontStyle, FontWeight, Key};
 
+use crate::recipes::{
+    format::{DecimalSeparator, UnitFormat, UnitStyle},
+    measure::VolumeSystem,
+};
+
 /// Font used for large and bold text like central titles
 pub const HEADER_FONT: Key<FontDescriptor> = Key::new("recipier.header-font");
 /// Larger and bold font for sub headers
@@ -16,8 +21,50 @@ pub const COLOR_2: Key<Color> = Key::new("recipier.color2");
 pub const COLOR_3: Key<Color> = Key::new("recipier.color3");
 pub const COLOR_4: Key<Color> = Key::new("recipier.color4");
 
+/// If measurements are displayed with abbreviated unit names
+pub const SHORT_UNITS: Key<bool> = Key::new("recipier.short-units");
+/// If numbers are displayed with a decimal comma instead of a decimal point
+pub const DECIMAL_COMMA: Key<bool> = Key::new("recipier.decimal-comma");
+/// Position in [VolumeSystem::ALL] of the system that volume units without a system are measured in
+pub const VOLUME_SYSTEM: Key<u64> = Key::new("recipier.volume-system");
+
 pub const SPACING: f64 = 5.;
 
+/// Set the measurement keys from the given unit format and volume system
+pub fn set_units(env: &mut Env, format: &UnitFormat, system: VolumeSystem) {
+    env.set(SHORT_UNITS, format.style == UnitStyle::Short);
+    env.set(DECIMAL_COMMA, format.separator == DecimalSeparator::Comma);
+    env.set(
+        VOLUME_SYSTEM,
+        VolumeSystem::ALL
+            .iter()
+            .position(|other| *other == system)
+            .unwrap_or(0) as u64,
+    );
+}
+
+/// Get the unit format to display measurements with from the environment
+pub fn unit_format(env: &Env) -> UnitFormat {
+    UnitFormat {
+        style: match env.get(SHORT_UNITS) {
+            true => UnitStyle::Short,
+            false => UnitStyle::Long,
+        },
+        separator: match env.get(DECIMAL_COMMA) {
+            true => DecimalSeparator::Comma,
+            false => DecimalSeparator::Point,
+        },
+    }
+}
+
+/// Get the system to measure volume units without a system in from the environment
+pub fn volume_system(env: &Env) -> VolumeSystem {
+    VolumeSystem::ALL
+        .get(env.get(VOLUME_SYSTEM) as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
 /// Set environment keys to their appropriate values
 pub fn set(env: &mut Env) {
     env.set(
@@ -84,4 +131,6 @@ pub fn set(env: &mut Env) {
     env.set(WINDOW_BACKGROUND_COLOR, env.get(COLOR_1));
 
     env.set(CURSOR_COLOR, env.get(COLOR_4));
+
+    set_units(env, &UnitFormat::default(), VolumeSystem::default());
 }
@@ -0,0 +1,181 @@
+//! Backups screen listing snapshots that the whole cookbook or single recipes can be restored
+//! from
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, Utc};
+use druid::{
+    text::format::ParseFormatter,
+    widget::{Button, CrossAxisAlignment, Flex, Label, LineBreaking, List, Scroll, TextBox},
+    Lens, LensExt, TextAlignment, Widget, WidgetExt,
+};
+
+use crate::gui::{
+    data::{
+        backup::{BackupRecipe, BackupState, OpenedSnapshot, SnapshotItem},
+        config::Config,
+        AppState,
+    },
+    theme,
+    widgets::{maybe::Maybe, separator::Separator},
+    CREATE_BACKUP, OPEN_BACKUP, RESTORE_BACKUP, RESTORE_BACKUP_RECIPE,
+};
+
+use super::sidebar;
+
+/// Build the root widget for the backups screen
+pub fn backups_widget() -> impl Widget<AppState> {
+    let title_bar = Flex::row()
+        .with_child(
+            Label::new("Backups")
+                .with_font(theme::HEADER_FONT)
+                .align_left(),
+        )
+        .with_flex_spacer(1.)
+        .with_child(
+            Button::new("Back Up Now")
+                .on_click(|ctx, _data, _env| ctx.submit_command(CREATE_BACKUP)),
+        )
+        .with_spacer(10.);
+
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(title_bar.fix_height(50.).expand_width())
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.))
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Back up every"))
+                    .with_spacer(5.)
+                    .with_child(number_box(Config::backup_hours))
+                    .with_spacer(5.)
+                    .with_child(Label::new("hours, keeping the last"))
+                    .with_spacer(5.)
+                    .with_child(number_box(Config::backup_count))
+                    .with_spacer(5.)
+                    .with_child(Label::new("backups")),
+            )
+            .with_child(
+                Label::new("Set the hours to 0 to only back up by hand")
+                    .with_font(theme::SMALL_FONT),
+            )
+            .with_default_spacer()
+            .with_child(
+                Maybe::or_empty(|| {
+                    Label::dynamic(|message: &Arc<str>, _env| message.to_string())
+                        .with_font(theme::LABEL_FONT)
+                        .with_line_break_mode(LineBreaking::WordWrap)
+                })
+                .lens(AppState::backups.then(BackupState::message)),
+            )
+            .with_default_spacer()
+            .with_flex_child(
+                Flex::row()
+                    .cross_axis_alignment(CrossAxisAlignment::Start)
+                    .with_flex_child(
+                        Scroll::new(List::new(snapshot_widget).with_spacing(10.))
+                            .vertical()
+                            .lens(AppState::backups.then(BackupState::snapshots)),
+                        1.0,
+                    )
+                    .with_default_spacer()
+                    .with_flex_child(
+                        Maybe::or_empty(opened_widget)
+                            .lens(AppState::backups.then(BackupState::opened)),
+                        1.0,
+                    ),
+                1.0,
+            )
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a text box editing a number in the config
+fn number_box(lens: impl Lens<Config, u32> + 'static) -> impl Widget<AppState> {
+    TextBox::new()
+        .with_text_alignment(TextAlignment::Center)
+        .with_formatter(ParseFormatter::new())
+        .update_data_while_editing(true)
+        .fix_width(50.)
+        .lens(AppState::config.then(lens))
+}
+
+/// Format the time a backup was made in local time
+fn format_created(created: &DateTime<Utc>) -> String {
+    created
+        .with_timezone(&Local)
+        .format("%B %e, %Y at %I:%M %p")
+        .to_string()
+}
+
+/// Build a row showing one backup with buttons to browse or restore it
+fn snapshot_widget() -> impl Widget<SnapshotItem> {
+    Flex::row()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Label::dynamic(|item: &SnapshotItem, _env| format_created(&item.created))
+                        .with_font(theme::LABEL_FONT),
+                )
+                .with_child(
+                    Label::dynamic(|item: &SnapshotItem, _env| format!("{} recipes", item.recipes))
+                        .with_font(theme::SMALL_FONT),
+                ),
+            1.0,
+        )
+        .with_child(
+            Button::new("Show Recipes").on_click(|ctx, item: &mut SnapshotItem, _env| {
+                ctx.submit_command(OPEN_BACKUP.with(item.path.clone()))
+            }),
+        )
+        .with_default_spacer()
+        .with_child(
+            Button::new("Restore All").on_click(|ctx, item: &mut SnapshotItem, _env| {
+                ctx.submit_command(RESTORE_BACKUP.with(item.path.clone()))
+            }),
+        )
+        .padding((0., 0., 10., 0.))
+}
+
+/// Build a list of the recipes in the opened backup with buttons to restore each one
+fn opened_widget() -> impl Widget<OpenedSnapshot> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::dynamic(|opened: &OpenedSnapshot, _env| {
+                format!("Recipes backed up {}", format_created(&opened.created))
+            })
+            .with_font(theme::LABEL_FONT),
+        )
+        .with_default_spacer()
+        .with_flex_child(
+            Scroll::new(List::new(backup_recipe_widget).with_spacing(5.))
+                .vertical()
+                .lens(OpenedSnapshot::recipes),
+            1.0,
+        )
+}
+
+/// Build a row showing a recipe in a backup with a button to restore it
+fn backup_recipe_widget() -> impl Widget<BackupRecipe> {
+    Flex::row()
+        .with_flex_child(
+            Label::dynamic(|item: &BackupRecipe, _env| item.recipe.name.to_string())
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .expand_width(),
+            1.0,
+        )
+        .with_child(
+            Button::new("Restore").on_click(|ctx, item: &mut BackupRecipe, _env| {
+                ctx.submit_command(
+                    RESTORE_BACKUP_RECIPE.with((item.snapshot.clone(), item.recipe.id)),
+                )
+            }),
+        )
+        .padding((0., 0., 10., 0.))
+}
@@ -0,0 +1,115 @@
+//! Screen showing both copies of recipes that were changed in two places to choose which to keep
+
+use std::sync::Arc;
+
+use druid::{
+    widget::{Button, CrossAxisAlignment, Flex, Label, LineBreaking, List, Scroll},
+    Widget, WidgetExt,
+};
+
+use crate::{
+    gui::{
+        data::{conflict::ConflictItem, AppState},
+        theme,
+        widgets::separator::Separator,
+        RESOLVE_CONFLICT,
+    },
+    recipes::{db::conflict::Resolution, format::UnitFormat, recipe::Recipe},
+};
+
+use super::sidebar;
+
+/// Build the root widget for the conflict resolution screen
+pub fn conflicts_widget() -> impl Widget<AppState> {
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .with_child(
+                Label::new("Conflicts")
+                    .with_font(theme::HEADER_FONT)
+                    .align_left()
+                    .fix_height(50.),
+            )
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.).align_left())
+            .with_default_spacer()
+            .with_flex_child(
+                Scroll::new(List::new(conflict_widget).with_spacing(20.))
+                    .vertical()
+                    .lens(AppState::conflicts),
+                1.0,
+            )
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a widget showing both copies of one conflicting recipe with buttons to resolve it
+fn conflict_widget() -> impl Widget<ConflictItem> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(copy_widget("Mine").lens(ConflictItem::mine), 1.0)
+                .with_default_spacer()
+                .with_child(Separator::new(2.).vertical(true).with_color(theme::COLOR_2))
+                .with_default_spacer()
+                .with_flex_child(
+                    Flex::column()
+                        .cross_axis_alignment(CrossAxisAlignment::Start)
+                        .with_child(
+                            Label::dynamic(|item: &ConflictItem, _env| {
+                                format!("Theirs ({})", item.source)
+                            })
+                            .with_font(theme::LABEL_FONT),
+                        )
+                        .with_child(copy_text().lens(ConflictItem::theirs)),
+                    1.0,
+                ),
+        )
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(resolve_button("Keep Mine", Resolution::KeepMine))
+                .with_default_spacer()
+                .with_child(resolve_button("Keep Theirs", Resolution::KeepTheirs))
+                .with_default_spacer()
+                .with_child(resolve_button("Keep Both", Resolution::KeepBoth)),
+        )
+}
+
+/// Build a widget showing one copy of a conflicting recipe under a heading
+fn copy_widget(heading: &'static str) -> impl Widget<Option<Arc<Recipe>>> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new(heading).with_font(theme::LABEL_FONT))
+        .with_child(copy_text())
+}
+
+/// Build a label with the full contents of one copy of a recipe
+fn copy_text() -> impl Widget<Option<Arc<Recipe>>> {
+    Label::dynamic(|recipe: &Option<Arc<Recipe>>, env| match recipe {
+        Some(recipe) => recipe_text(recipe, &theme::unit_format(env)),
+        None => "Deleted".to_owned(),
+    })
+    .with_font(theme::SMALL_FONT)
+    .with_line_break_mode(LineBreaking::WordWrap)
+}
+
+/// Write out the name, ingredients, and instructions of a recipe to compare copies of it
+pub(super) fn recipe_text(recipe: &Recipe, format: &UnitFormat) -> String {
+    let mut text = format!("{}\n", recipe.name);
+    for ingredient in recipe.ingredients.iter() {
+        text.push_str(&format!("\n- {}", format.ingredient(ingredient)));
+    }
+    text.push_str(&format!("\n\n{}", recipe.body));
+    text
+}
+
+/// Build a button that resolves the conflict with the given resolution
+fn resolve_button(text: &'static str, resolution: Resolution) -> impl Widget<ConflictItem> {
+    Button::new(text).on_click(move |ctx, item: &mut ConflictItem, _env| {
+        ctx.submit_command(RESOLVE_CONFLICT.with((item.id, resolution)))
+    })
+}
@@ -0,0 +1,135 @@
+//! Screen showing pairs of recipes that are likely copies of each other to merge them
+
+use druid::{
+    im::Vector,
+    widget::{Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll, Slider},
+    LensExt, Widget, WidgetExt,
+};
+
+use crate::{
+    gui::{
+        data::{
+            duplicate::{DuplicatePair, DuplicateState},
+            AppState,
+        },
+        theme,
+        widgets::separator::Separator,
+        FIND_DUPLICATES, MERGE_DUPLICATES,
+    },
+    recipes::format::UnitFormat,
+};
+
+use super::{conflict::recipe_text, sidebar};
+
+/// Build the root widget for the duplicates screen
+pub fn duplicates_widget() -> impl Widget<AppState> {
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::new("Duplicates")
+                    .with_font(theme::HEADER_FONT)
+                    .fix_height(50.),
+            )
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.))
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Find recipes at least"))
+                    .with_spacer(5.)
+                    .with_child(
+                        Slider::new()
+                            .with_range(0.3, 1.)
+                            .fix_width(120.)
+                            .lens(AppState::duplicates.then(DuplicateState::threshold)),
+                    )
+                    .with_spacer(5.)
+                    .with_child(
+                        Label::dynamic(|threshold: &f64, _env| {
+                            format!("{:.0}% similar", threshold * 100.)
+                        })
+                        .lens(AppState::duplicates.then(DuplicateState::threshold)),
+                    )
+                    .with_default_spacer()
+                    .with_child(
+                        Button::new("Find Duplicates")
+                            .on_click(|ctx, _data, _env| ctx.submit_command(FIND_DUPLICATES)),
+                    )
+                    .with_default_spacer()
+                    .with_child(
+                        Label::dynamic(|searching: &bool, _env| match searching {
+                            true => "Searching...".to_owned(),
+                            false => String::new(),
+                        })
+                        .with_font(theme::SMALL_FONT)
+                        .lens(AppState::duplicates.then(DuplicateState::searching)),
+                    ),
+            )
+            .with_default_spacer()
+            .with_flex_child(
+                Either::new(
+                    |pairs: &Vector<DuplicatePair>, _env| pairs.is_empty(),
+                    Label::new("No likely duplicates found").with_font(theme::SMALL_FONT),
+                    Scroll::new(List::new(pair_widget).with_spacing(20.)).vertical(),
+                )
+                .lens(AppState::duplicates.then(DuplicateState::pairs)),
+                1.0,
+            )
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a widget showing both recipes of a likely duplicate pair with buttons to merge them
+fn pair_widget() -> impl Widget<DuplicatePair> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::dynamic(|pair: &DuplicatePair, _env| {
+                format!("{:.0}% similar", pair.score * 100.)
+            })
+            .with_font(theme::LABEL_FONT),
+        )
+        .with_spacer(5.)
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(
+                    recipe_label(|pair, format| recipe_text(&pair.first, format)),
+                    1.0,
+                )
+                .with_default_spacer()
+                .with_child(Separator::new(2.).vertical(true).with_color(theme::COLOR_2))
+                .with_default_spacer()
+                .with_flex_child(
+                    recipe_label(|pair, format| recipe_text(&pair.second, format)),
+                    1.0,
+                ),
+        )
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(Button::new("Merge Into Left").on_click(
+                    |ctx, pair: &mut DuplicatePair, _env| {
+                        ctx.submit_command(MERGE_DUPLICATES.with((pair.first.id, pair.second.id)))
+                    },
+                ))
+                .with_default_spacer()
+                .with_child(Button::new("Merge Into Right").on_click(
+                    |ctx, pair: &mut DuplicatePair, _env| {
+                        ctx.submit_command(MERGE_DUPLICATES.with((pair.second.id, pair.first.id)))
+                    },
+                )),
+        )
+}
+
+/// Build a label with the full contents of one recipe in a pair
+fn recipe_label(
+    text: impl Fn(&DuplicatePair, &UnitFormat) -> String + 'static,
+) -> impl Widget<DuplicatePair> {
+    Label::dynamic(move |pair: &DuplicatePair, env| text(pair, &theme::unit_format(env)))
+        .with_font(theme::SMALL_FONT)
+        .with_line_break_mode(LineBreaking::WordWrap)
+}
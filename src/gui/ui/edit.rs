@@ -96,9 +96,9 @@ pub fn edit_widget() -> impl Widget<AppState> {
         )
         .with_spacer(2.0)
         .with_child(ViewSwitcher::new(
-            |data: &EditState, _env| data.image.is_some(),
+            |data: &EditState, _env| (data.image.is_some(), data.image_loading),
             |img, _data, _env| match img {
-                true => Flex::column()
+                (true, _) => Flex::column()
                     .with_child(ImageBuilder::new().fix_height(175.))
                     .with_spacer(2.0)
                     .with_child(
@@ -107,13 +107,19 @@ pub fn edit_widget() -> impl Widget<AppState> {
                             .highlight_on_hover()
                             .on_click(|_ctx, data: &mut EditState, _env| {
                                 data.image = None;
+                                data.image_changed = true;
                             })
                             .fix_size(25., 25.),
                     )
                     .expand_width()
                     .align_left()
                     .boxed(),
-                false => PLUS_ICON
+                (false, true) => Label::new("Loading image...")
+                    .with_font(theme::SMALL_FONT)
+                    .center()
+                    .fix_height(175.)
+                    .boxed(),
+                (false, false) => PLUS_ICON
                     .clone()
                     .highlight_on_hover()
                     .on_click(|ctx, _data, _env| {
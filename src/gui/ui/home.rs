@@ -1,15 +1,17 @@
 //! Homescreen widgets displaying a view of all saved recipes
 
+use std::sync::Arc;
+
 use druid::{
-    widget::{Button, Flex, Label, List, Scroll},
-    Widget, WidgetExt,
+    widget::{Button, Flex, Label, LineBreaking, List, Scroll},
+    Color, Widget, WidgetExt,
 };
 
 use crate::gui::{
-    data::{screen::AppScreen, AppState},
+    data::{home::HomeState, screen::AppScreen, AppState},
     theme,
     widgets::{icon, separator::Separator},
-    CHANGE_SCREEN, CREATE_RECIPE, LOAD_MORE_RECIPES,
+    CHANGE_SCREEN, CREATE_RECIPE, LOAD_MORE_RECIPES, SORT_RECIPES,
 };
 
 use super::{recipe::recipe_brief_widget, sidebar};
@@ -24,6 +26,28 @@ pub fn home_widget() -> impl Widget<AppState> {
                     .align_left(),
             )
             .with_flex_spacer(5.)
+            .with_child(
+                Button::dynamic(|home: &HomeState, _env| format!("Sort: {}", home.sort))
+                    .on_click(|ctx, home: &mut HomeState, _env| {
+                        ctx.submit_command(SORT_RECIPES.with((home.sort.next(), home.descending)))
+                    })
+                    .lens(AppState::home),
+            )
+            .with_spacer(5.)
+            .with_child(
+                Button::dynamic(|home: &HomeState, _env| {
+                    match home.descending {
+                        true => "Descending",
+                        false => "Ascending",
+                    }
+                    .to_owned()
+                })
+                .on_click(|ctx, home: &mut HomeState, _env| {
+                    ctx.submit_command(SORT_RECIPES.with((home.sort, !home.descending)))
+                })
+                .lens(AppState::home),
+            )
+            .with_spacer(10.)
             .with_child(icon::PLUS_ICON.clone().highlight_on_hover().on_click(
                 |ctx, _data, _env| {
                     ctx.submit_command(CREATE_RECIPE);
@@ -38,6 +62,7 @@ pub fn home_widget() -> impl Widget<AppState> {
             .with_spacer(1.)
             .with_child(Separator::new(2.5).fix_width(130.).align_left())
             .with_default_spacer()
+            .with_child(load_errors_widget())
             .with_flex_child(
                 Scroll::new(
                     Flex::column()
@@ -59,3 +84,16 @@ pub fn home_widget() -> impl Widget<AppState> {
         1.0,
     )
 }
+
+/// Construct a widget listing saved data that could not be loaded at startup
+fn load_errors_widget() -> impl Widget<AppState> {
+    List::new(|| {
+        Label::dynamic(|error: &Arc<str>, _env| error.to_string())
+            .with_font(theme::SMALL_FONT)
+            .with_text_color(Color::rgb8(0xC0, 0x39, 0x2B))
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .padding((15., 2.))
+            .expand_width()
+    })
+    .lens(AppState::load_errors)
+}
@@ -0,0 +1,39 @@
+//! Screen showing progress while recipes are loaded at startup
+
+use druid::{
+    widget::{Flex, Label, ProgressBar},
+    Widget, WidgetExt,
+};
+
+use crate::gui::{
+    data::{loading::LoadingState, AppState},
+    theme,
+    widgets::separator::Separator,
+};
+
+/// Build the root widget for the loading screen
+pub fn loading_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(Label::new("Loading Recipes").with_font(theme::HEADER_FONT))
+        .with_spacer(1.)
+        .with_child(Separator::new(2.5).fix_width(130.))
+        .with_default_spacer()
+        .with_child(
+            ProgressBar::new()
+                .lens(druid::lens::Map::new(
+                    LoadingState::fraction,
+                    |_: &mut LoadingState, _: f64| (),
+                ))
+                .fix_width(250.),
+        )
+        .with_default_spacer()
+        .with_child(
+            Label::dynamic(|loading: &LoadingState, _env| match loading.total {
+                0 => "Reading recipe index...".to_owned(),
+                total => format!("Loaded {} of {} recipes", loading.loaded, total),
+            })
+            .with_font(theme::SMALL_FONT),
+        )
+        .lens(AppState::loading)
+        .center()
+}
@@ -0,0 +1,231 @@
+//! Maintenance screen with actions to repair and rewrite the recipe database
+
+use std::sync::Arc;
+
+use druid::{
+    commands::SHOW_OPEN_PANEL,
+    widget::{Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll},
+    Env, EventCtx, FileDialogOptions, LensExt, Widget, WidgetExt,
+};
+
+use crate::gui::{
+    data::{
+        config::Config, maintenance::MaintenanceState, passphrase::PassphraseState,
+        screen::AppScreen, AppState,
+    },
+    theme,
+    widgets::{masked::masked_text_box, maybe::Maybe, separator::Separator},
+    CHANGE_SCREEN, CHECK_DATABASE, JSON_LD, REMOVE_PASSPHRASE, REWRITE_ALL_RECIPES, SET_PASSPHRASE,
+};
+
+use super::sidebar;
+
+/// Build the root widget for the maintenance screen
+pub fn maintenance_widget() -> impl Widget<AppState> {
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .with_child(
+                Label::new("Maintenance")
+                    .with_font(theme::HEADER_FONT)
+                    .align_left()
+                    .fix_height(50.),
+            )
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.).align_left())
+            .with_default_spacer()
+            .with_child(action(
+                "Rewrite All Recipes",
+                "Save every recipe again, even if it has not changed since it was last saved",
+                |ctx, _data, _env| ctx.submit_command(REWRITE_ALL_RECIPES),
+            ))
+            .with_default_spacer()
+            .with_child(action(
+                "Check Recipes",
+                "Look for saved recipes that can't be read or are stored incorrectly",
+                |ctx, _data, _env| ctx.submit_command(CHECK_DATABASE.with(false)),
+            ))
+            .with_default_spacer()
+            .with_child(action(
+                "Repair Recipes",
+                "Fix recipes that are stored incorrectly and move recipes that can't be read to the quarantine folder",
+                |ctx, _data, _env| ctx.submit_command(CHECK_DATABASE.with(true)),
+            ))
+            .with_default_spacer()
+            .with_child(action(
+                "Import Recipe",
+                "Add a recipe saved from a recipe website as a schema.org JSON-LD file",
+                |ctx, _data, _env| {
+                    ctx.submit_command(
+                        SHOW_OPEN_PANEL.with(
+                            FileDialogOptions::new()
+                                .allowed_types(vec![JSON_LD])
+                                .button_text("Import")
+                                .title("Choose Recipe to Import"),
+                        ),
+                    )
+                },
+            ))
+            .with_default_spacer()
+            .with_child(action(
+                "Find Duplicates",
+                "List recipes that are likely copies of each other, like a recipe imported twice, to merge them into one recipe",
+                |ctx, _data, _env| ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Duplicates)),
+            ))
+            .with_default_spacer()
+            .with_child(action(
+                "Import Cookbook",
+                "Merge the recipes in another cookbook's recipe folder into this one, choosing which copy to keep of recipes that differ",
+                |ctx, _data, _env| {
+                    ctx.submit_command(
+                        SHOW_OPEN_PANEL.with(
+                            FileDialogOptions::new()
+                                .select_directories()
+                                .button_text("Import")
+                                .title("Choose Recipe Folder to Import"),
+                        ),
+                    )
+                },
+            ))
+            .with_default_spacer()
+            .with_child(volume_units())
+            .with_default_spacer()
+            .with_child(encryption())
+            .with_default_spacer()
+            .with_flex_child(check_results().lens(AppState::maintenance), 1.0)
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a row with a button that runs a maintenance action and a description of the action
+fn action(
+    name: &'static str,
+    description: &'static str,
+    on_click: impl Fn(&mut EventCtx, &mut AppState, &Env) + 'static,
+) -> impl Widget<AppState> {
+    Flex::row()
+        .with_child(Button::new(name).on_click(on_click).fix_width(180.))
+        .with_default_spacer()
+        .with_flex_child(
+            Label::new(description)
+                .with_font(theme::SMALL_FONT)
+                .with_line_break_mode(LineBreaking::WordWrap),
+            1.0,
+        )
+}
+
+/// Build the setting for which measurement system cups, spoons, and other volume units without a
+/// system are measured in
+fn volume_units() -> impl Widget<AppState> {
+    Flex::row()
+        .with_child(
+            Button::new("Change Volume Units")
+                .on_click(|_ctx, data: &mut AppState, _env| {
+                    data.config.volume_system = data.config.volume_system.next()
+                })
+                .fix_width(180.),
+        )
+        .with_default_spacer()
+        .with_flex_child(
+            Label::dynamic(|data: &AppState, _env| {
+                format!(
+                    "Cups, spoons, fluid ounces, pints, quarts, and gallons are measured in {} units",
+                    data.config.volume_system
+                )
+            })
+            .with_font(theme::SMALL_FONT)
+            .with_line_break_mode(LineBreaking::WordWrap),
+            1.0,
+        )
+}
+
+/// Build the settings to encrypt recipes with a passphrase, change the passphrase, or decrypt
+/// recipes back to plain files
+fn encryption() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Label::dynamic(|encrypted: &bool, _env| {
+                match encrypted {
+                    true => "Recipes are encrypted with a passphrase that is asked for at startup",
+                    false => "Recipes are saved as plain files",
+                }
+                .to_owned()
+            })
+            .with_font(theme::LABEL_FONT)
+            .lens(AppState::config.then(Config::encrypted)),
+        )
+        .with_spacer(5.)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    masked_text_box("New passphrase")
+                        .fix_width(180.)
+                        .lens(AppState::passphrase.then(PassphraseState::passphrase)),
+                )
+                .with_default_spacer()
+                .with_child(
+                    masked_text_box("Confirm passphrase")
+                        .fix_width(180.)
+                        .lens(AppState::passphrase.then(PassphraseState::confirm)),
+                ),
+        )
+        .with_spacer(5.)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    Either::new(
+                        |data: &AppState, _env| data.config.encrypted,
+                        Button::new("Change Passphrase"),
+                        Button::new("Encrypt Recipes"),
+                    )
+                    .on_click(|ctx, _data, _env| ctx.submit_command(SET_PASSPHRASE))
+                    .fix_width(180.),
+                )
+                .with_default_spacer()
+                .with_child(Either::new(
+                    |data: &AppState, _env| data.config.encrypted,
+                    Button::new("Decrypt Recipes")
+                        .on_click(|ctx, _data, _env| ctx.submit_command(REMOVE_PASSPHRASE))
+                        .fix_width(180.),
+                    Flex::row(),
+                )),
+        )
+        .with_child(
+            Maybe::or_empty(|| {
+                Label::dynamic(|status: &Arc<str>, _env| status.to_string())
+                    .with_font(theme::SMALL_FONT)
+            })
+            .lens(AppState::passphrase.then(PassphraseState::status)),
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+/// Build a widget listing the problems found by the last integrity check
+fn check_results() -> impl Widget<MaintenanceState> {
+    Flex::column()
+        .with_child(
+            Maybe::or_empty(|| {
+                Label::dynamic(|summary: &Arc<str>, _env| summary.to_string())
+                    .with_font(theme::LABEL_FONT)
+            })
+            .lens(MaintenanceState::summary),
+        )
+        .with_default_spacer()
+        .with_flex_child(
+            Scroll::new(
+                List::new(|| {
+                    Label::dynamic(|finding: &Arc<str>, _env| finding.to_string())
+                        .with_font(theme::SMALL_FONT)
+                        .with_line_break_mode(LineBreaking::WordWrap)
+                        .expand_width()
+                })
+                .with_spacing(2.),
+            )
+            .vertical()
+            .lens(MaintenanceState::findings),
+            1.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
@@ -1,17 +1,25 @@
+pub mod backup;
+pub mod conflict;
+pub mod duplicate;
 pub mod edit;
 pub mod home;
+pub mod loading;
+pub mod maintenance;
 pub mod recipe;
 pub mod remove;
 pub mod search;
+pub mod stats;
+pub mod trash;
+pub mod unlock;
 
 use druid::{
-    widget::{Flex, ViewSwitcher},
+    widget::{Button, Either, Flex, Label, LineBreaking, SizedBox, ViewSwitcher},
     LensExt, Widget, WidgetExt,
 };
 
 use crate::gui::{
     theme,
-    widgets::{icon, separator::Separator},
+    widgets::{autosave::AutosaveController, backup::BackupController, icon, separator::Separator},
 };
 
 use super::{
@@ -31,11 +39,24 @@ pub fn root_widget() -> impl Widget<AppState> {
             AppScreen::Delete => remove::remove_widget().boxed(),
             AppScreen::Edit => edit::edit_widget().boxed(),
             AppScreen::Home => home::home_widget().boxed(),
+            AppScreen::Maintenance => maintenance::maintenance_widget().boxed(),
+            AppScreen::Conflicts => conflict::conflicts_widget().boxed(),
+            AppScreen::Trash => trash::trash_widget().boxed(),
+            AppScreen::Backups => backup::backups_widget().boxed(),
+            AppScreen::Stats => stats::stats_widget().boxed(),
+            AppScreen::Duplicates => duplicate::duplicates_widget().boxed(),
+            AppScreen::Unlock => unlock::unlock_widget().boxed(),
+            AppScreen::Loading => loading::loading_widget().boxed(),
             AppScreen::SearchResults => search::search_screen().boxed(),
             AppScreen::View => recipe::view_screen().boxed(),
         },
     )
     .expand()
+    .controller(AutosaveController::default())
+    .controller(BackupController::default())
+    .env_scope(|env, state: &AppState| {
+        theme::set_units(env, &state.config.units, state.config.volume_system)
+    })
 }
 
 /// Build the sidebar with search and home icon
@@ -76,6 +97,57 @@ pub fn sidebar() -> impl Widget<AppState> {
                 )
                 .with_default_spacer()
                 .with_flex_spacer(0.5)
+                .with_child(Either::new(
+                    |state: &AppState, _env| state.conflicts.is_empty(),
+                    SizedBox::empty(),
+                    Button::dynamic(|state: &AppState, _env| {
+                        format!("Resolve {} Conflicts", state.conflicts.len())
+                    })
+                    .on_click(|ctx, _data, _env| {
+                        ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Conflicts))
+                    })
+                    .align_left(),
+                ))
+                .with_default_spacer()
+                .with_child(
+                    Button::new("Recently Deleted")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Trash))
+                        })
+                        .align_left(),
+                )
+                .with_default_spacer()
+                .with_child(
+                    Button::new("Backups")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Backups))
+                        })
+                        .align_left(),
+                )
+                .with_default_spacer()
+                .with_child(
+                    Button::new("Statistics")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Stats))
+                        })
+                        .align_left(),
+                )
+                .with_default_spacer()
+                .with_child(
+                    Button::new("Maintenance")
+                        .on_click(|ctx, _data, _env| {
+                            ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Maintenance))
+                        })
+                        .align_left(),
+                )
+                .with_default_spacer()
+                .with_child(
+                    Label::dynamic(|state: &AppState, _env| state.save_status.to_string())
+                        .with_font(theme::SMALL_FONT)
+                        .with_line_break_mode(LineBreaking::WordWrap)
+                        .align_left(),
+                )
+                .with_default_spacer()
                 .padding((5., 0., 0., 0.))
                 .fix_width(200.),
         )
@@ -1,26 +1,31 @@
 //! Widgets for displaying recipes
 
-use std::{sync::Arc, time::Duration};
+use std::{path, sync::Arc, time::Duration};
 
 use druid::{
+    commands::SHOW_SAVE_PANEL,
     lens,
-    widget::{FillStrat, Flex, Image, Label, LineBreaking, List, Scroll, SizedBox},
-    Data, LensExt, LifeCycle, TextAlignment, Widget, WidgetExt,
+    widget::{Button, FillStrat, Flex, Image, Label, LineBreaking, List, Scroll, SizedBox},
+    FileDialogOptions, LensExt, TextAlignment, Widget, WidgetExt,
 };
 
 use crate::{
     gui::{
-        data::{screen::AppScreen, AppState},
+        data::{save::SaveStatus, screen::AppScreen, AppState},
         theme,
         widgets::{
             icon::{PEN_ICON, RECYCLE_ICON, RIGHT_ARROW_ICON},
             maybe::Maybe,
+            recipe_image::RecipeImage,
             separator::Separator,
             RecipierWidget,
         },
-        CHANGE_SCREEN, EDIT_RECIPE, REMOVE_RECIPE, VIEW_RECIPE,
+        CHANGE_SCREEN, EDIT_RECIPE, JSON_LD, REMOVE_RECIPE, VIEW_RECIPE,
+    },
+    recipes::{
+        db::{images::ImageSize, Database},
+        recipe::{Ingredient, Recipe},
     },
-    recipes::recipe::{Ingredient, Recipe},
 };
 
 use super::sidebar;
@@ -28,6 +33,9 @@ use super::sidebar;
 /// The string to use when formatting chrono datetimes
 pub const DATETIME_FORMAT: &str = "%e %B %Y %I:%M";
 
+/// Width and height that thumbnails of recipe images are shown at in lists of recipes
+const THUMBNAIL_SIZE: f64 = 64.;
+
 /// Return a widget that displays one recipe in a maximized view
 pub fn view_screen() -> impl Widget<AppState> {
     Flex::row()
@@ -41,7 +49,7 @@ pub fn recipe_widget() -> impl Widget<AppState> {
         |state: &AppState| state.recipes.get(state.view.viewed?),
         |state, recipe| {
             if let Some(recipe) = recipe {
-                state.recipes.update(recipe);
+                state.save_status = SaveStatus::from_result(state.recipes.update(recipe));
             }
         },
     );
@@ -59,6 +67,8 @@ pub fn recipe_widget() -> impl Widget<AppState> {
                             .lens(Recipe::name),
                     )
                     .with_flex_spacer(1.)
+                    .with_child(export_button())
+                    .with_spacer(10.)
                     .with_child(
                         Flex::column()
                             .with_child(edit_button(AppScreen::View))
@@ -80,7 +90,17 @@ pub fn recipe_widget() -> impl Widget<AppState> {
             .lens(LensExt::<Arc<Recipe>, Arc<Recipe>>::in_arc(lens::Identity))
     });
 
-    let image = ImageBuilder::new().fix_height(175.);
+    let image = RecipeImage::new(
+        ImageSize::Full,
+        |data: &AppState| data.view.viewed.map(|id| (&data.recipes, id)),
+        |buf| Image::new(buf).fill_mode(FillStrat::Contain),
+    )
+    .with_placeholder(|| {
+        Label::new("Loading image...")
+            .with_font(theme::SMALL_FONT)
+            .center()
+    })
+    .fix_height(175.);
 
     let lower = Maybe::or_empty(|| {
         Flex::column()
@@ -89,11 +109,12 @@ pub fn recipe_widget() -> impl Widget<AppState> {
                 Maybe::or_empty(|| {
                     Flex::column()
                         .with_child(
-                            Label::new(|servings: &f32, _env: &'_ _| {
+                            Label::new(|servings: &f32, env: &'_ _| {
+                                let format = theme::unit_format(env);
                                 format!(
                                     "Makes {} serving{}",
-                                    servings,
-                                    if (*servings - 1f32).abs() < f32::EPSILON {
+                                    format.number(*servings),
+                                    if format.is_singular(*servings) {
                                         ""
                                     } else {
                                         "s"
@@ -111,8 +132,12 @@ pub fn recipe_widget() -> impl Widget<AppState> {
                 Maybe::or_empty(|| {
                     Flex::column()
                         .with_child(
-                            Label::new(|time: &f32, _env: &'_ _| {
-                                format!("Takes {} to cook", FormattedDuration(*time))
+                            Label::new(|time: &f32, env: &'_ _| {
+                                format!(
+                                    "Takes {} to cook",
+                                    theme::unit_format(env)
+                                        .duration(Duration::from_secs_f32(*time))
+                                )
                             })
                             .align_left()
                             .expand_width(),
@@ -149,8 +174,8 @@ pub fn recipe_widget() -> impl Widget<AppState> {
                                         .align_left(),
                                 )
                                 .with_default_spacer()
-                                .with_child(Label::new(|ingredient: &Ingredient, _env: &'_ _| {
-                                    format!("{}", ingredient.amount)
+                                .with_child(Label::new(|ingredient: &Ingredient, env: &'_ _| {
+                                    theme::unit_format(env).amount(&ingredient.amount)
                                 }))
                                 .expand_width()
                                 .padding((2.5, 5.)),
@@ -209,8 +234,25 @@ fn edit_button(screen: AppScreen) -> impl Widget<Recipe> {
         .fix_size(20., 20.)
 }
 
-/// Show a peek of a recipe with brief details
-pub fn recipe_brief_widget() -> impl Widget<Recipe> {
+/// Button that asks where to export the recipe to as a schema.org JSON-LD file
+fn export_button() -> impl Widget<Recipe> {
+    Button::new("Export").on_click(|ctx, recipe: &mut Recipe, _env| {
+        let name = recipe.name.replace(path::is_separator, "-");
+        ctx.submit_command(
+            SHOW_SAVE_PANEL.with(
+                FileDialogOptions::new()
+                    .allowed_types(vec![JSON_LD])
+                    .default_type(JSON_LD)
+                    .default_name(format!("{}.json", name))
+                    .button_text("Export")
+                    .title("Export Recipe"),
+            ),
+        )
+    })
+}
+
+/// Show a peek of a recipe with brief details and a thumbnail of its image
+pub fn recipe_brief_widget() -> impl Widget<(Database, Arc<Recipe>)> {
     let recipe = Flex::column()
         .with_child(
             Label::raw()
@@ -242,7 +284,7 @@ pub fn recipe_brief_widget() -> impl Widget<Recipe> {
         )
         .expand_width();
 
-    Flex::row()
+    let details = Flex::row()
         .with_flex_child(recipe, 10.)
         .with_spacer(5.0)
         .with_child(
@@ -252,127 +294,34 @@ pub fn recipe_brief_widget() -> impl Widget<Recipe> {
                 .with_child(delete_button(AppScreen::Home)),
         )
         .with_spacer(5.0)
-        .expand_width()
-}
-
-/// A newtype over [f32] used for a custom [Display](std::fmt::Display) impl that shows
-/// the duration in a more readable way
-struct FormattedDuration(f32);
-impl std::fmt::Display for FormattedDuration {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        const MILLIS_DAY: f32 = 86400f32;
-        const MILLIS_HOUR: f32 = 3600f32;
-        const MILLIS_MINUTE: f32 = 60f32;
-        const MILLIS_SECOND: f32 = 1f32;
-
-        let seconds = self.0;
-        if seconds >= MILLIS_DAY {
-            write!(f, "{} day", seconds / MILLIS_DAY)?;
-            if seconds >= (MILLIS_DAY * 2f32) {
-                write!(f, "s")?;
-            }
-        } else if seconds >= MILLIS_HOUR {
-            write!(f, "{} hour", seconds / MILLIS_HOUR)?;
-            if seconds >= (MILLIS_HOUR * 2f32) {
-                write!(f, "s")?;
-            }
-        } else if seconds >= MILLIS_MINUTE {
-            write!(f, "{} min", seconds / MILLIS_MINUTE)?;
-            if seconds >= (MILLIS_MINUTE * 2f32) {
-                write!(f, "s")?;
-            }
-        } else if seconds >= MILLIS_SECOND {
-            write!(f, "{} sec", seconds / MILLIS_SECOND)?;
-            if seconds >= (MILLIS_SECOND * 2f32) {
-                write!(f, "s")?;
-            }
-        } else {
-            write!(f, "{} seconds", seconds)?;
-        }
-
-        Ok(())
-    }
-}
-
-/// A widget that builds an image using app state
-struct ImageBuilder {
-    /// The internal widget to display
-    widget: Box<dyn Widget<AppState>>,
-}
-
-impl ImageBuilder {
-    /// Create a new empty image builder
-    pub fn new() -> Self {
-        Self {
-            widget: SizedBox::empty().boxed(),
-        }
-    }
-}
-
-impl Widget<AppState> for ImageBuilder {
-    fn event(
-        &mut self,
-        ctx: &mut druid::EventCtx,
-        event: &druid::Event,
-        data: &mut AppState,
-        env: &druid::Env,
-    ) {
-        self.widget.event(ctx, event, data, env)
-    }
-
-    fn lifecycle(
-        &mut self,
-        ctx: &mut druid::LifeCycleCtx,
-        event: &druid::LifeCycle,
-        data: &AppState,
-        env: &druid::Env,
-    ) {
-        if let LifeCycle::WidgetAdded = event {
-            if let Some(id) = data.view.viewed {
-                if let Some(data) = data.recipes.get_image(id).as_ref() {
-                    self.widget = Image::new(data.clone())
-                        .fill_mode(FillStrat::Contain)
-                        .boxed();
-                }
-            }
-        }
-        self.widget.lifecycle(ctx, event, data, env)
-    }
-
-    fn update(
-        &mut self,
-        ctx: &mut druid::UpdateCtx,
-        old_data: &AppState,
-        data: &AppState,
-        _env: &druid::Env,
-    ) {
-        if !old_data.same(data) {
-            if let Some(id) = data.view.viewed {
-                if let Some(data) = data.recipes.get_image(id).as_ref() {
-                    self.widget = Image::new(data.clone())
-                        .fill_mode(FillStrat::Contain)
-                        .boxed();
-                    ctx.children_changed()
-                }
-            }
-        }
-    }
-
-    fn layout(
-        &mut self,
-        ctx: &mut druid::LayoutCtx,
-        bc: &druid::BoxConstraints,
-        data: &AppState,
-        env: &druid::Env,
-    ) -> druid::Size {
-        self.widget.layout(ctx, bc, data, env)
-    }
-
-    fn paint(&mut self, ctx: &mut druid::PaintCtx, data: &AppState, env: &druid::Env) {
-        self.widget.paint(ctx, data, env)
-    }
+        .expand_width();
 
-    fn id(&self) -> Option<druid::WidgetId> {
-        self.widget.id()
-    }
+    Flex::row()
+        .with_child(
+            RecipeImage::new(
+                ImageSize::Thumbnail,
+                |(recipes, recipe): &(Database, Arc<Recipe>)| Some((recipes, recipe.id)),
+                |buf| {
+                    Image::new(buf)
+                        .fill_mode(FillStrat::Cover)
+                        .fix_size(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                        .padding((0., 0., 5., 0.))
+                },
+            )
+            .with_placeholder(|| {
+                SizedBox::empty()
+                    .fix_size(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                    .background(theme::COLOR_2)
+                    .rounded(5.)
+                    .padding((0., 0., 5., 0.))
+            }),
+        )
+        .with_flex_child(
+            details.lens(
+                lens!((Database, Arc<Recipe>), 1)
+                    .then(LensExt::<Arc<Recipe>, Arc<Recipe>>::in_arc(lens::Identity)),
+            ),
+            1.,
+        )
+        .expand_width()
 }
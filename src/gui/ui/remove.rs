@@ -9,7 +9,7 @@ use druid::{
 
 use crate::{
     gui::{
-        data::{remove::RemoveState, screen::AppScreen, AppState},
+        data::{remove::RemoveState, save::SaveStatus, screen::AppScreen, AppState},
         theme,
         widgets::{maybe::Maybe, separator::Separator},
         CHANGE_SCREEN,
@@ -27,6 +27,8 @@ pub fn remove_widget() -> impl Widget<AppState> {
                 .with_spacer(1.)
                 .with_child(Separator::new(2.5).fix_width(130.))
                 .with_default_spacer()
+                .with_child(Label::new("Deleted recipes can be restored from Recently Deleted").with_font(theme::SMALL_FONT))
+                .with_default_spacer()
                 .lens(RemoveState::deleted),
             || Label::dynamic(|_, _| {
                 log::error!("Remove screen displayed while there is no recipe to remove!");
@@ -48,7 +50,7 @@ pub fn remove_widget() -> impl Widget<AppState> {
         .with_child(Button::new("Delete")
             .on_click(|ctx, data: &mut AppState, _env| {
                 if let Some(ref remove) = data.remove {
-                    data.recipes.remove(remove.deleted.id);
+                    data.save_status = SaveStatus::from_result(data.recipes.remove(remove.deleted.id));
                     ctx.submit_command(CHANGE_SCREEN.with(AppScreen::Home)) //Ignore the return to option because we deleted the recipe
                 }
                 data.remove = None;
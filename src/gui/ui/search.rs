@@ -1,25 +1,20 @@
 //! Search widget builders that modify the search term state data and dispatch commands to search
 
-use std::sync::Arc;
-
 use druid::{
     lens,
-    widget::{Controller, Flex, Label, List, Scroll, SizedBox, TextBox},
+    widget::{Button, Checkbox, Controller, Flex, Label, List, Scroll, SizedBox, TextBox},
     Data, Event, LensExt, Widget, WidgetExt,
 };
 
-use crate::{
-    gui::{
-        data::{
-            screen::AppScreen,
-            search::{Query, SearchResults, SearchState},
-            AppState,
-        },
-        theme,
-        widgets::{icon, maybe::Maybe, separator::Separator, RecipierWidget},
-        CHANGE_SCREEN, POPULATE_RESULTS,
+use crate::gui::{
+    data::{
+        screen::AppScreen,
+        search::{Query, SearchResults, SearchState},
+        AppState,
     },
-    recipes::recipe::Recipe,
+    theme,
+    widgets::{icon, maybe::Maybe, separator::Separator, RecipierWidget},
+    CHANGE_SCREEN, LOAD_MORE_RESULTS, POPULATE_RESULTS,
 };
 
 use super::{recipe::recipe_brief_widget, sidebar};
@@ -60,15 +55,21 @@ pub fn search_screen() -> impl Widget<AppState> {
             .with_child(
                 search_bar().lens(AppState::search.then(SearchState::query.then(Query::term))),
             )
+            .with_child(
+                Checkbox::new("Quick recipes only")
+                    .align_left()
+                    .lens(AppState::search.then(SearchState::query.then(Query::quick))),
+            )
             .with_default_spacer()
             .with_child(
                 Maybe::or_empty(|| {
                     Flex::row()
                         .with_child(Label::new("Results for").with_font(theme::SMALL_FONT))
                         .with_child(
-                            Label::raw()
-                                .with_font(theme::SMALL_FONT)
-                                .lens(SearchResults::term),
+                            Label::dynamic(|results: &SearchResults, _env| {
+                                results.query.term.clone()
+                            })
+                            .with_font(theme::SMALL_FONT),
                         )
                         .align_left()
                 })
@@ -80,19 +81,27 @@ pub fn search_screen() -> impl Widget<AppState> {
                 Maybe::new(
                     || {
                         Scroll::new(
-                            List::new(|| {
-                                recipe_brief_widget().lens(
-                                    LensExt::<Arc<Recipe>, Arc<Recipe>>::in_arc(lens::Identity),
-                                )
-                            })
-                            .with_spacing(10.),
+                            Flex::column()
+                                .with_child(List::new(recipe_brief_widget).with_spacing(10.))
+                                .with_default_spacer()
+                                .with_child(Button::new("Load More").fix_size(100., 40.).on_click(
+                                    |ctx, _data, _env| ctx.submit_command(LOAD_MORE_RESULTS),
+                                )),
                         )
                         .vertical()
                     },
                     || SizedBox::empty().expand_height(),
                 )
                 .expand_width()
-                .lens(AppState::search.then(SearchState::results)),
+                .lens(lens::Map::new(
+                    |data: &AppState| {
+                        data.search
+                            .results
+                            .as_ref()
+                            .map(|results| (data.recipes.clone(), results.loaded.clone()))
+                    },
+                    |_, _| (),
+                )),
                 10.,
             )
             .padding((2., 0.)),
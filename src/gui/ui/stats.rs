@@ -0,0 +1,157 @@
+//! Statistics screen showing totals about every recipe in the database
+
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use druid::{
+    widget::{CrossAxisAlignment, Flex, Label, Scroll, SizedBox, ViewSwitcher},
+    Env, Widget, WidgetExt,
+};
+
+use crate::{
+    gui::{data::AppState, theme, widgets::separator::Separator},
+    recipes::db::stats::{Month, Stats},
+};
+
+use super::sidebar;
+
+/// Width of the longest bar in a bar chart
+const BAR_WIDTH: f64 = 200.;
+
+/// Build the root widget for the statistics screen
+pub fn stats_widget() -> impl Widget<AppState> {
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::new("Statistics")
+                    .with_font(theme::HEADER_FONT)
+                    .fix_height(50.),
+            )
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.))
+            .with_default_spacer()
+            .with_child(
+                Label::dynamic(|collecting: &bool, _env| match collecting {
+                    true => "Collecting statistics...".to_owned(),
+                    false => String::new(),
+                })
+                .with_font(theme::SMALL_FONT)
+                .lens(AppState::collecting_stats),
+            )
+            .with_flex_child(
+                Scroll::new(ViewSwitcher::new(
+                    |state: &AppState, _env| state.stats.clone(),
+                    |stats, _state, env| stats_view(stats, env).boxed(),
+                ))
+                .vertical()
+                .expand_width(),
+                1.0,
+            )
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a column showing every statistic
+fn stats_view(stats: &Arc<Stats>, env: &Env) -> impl Widget<AppState> {
+    let mut column = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new(format!("{} recipes", stats.total)).with_font(theme::LABEL_FONT));
+    if stats.unreadable > 0 {
+        column.add_child(
+            Label::new(format!(
+                "{} recipes could not be read and are only counted in the total",
+                stats.unreadable
+            ))
+            .with_font(theme::SMALL_FONT),
+        );
+    }
+    column.add_child(Label::new(match stats.average_time {
+        Some(time) => format!(
+            "Recipes take {} to make on average",
+            theme::unit_format(env).duration(time)
+        ),
+        None => "No recipes say how long they take to make".to_owned(),
+    }));
+
+    column.add_default_spacer();
+    column.add_child(heading("Recipes Added Each Month"));
+    column.add_child(bar_chart(
+        stats
+            .per_month
+            .iter()
+            .map(|(month, count)| (month_name(*month), *count))
+            .collect(),
+    ));
+
+    column.add_default_spacer();
+    column.add_child(heading("Most Used Ingredients"));
+    column.add_child(bar_chart(
+        stats
+            .ingredients
+            .iter()
+            .map(|(name, count)| (name.to_string(), *count))
+            .collect(),
+    ));
+
+    column.add_default_spacer();
+    column.add_child(heading("Servings"));
+    column.add_child(bar_chart(
+        stats
+            .servings
+            .iter()
+            .map(|(servings, count)| (format!("{} servings", servings), *count))
+            .chain(
+                Some(("Not given".to_owned(), stats.no_servings)).filter(|(_, count)| *count > 0),
+            )
+            .collect(),
+    ));
+    column.add_default_spacer();
+    column
+}
+
+/// Build the heading of one section of statistics
+fn heading(text: &str) -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new(text).with_font(theme::LABEL_FONT))
+        .with_child(Separator::new(1.).fix_width(100.))
+}
+
+/// Build a horizontal bar chart with one labelled bar for each count, or a note if there are no
+/// counts
+fn bar_chart(rows: Vec<(String, usize)>) -> impl Widget<AppState> {
+    let max = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    if max == 0 {
+        return Label::new("Nothing to show yet")
+            .with_font(theme::SMALL_FONT)
+            .boxed();
+    }
+
+    let mut chart = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for (name, count) in rows {
+        chart.add_child(
+            Flex::row()
+                .with_child(Label::new(name).fix_width(150.))
+                .with_child(
+                    SizedBox::empty()
+                        .fix_size(BAR_WIDTH * count as f64 / max as f64, 12.)
+                        .background(theme::COLOR_3),
+                )
+                .with_spacer(5.)
+                .with_child(Label::new(count.to_string()).with_font(theme::SMALL_FONT)),
+        );
+        chart.add_spacer(2.);
+    }
+    chart.boxed()
+}
+
+/// Get the name of a month like "March 2022"
+fn month_name(month: Month) -> String {
+    match NaiveDate::from_ymd_opt(month.year, month.month, 1) {
+        Some(date) => date.format("%B %Y").to_string(),
+        None => format!("{}-{:02}", month.year, month.month),
+    }
+}
@@ -0,0 +1,103 @@
+//! Recently deleted screen listing recipes in the trash that can be restored or purged
+
+use chrono::Local;
+use druid::{
+    text::format::ParseFormatter,
+    widget::{Button, CrossAxisAlignment, Flex, Label, List, Scroll, TextBox},
+    LensExt, TextAlignment, Widget, WidgetExt,
+};
+
+use crate::gui::{
+    data::{config::Config, trash::TrashItem, AppState},
+    theme,
+    widgets::separator::Separator,
+    EMPTY_TRASH, PURGE_RECIPE, RESTORE_RECIPE,
+};
+
+use super::sidebar;
+
+/// Build the root widget for the recently deleted screen
+pub fn trash_widget() -> impl Widget<AppState> {
+    let title_bar = Flex::row()
+        .with_child(
+            Label::new("Recently Deleted")
+                .with_font(theme::HEADER_FONT)
+                .align_left(),
+        )
+        .with_flex_spacer(1.)
+        .with_child(
+            Button::new("Empty Trash").on_click(|ctx, _data, _env| ctx.submit_command(EMPTY_TRASH)),
+        )
+        .with_spacer(10.);
+
+    Flex::row().with_child(sidebar()).with_flex_child(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(title_bar.fix_height(50.).expand_width())
+            .with_spacer(1.)
+            .with_child(Separator::new(2.5).fix_width(130.))
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(Label::new("Permanently delete recipes after"))
+                    .with_spacer(5.)
+                    .with_child(
+                        TextBox::new()
+                            .with_text_alignment(TextAlignment::Center)
+                            .with_formatter(ParseFormatter::new())
+                            .update_data_while_editing(true)
+                            .fix_width(50.)
+                            .lens(AppState::config.then(Config::trash_days)),
+                    )
+                    .with_spacer(5.)
+                    .with_child(Label::new("days, or 0 to keep them")),
+            )
+            .with_default_spacer()
+            .with_flex_child(
+                Scroll::new(List::new(trash_item_widget).with_spacing(10.))
+                    .vertical()
+                    .lens(AppState::trash),
+                1.0,
+            )
+            .padding((15., 0.))
+            .expand(),
+        1.0,
+    )
+}
+
+/// Build a row showing one deleted recipe with buttons to restore or purge it
+fn trash_item_widget() -> impl Widget<TrashItem> {
+    Flex::row()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Label::dynamic(|item: &TrashItem, _env| item.recipe.name.to_string())
+                        .with_font(theme::LABEL_FONT),
+                )
+                .with_child(
+                    Label::dynamic(|item: &TrashItem, _env| {
+                        format!(
+                            "Deleted {}",
+                            item.deleted_on
+                                .with_timezone(&Local)
+                                .format("%B %e, %Y at %I:%M %p")
+                        )
+                    })
+                    .with_font(theme::SMALL_FONT),
+                ),
+            1.0,
+        )
+        .with_child(
+            Button::new("Restore").on_click(|ctx, item: &mut TrashItem, _env| {
+                ctx.submit_command(RESTORE_RECIPE.with(item.recipe.id))
+            }),
+        )
+        .with_default_spacer()
+        .with_child(
+            Button::new("Delete Forever").on_click(|ctx, item: &mut TrashItem, _env| {
+                ctx.submit_command(PURGE_RECIPE.with(item.recipe.id))
+            }),
+        )
+        .padding((0., 0., 10., 0.))
+}
@@ -0,0 +1,46 @@
+//! Screen asking for the passphrase that recipes are encrypted with before they are loaded
+
+use std::sync::Arc;
+
+use druid::{
+    widget::{Button, Flex, Label, LineBreaking},
+    LensExt, Widget, WidgetExt,
+};
+
+use crate::gui::{
+    data::{passphrase::PassphraseState, AppState},
+    theme,
+    widgets::{masked::masked_text_box, maybe::Maybe, separator::Separator},
+    UNLOCK,
+};
+
+/// Build the root widget for the unlock screen
+pub fn unlock_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(Label::new("Recipes are Encrypted").with_font(theme::HEADER_FONT))
+        .with_spacer(1.)
+        .with_child(Separator::new(2.5).fix_width(130.))
+        .with_default_spacer()
+        .with_child(
+            Label::new("Enter the passphrase that recipes were encrypted with to open them")
+                .with_font(theme::SMALL_FONT)
+                .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_default_spacer()
+        .with_child(
+            masked_text_box("Passphrase")
+                .fix_width(250.)
+                .lens(AppState::passphrase.then(PassphraseState::passphrase)),
+        )
+        .with_default_spacer()
+        .with_child(Button::new("Unlock").on_click(|ctx, _data, _env| ctx.submit_command(UNLOCK)))
+        .with_default_spacer()
+        .with_child(
+            Maybe::or_empty(|| {
+                Label::dynamic(|status: &Arc<str>, _env| status.to_string())
+                    .with_font(theme::SMALL_FONT)
+            })
+            .lens(AppState::passphrase.then(PassphraseState::status)),
+        )
+        .center()
+}
@@ -0,0 +1,77 @@
+//! Controller that saves application state shortly after it stops changing
+
+use std::time::Duration;
+
+use druid::{widget::Controller, Data, Env, Event, EventCtx, TimerToken, UpdateCtx, Widget};
+
+use crate::{
+    gui::{
+        data::{save::SaveStatus, AppState},
+        SET_SAVE_STATUS,
+    },
+    paths::Paths,
+};
+
+/// How long the app state must stay unchanged before it is saved
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(3);
+
+/// Controller wrapping the root widget that saves the app state after it stops changing for
+/// [AUTOSAVE_DELAY]
+pub struct AutosaveController {
+    /// The timer for the next save, reset every time the state changes
+    timer: TimerToken,
+}
+
+impl Default for AutosaveController {
+    /// Create a new autosave controller with no save scheduled
+    fn default() -> Self {
+        Self {
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for AutosaveController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer {
+                let save_file = Paths::get().save_file();
+                log::trace!("Autosaving app state to {}", save_file.display());
+                self.timer = TimerToken::INVALID;
+                data.save_status = SaveStatus::from_result(data.save(save_file));
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        //Changes to the save status alone don't need to be saved
+        let mut old_state = old_data.clone();
+        old_state.save_status = data.save_status.clone();
+        if !old_state.same(data) {
+            self.timer = ctx.request_timer(AUTOSAVE_DELAY);
+            if !matches!(
+                data.save_status,
+                SaveStatus::Pending | SaveStatus::Failed(_)
+            ) {
+                ctx.submit_command(SET_SAVE_STATUS.with(SaveStatus::Pending));
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -0,0 +1,68 @@
+//! Controller that backs up recipes and app state on a schedule
+
+use std::time::Duration;
+
+use druid::{
+    widget::Controller, Env, Event, EventCtx, LifeCycle, LifeCycleCtx, TimerToken, Widget,
+};
+
+use crate::gui::{data::AppState, CREATE_BACKUP};
+
+/// How long after startup to check if a backup is due, so backing up doesn't slow down startup
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+/// How often to check if a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Controller wrapping the root widget that backs up the app when the last backup is older than
+/// the configured interval
+pub struct BackupController {
+    /// The timer for the next check
+    timer: TimerToken,
+}
+
+impl Default for BackupController {
+    /// Create a new backup controller with no check scheduled
+    fn default() -> Self {
+        Self {
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for BackupController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer {
+                if data.backup_due() {
+                    log::trace!("Scheduled backup is due");
+                    ctx.submit_command(CREATE_BACKUP);
+                }
+                self.timer = ctx.request_timer(CHECK_INTERVAL);
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer = ctx.request_timer(STARTUP_DELAY);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,64 @@
+//! Text box that hides the text entered into it, used to enter passphrases
+
+use druid::{
+    lens,
+    widget::{Controller, TextBox},
+    Env, Event, EventCtx, KbKey, Widget, WidgetExt,
+};
+
+/// Character shown in place of every entered character
+const MASK: &str = "\u{2022}";
+
+/// Build a text box that shows a bullet for every entered character instead of the text
+pub fn masked_text_box(placeholder: &str) -> impl Widget<String> {
+    TextBox::new()
+        .with_placeholder(placeholder)
+        .lens(lens::Map::new(
+            |text: &String| MASK.repeat(text.chars().count()),
+            |_text: &mut String, _masked: String| (),
+        ))
+        .controller(MaskController)
+}
+
+/// Controller that edits the hidden text instead of the bullets shown by the text box it wraps.
+/// Characters can only be added to or removed from the end of the hidden text, as the bullets
+/// don't show where anything else would go
+pub struct MaskController;
+
+impl<W: Widget<String>> Controller<String, W> for MaskController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut String,
+        env: &Env,
+    ) {
+        match event {
+            Event::KeyDown(key) if key.key == KbKey::Tab => (),
+            Event::KeyDown(key) if ctx.is_focused() => {
+                let shortcut = key.mods.ctrl() || key.mods.meta();
+                match &key.key {
+                    KbKey::Character(text) if !shortcut => data.push_str(text),
+                    KbKey::Backspace if shortcut => data.clear(),
+                    KbKey::Backspace => {
+                        data.pop();
+                    }
+                    //Nothing else can be copied or moved through without showing the text
+                    _ => (),
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::Paste(clipboard) if ctx.is_focused() => {
+                if let Some(text) = clipboard.get_string() {
+                    data.push_str(text.trim_end_matches(['\r', '\n']));
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+}
@@ -3,9 +3,13 @@ use druid::{
     Data, Env, EventCtx, Widget,
 };
 
+pub mod autosave;
+pub mod backup;
 pub mod icon;
+pub mod masked;
 pub mod maybe;
 pub mod none;
+pub mod recipe_image;
 pub mod separator;
 pub mod unit;
 
@@ -0,0 +1,141 @@
+//! A widget showing a recipe's image that is decoded in the background
+
+use druid::{
+    widget::SizedBox, BoxConstraints, Data, Env, Event, EventCtx, ExtEventSink, ImageBuf,
+    LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Size, Target, UpdateCtx, Widget,
+    WidgetExt, WidgetPod,
+};
+
+use crate::{
+    gui::IMAGE_LOADED,
+    recipes::db::{
+        images::{ImageSize, ImageState},
+        Database, RecipeId,
+    },
+};
+
+/// Get a recipe's image if it is cached, or start decoding it in the background and send
+/// [IMAGE_LOADED] once it is ready
+pub fn load_image(
+    recipes: &Database,
+    id: RecipeId,
+    size: ImageSize,
+    sink: ExtEventSink,
+) -> ImageState {
+    recipes.load_image(id, size, move || {
+        if let Err(e) = sink.submit_command(IMAGE_LOADED, (id, size), Target::Global) {
+            log::error!("Failed to send loaded image for recipe {}: {}", id, e);
+        }
+    })
+}
+
+/// Gets the database and ID of the recipe to show the image of from app data
+type RecipeLookup<T> = Box<dyn Fn(&T) -> Option<(&Database, RecipeId)>>;
+
+/// A widget showing the image of a recipe chosen from app data, with a placeholder shown while
+/// the image is decoded and nothing shown if the recipe has no image
+pub struct RecipeImage<T> {
+    /// Which copy of the image to show
+    size: ImageSize,
+    /// Get the database and ID of the recipe to show the image of
+    recipe: RecipeLookup<T>,
+    /// Build the widget showing a decoded image
+    image: Box<dyn Fn(ImageBuf) -> Box<dyn Widget<T>>>,
+    /// Build the widget shown while the image is decoded
+    placeholder: Box<dyn Fn() -> Box<dyn Widget<T>>>,
+    /// The ID of the recipe whose image is shown
+    shown: Option<RecipeId>,
+    /// The image, placeholder, or empty widget
+    widget: WidgetPod<T, Box<dyn Widget<T>>>,
+}
+
+impl<T: Data> RecipeImage<T> {
+    /// Create a new recipe image widget with an empty placeholder
+    pub fn new<W: Widget<T> + 'static>(
+        size: ImageSize,
+        recipe: impl Fn(&T) -> Option<(&Database, RecipeId)> + 'static,
+        image: impl Fn(ImageBuf) -> W + 'static,
+    ) -> Self {
+        Self {
+            size,
+            recipe: Box::new(recipe),
+            image: Box::new(move |buf| image(buf).boxed()),
+            placeholder: Box::new(|| SizedBox::empty().boxed()),
+            shown: None,
+            widget: WidgetPod::new(SizedBox::empty().boxed()),
+        }
+    }
+
+    /// Builder method to set the widget shown while the image is decoded
+    pub fn with_placeholder<W: Widget<T> + 'static>(
+        mut self,
+        placeholder: impl Fn() -> W + 'static,
+    ) -> Self {
+        self.placeholder = Box::new(move || placeholder().boxed());
+        self
+    }
+
+    /// Replace the shown widget with the current image of the recipe
+    fn rebuild(&mut self, data: &T, sink: ExtEventSink) {
+        let recipe = (self.recipe)(data);
+        self.shown = recipe.map(|(_, id)| id);
+        let state = match recipe {
+            Some((recipes, id)) => load_image(recipes, id, self.size, sink),
+            None => ImageState::Missing,
+        };
+
+        self.widget = WidgetPod::new(match state {
+            ImageState::Ready(buf) => (self.image)(buf),
+            ImageState::Missing => SizedBox::empty().boxed(),
+            ImageState::Loading => (self.placeholder)(),
+        });
+    }
+}
+
+impl<T: Data> Widget<T> for RecipeImage<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Command(cmd) = event {
+            if let Some((id, size)) = cmd.get(IMAGE_LOADED) {
+                if *size == self.size && self.shown == Some(*id) {
+                    self.rebuild(data, ctx.get_external_handle());
+                    ctx.children_changed();
+                    return;
+                }
+            }
+        }
+        self.widget.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild(data, ctx.get_external_handle());
+        }
+        self.widget.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        let changed = match ((self.recipe)(old_data), (self.recipe)(data)) {
+            (Some((old_recipes, old_id)), Some((recipes, id))) => {
+                old_id != id || !old_recipes.same(recipes)
+            }
+            (old, new) => old.is_some() != new.is_some(),
+        };
+
+        if changed {
+            self.rebuild(data, ctx.get_external_handle());
+            ctx.children_changed();
+        } else {
+            self.widget.update(ctx, data, env)
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let size = self.widget.layout(ctx, bc, data, env);
+        self.widget.set_origin(ctx, data, env, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.widget.paint(ctx, data, env)
+    }
+}
@@ -2,11 +2,39 @@
 
 use druid::{widget::Controller, ContextMenu, Event, LocalizedString, MenuDesc, MenuItem, Widget};
 
-use crate::gui::{
-    data::{edit::EditedIngredient, AppState},
-    CHANGE_INGREDIENT_UNIT,
+use crate::{
+    gui::{
+        data::{edit::EditedIngredient, AppState},
+        theme, CHANGE_INGREDIENT_UNIT,
+    },
+    recipes::measure::{VolumeSystem, VolumeUnit},
 };
 
+/// Volume units with an explicit measurement system that differ from each other
+const REGIONAL_UNITS: &[VolumeUnit] = &[
+    VolumeUnit::RegionalCup(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalCup(VolumeSystem::UsLegal),
+    VolumeUnit::RegionalCup(VolumeSystem::Imperial),
+    VolumeUnit::RegionalCup(VolumeSystem::Metric),
+    VolumeUnit::RegionalPint(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalPint(VolumeSystem::Imperial),
+    VolumeUnit::RegionalQuart(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalQuart(VolumeSystem::Imperial),
+    VolumeUnit::RegionalGallon(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalGallon(VolumeSystem::Imperial),
+    VolumeUnit::RegionalTeaspoon(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalTeaspoon(VolumeSystem::UsLegal),
+    VolumeUnit::RegionalTeaspoon(VolumeSystem::Imperial),
+    VolumeUnit::RegionalTeaspoon(VolumeSystem::Metric),
+    VolumeUnit::RegionalTablespoon(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalTablespoon(VolumeSystem::UsLegal),
+    VolumeUnit::RegionalTablespoon(VolumeSystem::Imperial),
+    VolumeUnit::RegionalTablespoon(VolumeSystem::Metric),
+    VolumeUnit::RegionalFluidOz(VolumeSystem::UsCustomary),
+    VolumeUnit::RegionalFluidOz(VolumeSystem::UsLegal),
+    VolumeUnit::RegionalFluidOz(VolumeSystem::Imperial),
+];
+
 /// Widget allowing the user to modify the units of an ingredient amount
 pub struct UnitSelectorController;
 
@@ -30,6 +58,17 @@ impl<W: Widget<EditedIngredient>> Controller<EditedIngredient, W> for UnitSelect
             };
         }
 
+        //Units without a system are labelled with the system they are measured in
+        let system = theme::volume_system(env);
+        macro_rules! volume_item {
+            ($unit:expr, $name:literal) => {
+                MenuItem::new(
+                    LocalizedString::new($name).with_placeholder(format!("{} ({})", $name, system)),
+                    CHANGE_INGREDIENT_UNIT.with((data.id, AmountUnit::Volume($unit))),
+                )
+            };
+        }
+
         let unit_selector = MenuDesc::<AppState>::new(LocalizedString::new("Unit"))
             .append(unit_item!(AmountUnit::Count, "count"))
             .append(unit_item!(AmountUnit::None, "no measure"))
@@ -40,22 +79,26 @@ impl<W: Widget<EditedIngredient>> Controller<EditedIngredient, W> for UnitSelect
                 AmountUnit::Mass(MassUnit::Milligram),
                 "milligram"
             ))
-            .append(unit_item!(AmountUnit::Volume(VolumeUnit::Cup), "cup"))
-            .append(unit_item!(AmountUnit::Volume(VolumeUnit::Teaspoon), "tsp"))
-            .append(unit_item!(
-                AmountUnit::Volume(VolumeUnit::Tablespoon),
-                "tbsp"
-            ))
-            .append(unit_item!(
-                AmountUnit::Volume(VolumeUnit::FluidOz),
-                "fluiz oz"
-            ))
-            .append(unit_item!(AmountUnit::Volume(VolumeUnit::Pint), "pint"))
+            .append(volume_item!(VolumeUnit::Cup, "cup"))
+            .append(volume_item!(VolumeUnit::Teaspoon, "tsp"))
+            .append(volume_item!(VolumeUnit::Tablespoon, "tbsp"))
+            .append(volume_item!(VolumeUnit::FluidOz, "fluiz oz"))
+            .append(volume_item!(VolumeUnit::Pint, "pint"))
             .append(unit_item!(AmountUnit::Volume(VolumeUnit::Liter), "liter"))
-            .append(unit_item!(AmountUnit::Volume(VolumeUnit::Quart), "quart"))
+            .append(volume_item!(VolumeUnit::Quart, "quart"))
+            .append(volume_item!(VolumeUnit::Gallon, "gallon"))
             .append(unit_item!(
                 AmountUnit::Volume(VolumeUnit::Milliliter),
                 "milliliter"
+            ))
+            .append(REGIONAL_UNITS.iter().fold(
+                MenuDesc::new(LocalizedString::new("regional")),
+                |menu, unit| {
+                    menu.append(MenuItem::new(
+                        LocalizedString::new("regional-unit").with_placeholder(unit.to_string()),
+                        CHANGE_INGREDIENT_UNIT.with((data.id, AmountUnit::Volume(*unit))),
+                    ))
+                },
             ));
 
         if let Event::MouseDown(mouse) = event {
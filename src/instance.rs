@@ -0,0 +1,166 @@
+//! Making sure only one instance of the application uses a data directory at a time, and
+//! forwarding requests from instances started later to the running instance
+//!
+//! The running instance holds an exclusive lock on a file in the data directory and listens on a
+//! local socket, writing the port and a secret token to the lock file. Later instances fail to
+//! take the lock, read the port and token, and send their requests to the running instance
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Name of the lock file in the data directory
+const LOCK_FILE: &str = "recipier.lock";
+
+/// How many times to read the lock file of a running instance that has not written it yet
+const READ_RETRIES: usize = 10;
+
+/// A request forwarded from a newly started instance to the running instance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Bring the window of the running instance to the front
+    Focus,
+    /// Open a file given on the command line
+    Open(PathBuf),
+}
+
+/// Exclusive lock on a data directory held by the running instance
+#[derive(Debug)]
+pub struct InstanceLock {
+    /// The locked file, unlocked when closed
+    _file: File,
+    /// The socket that requests from other instances are received on
+    listener: TcpListener,
+    /// Secret that other instances must send before their requests
+    token: String,
+}
+
+/// Another instance that holds the lock on a data directory
+#[derive(Debug)]
+pub struct RunningInstance {
+    /// The port that the running instance listens for requests on
+    port: u16,
+    /// The secret that the running instance expects before requests
+    token: String,
+}
+
+impl InstanceLock {
+    /// Try to lock a data directory, returning the instance that holds the lock if it is already
+    /// locked
+    pub fn acquire(dir: &Path) -> io::Result<Result<Self, RunningInstance>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))?;
+
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => return RunningInstance::read(&mut file).map(Err),
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let token = Uuid::new_v4().to_string();
+        file.set_len(0)?;
+        writeln!(file, "{}\n{}", listener.local_addr()?.port(), token)?;
+        file.sync_all()?;
+
+        Ok(Ok(Self {
+            _file: file,
+            listener,
+            token,
+        }))
+    }
+
+    /// Handle requests from other instances in the background for as long as the program runs
+    pub fn listen(&self, on_request: impl Fn(Request) + Send + Clone + 'static) -> io::Result<()> {
+        let listener = self.listener.try_clone()?;
+        let token = self.token.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept connection from another instance: {}", e);
+                        continue;
+                    }
+                };
+
+                let token = token.clone();
+                let on_request = on_request.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = Self::handle(stream, &token, on_request) {
+                        log::warn!("Failed to receive requests from another instance: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Read the token and requests sent by another instance
+    fn handle(stream: TcpStream, token: &str, on_request: impl Fn(Request)) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut lines = BufReader::new(stream).lines();
+        if lines.next().transpose()?.as_deref() != Some(token) {
+            log::warn!("Rejected connection from another instance with the wrong token");
+            return Ok(());
+        }
+
+        for line in lines {
+            let request = serde_json::from_str::<Request>(&line?)?;
+            log::trace!("Received request {:?} from another instance", request);
+            on_request(request);
+        }
+
+        Ok(())
+    }
+}
+
+impl RunningInstance {
+    /// Read the port and token of the running instance from its lock file
+    fn read(file: &mut File) -> io::Result<Self> {
+        for _ in 0..READ_RETRIES {
+            let mut contents = String::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_string(&mut contents)?;
+
+            let mut lines = contents.lines();
+            if let (Some(Ok(port)), Some(token)) = (lines.next().map(str::parse), lines.next()) {
+                return Ok(Self {
+                    port,
+                    token: token.to_owned(),
+                });
+            }
+
+            //The running instance may have just started and not written the lock file yet
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "lock file of running instance is invalid",
+        ))
+    }
+
+    /// Send requests to the running instance
+    pub fn forward(&self, requests: &[Request]) -> io::Result<()> {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port))?;
+        writeln!(stream, "{}", self.token)?;
+        for request in requests {
+            serde_json::to_writer(&mut stream, request)?;
+            writeln!(stream)?;
+        }
+        stream.flush()
+    }
+}
@@ -1,21 +1,27 @@
 // Disable console on windows
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+pub mod cli;
 pub mod gui;
+pub mod instance;
+pub mod paths;
 pub mod recipes;
 pub mod update;
 
-use druid::{AppLauncher, WindowDesc, WindowState};
-use gui::{data::AppState, root_widget};
+use cli::Args;
+use druid::{commands::SHOW_WINDOW, AppLauncher, Target, WindowDesc, WindowState};
+use gui::{
+    data::{loading, AppState},
+    root_widget, OPEN_PATH,
+};
+use instance::{InstanceLock, Request};
 use lazy_static::lazy_static;
 use log::LevelFilter;
+use paths::Paths;
 use semver::Version;
 use simplelog::ConfigBuilder;
 use update::autoupdate;
 
-/// The file name to save and load application data from
-pub const SAVE_FILE: &str = "./save.json";
-
 /// The target triple this application was compiled for
 pub const TARGET_TRIPLE: &str = env!("TARGET_TRIPLE");
 
@@ -44,7 +50,54 @@ fn main() {
         );
     }));
 
-    match std::fs::File::create("./recipier.log") {
+    let args = match Args::from_env() {
+        Ok(args) if args.help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let paths = Paths::resolve(args.data_dir.clone(), args.portable).init();
+
+    //Lock the data directory before opening the log file so another instance's log is kept
+    let lock = match InstanceLock::acquire(&paths.data) {
+        Ok(Ok(lock)) => Some(lock),
+        Ok(Err(running)) => {
+            if args.check || args.repair {
+                eprintln!("Recipier is already running, close it before checking recipes");
+                std::process::exit(1);
+            }
+
+            let mut requests = args
+                .files
+                .iter()
+                .map(|file| Request::Open(file.canonicalize().unwrap_or_else(|_| file.clone())))
+                .collect::<Vec<_>>();
+            requests.push(Request::Focus);
+            match running.forward(&requests) {
+                Ok(()) => return,
+                Err(e) => {
+                    eprintln!("Recipier is already running but can't be reached: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to lock data directory {}: {}",
+                paths.data.display(),
+                e
+            );
+            None
+        }
+    };
+
+    let log_file = paths.log_file();
+    match std::fs::File::create(&log_file) {
         Ok(log) => {
             if let Err(e) = simplelog::WriteLogger::init(
                 LevelFilter::max(),
@@ -67,23 +120,66 @@ fn main() {
                 );
             }
             log::info!(
-                "failed to open log file at ./recipier.log: {}, logging to terminal instead",
+                "failed to open log file at {}: {}, logging to terminal instead",
+                log_file.display(),
                 e
             );
         }
     }
 
-    let state = AppState::init(SAVE_FILE);
+    log::info!("Using data directories {:?}", paths);
+    paths.migrate_from_working_dir();
+
+    let state = AppState::init(paths.save_file());
+    if args.check || args.repair {
+        std::process::exit(check_database(&state, args.repair));
+    }
 
     let window = WindowDesc::new(root_widget)
         .resizable(true)
         .title("Recipier")
         .set_window_state(WindowState::RESTORED)
         .window_size(state.config.window_size);
+    let window_id = window.id;
+
+    let launcher =
+        AppLauncher::with_window(window).configure_env(|env, _state| gui::theme::set(env));
+    let delegate = gui::handler::RecipierDelegate::new(launcher.get_external_handle());
+    let launcher = launcher.delegate(delegate);
+    let watch_sink = launcher.get_external_handle();
+    let _watcher = state.recipes.watch(move |ids| {
+        if let Err(e) = watch_sink.submit_command(gui::RECIPES_CHANGED, ids, Target::Global) {
+            log::error!("Failed to send changed recipes to the main window: {}", e);
+        }
+    });
+
+    loading::spawn(
+        state.recipes.clone(),
+        state.config.trash_days,
+        launcher.get_external_handle(),
+    );
+
+    let open_sink = launcher.get_external_handle();
+    for file in args.files {
+        if let Err(e) = open_sink.submit_command(OPEN_PATH, file, Target::Global) {
+            log::error!("Failed to open file given on the command line: {}", e);
+        }
+    }
+    if let Some(ref lock) = lock {
+        let listen = lock.listen(move |request| {
+            let sent = match request {
+                Request::Focus => open_sink.submit_command(SHOW_WINDOW, (), window_id),
+                Request::Open(path) => open_sink.submit_command(OPEN_PATH, path, Target::Global),
+            };
+            if let Err(e) = sent {
+                log::error!("Failed to handle request from another instance: {}", e);
+            }
+        });
+        if let Err(e) = listen {
+            log::error!("Failed to listen for requests from other instances: {}", e);
+        }
+    }
 
-    let launcher = AppLauncher::with_window(window)
-        .configure_env(|env, _state| gui::theme::set(env))
-        .delegate(gui::handler::RecipierDelegate);
     let event_sink = launcher.get_external_handle();
     if !state.config.no_update_check {
         std::thread::spawn(move || {
@@ -96,4 +192,28 @@ fn main() {
     if let Err(e) = launcher.launch(state) {
         panic!("Failed to launch app: {}", e);
     }
+    drop(lock);
+}
+
+/// Check the recipe database for problems and print them, returning the exit code of the check
+fn check_database(state: &AppState, repair: bool) -> i32 {
+    let findings = state.recipes.check(repair);
+    for finding in findings.iter() {
+        println!("{}", finding);
+    }
+
+    let unrepaired = findings
+        .iter()
+        .filter(|finding| !finding.repair.is_repaired())
+        .count();
+    println!(
+        "{} problems found, {} repaired",
+        findings.len(),
+        findings.len() - unrepaired
+    );
+
+    match unrepaired {
+        0 => 0,
+        _ => 1,
+    }
 }
@@ -0,0 +1,179 @@
+//! Locations of the files that the application saves, resolved once at startup from the
+//! per-user directories of the platform, a `--data-dir` option, or portable mode
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use directories::ProjectDirs;
+
+/// Name of the file that app state is saved to in the config directory
+const SAVE_FILE: &str = "save.json";
+
+/// Name of the log file in the cache directory
+const LOG_FILE: &str = "recipier.log";
+
+/// Name of the file that enables portable mode when it is placed next to the executable
+const PORTABLE_MARKER: &str = "portable";
+
+/// Name of the directory in the data directory that backup snapshots are kept in
+const BACKUP_DIR: &str = "backups";
+
+/// The directories resolved at startup, see [Paths::init]
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// The directories that application data, configuration, and caches are kept in
+#[derive(Clone, Debug)]
+pub struct Paths {
+    /// Directory holding the recipe database
+    pub data: PathBuf,
+    /// Directory holding saved app state and settings
+    pub config: PathBuf,
+    /// Directory holding logs and other files that can be regenerated
+    pub cache: PathBuf,
+}
+
+impl Paths {
+    /// Keep all files in one directory
+    pub fn single(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref().to_owned();
+        Self {
+            data: dir.clone(),
+            config: dir.clone(),
+            cache: dir,
+        }
+    }
+
+    /// Get the per-user directories of the platform, like `~/.local/share/recipier` on Linux
+    pub fn user() -> Option<Self> {
+        let dirs = ProjectDirs::from("", "", "recipier")?;
+        Some(Self {
+            data: dirs.data_dir().to_owned(),
+            config: dirs.config_dir().to_owned(),
+            cache: dirs.cache_dir().to_owned(),
+        })
+    }
+
+    /// Get the directory that the executable is in, where files are kept in portable mode
+    pub fn portable() -> Option<Self> {
+        let exe = std::env::current_exe().ok()?;
+        exe.parent().map(Self::single)
+    }
+
+    /// Check if portable mode was enabled by placing a marker file next to the executable
+    pub fn portable_marker_exists() -> bool {
+        Self::portable()
+            .map(|paths| paths.data.join(PORTABLE_MARKER).exists())
+            .unwrap_or(false)
+    }
+
+    /// Choose the directories to use from the command line options, falling back to the current
+    /// directory if the per-user directories can't be found
+    pub fn resolve(data_dir: Option<PathBuf>, portable: bool) -> Self {
+        if let Some(dir) = data_dir {
+            return Self::single(dir);
+        }
+        if portable || Self::portable_marker_exists() {
+            match Self::portable() {
+                Some(paths) => return paths,
+                None => log::error!("Failed to find executable directory for portable mode"),
+            }
+        }
+
+        Self::user().unwrap_or_else(|| Self::single("."))
+    }
+
+    /// Set the directories used for the rest of the program, creating them if needed. Has no
+    /// effect if the directories were already set
+    pub fn init(self) -> &'static Self {
+        for dir in [&self.data, &self.config, &self.cache] {
+            if let Err(e) = fs::create_dir_all(dir) {
+                eprintln!("Failed to create directory {}: {}", dir.display(), e);
+            }
+        }
+        PATHS.get_or_init(|| self)
+    }
+
+    /// Get the directories set by [Paths::init], or the current directory if they were never set
+    pub fn get() -> &'static Self {
+        PATHS.get_or_init(|| Self::single("."))
+    }
+
+    /// Get the file that app state is saved to
+    pub fn save_file(&self) -> PathBuf {
+        self.config.join(SAVE_FILE)
+    }
+
+    /// Get the file that logs are written to
+    pub fn log_file(&self) -> PathBuf {
+        self.cache.join(LOG_FILE)
+    }
+
+    /// Get the directory that backup snapshots are kept in
+    pub fn backup_dir(&self) -> PathBuf {
+        self.data.join(BACKUP_DIR)
+    }
+
+    /// Resolve a path from the config relative to the data directory
+    pub fn data_path(&self, path: &Path) -> PathBuf {
+        self.data.join(path)
+    }
+
+    /// Copy app state and recipes saved in the current directory by older versions into these
+    /// directories if nothing has been saved to them yet
+    pub fn migrate_from_working_dir(&self) {
+        let old_save = Path::new(".").join(SAVE_FILE);
+        let new_save = self.save_file();
+        if new_save.exists() || !old_save.exists() || same_file(&old_save, &new_save) {
+            return;
+        }
+
+        log::info!(
+            "Moving app state from {} to {}",
+            old_save.display(),
+            new_save.display()
+        );
+        if let Err(e) = fs::copy(&old_save, &new_save) {
+            log::error!("Failed to copy app state to {}: {}", new_save.display(), e);
+            return;
+        }
+
+        let old_recipes = Path::new("recipes");
+        let new_recipes = self.data.join("recipes");
+        if old_recipes.is_dir() && !new_recipes.exists() {
+            log::info!(
+                "Copying recipes from {} to {}",
+                old_recipes.display(),
+                new_recipes.display()
+            );
+            if let Err(e) = copy_dir(old_recipes, &new_recipes) {
+                log::error!("Failed to copy recipes to {}: {}", new_recipes.display(), e);
+            }
+        }
+    }
+}
+
+/// Check if two paths point to the same existing file
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Recursively copy a directory and all of its contents
+pub fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Crash-safe file saving that never leaves a partially written file in place of a good one
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use tempfile::NamedTempFile;
+
+/// Get the path that the last good copy of a file is kept at
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Write a file by writing to a temporary file in the same directory, syncing it to disk, and
+/// renaming it over the original file in one step. If `valid` accepts the contents of the file
+/// that is replaced, it is kept at its [backup path](backup_path), otherwise the last good copy is
+/// left alone
+pub fn write(
+    path: &Path,
+    valid: impl FnOnce(&[u8]) -> bool,
+    contents: impl FnOnce(&mut BufWriter<&mut File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut temp = NamedTempFile::new_in(dir)?;
+
+    let mut writer = BufWriter::new(temp.as_file_mut());
+    contents(&mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    temp.as_file().sync_all()?;
+
+    match fs::read(path) {
+        Ok(current) if valid(&current) => keep_backup(path)?,
+        Ok(_) => log::warn!(
+            "Not keeping invalid file {} as its last good copy",
+            path.display()
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+    temp.persist(path).map_err(|e| e.error)?;
+
+    //Make sure the renames are on disk as well, not supported on all platforms
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Make the current contents of a file its last good copy. The backup is a hard link to the file
+/// where supported so that the file itself is never missing, and a copy otherwise
+fn keep_backup(path: &Path) -> io::Result<()> {
+    let backup = backup_path(path);
+    match fs::remove_file(&backup) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    if fs::hard_link(path, &backup).is_err() {
+        fs::copy(path, &backup)?;
+    }
+    Ok(())
+}
+
+/// Securely remove the last good copy of a file. A backup with the same contents as the file
+/// may be a link to it and is only removed
+pub fn remove_backup(path: &Path) -> io::Result<()> {
+    let backup = backup_path(path);
+    let linked = match (fs::read(&backup), fs::read(path)) {
+        (Err(e), _) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        (Err(e), _) => return Err(e),
+        (Ok(backup), Ok(current)) => backup == current,
+        (Ok(_), Err(_)) => false,
+    };
+    match linked {
+        true => fs::remove_file(backup),
+        false => shred(&backup),
+    }
+}
+
+/// Overwrite a file with zeros and sync it to disk before removing it, so that its contents are
+/// not left behind in free disk space
+pub fn shred(path: &Path) -> io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    io::copy(&mut io::repeat(0).take(len), &mut file)?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+/// Read and parse a file, recovering from the last good copy if the file is missing or can't be
+/// parsed
+pub fn read<T, E>(path: &Path, parse: impl Fn(BufReader<File>) -> Result<T, E>) -> io::Result<T>
+where
+    io::Error: From<E>,
+{
+    let read = |path: &Path| -> io::Result<T> { Ok(parse(BufReader::new(File::open(path)?))?) };
+
+    match read(path) {
+        Ok(val) => Ok(val),
+        Err(e) => {
+            let backup = backup_path(path);
+            if !backup.exists() {
+                return Err(e);
+            }
+
+            log::warn!(
+                "Failed to read {}: {}, recovering from last good copy {}",
+                path.display(),
+                e,
+                backup.display()
+            );
+            read(&backup).map_err(|backup_err| {
+                log::error!(
+                    "Failed to recover from last good copy {}: {}",
+                    backup.display(),
+                    backup_err
+                );
+                e
+            })
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Snapshots of every stored recipe and the app state in zip archives, rotated so only the most
+//! recent snapshots are kept
+//!
+//! Archives hold the app state as `save.json` and every recipe as `recipes/<id>/save.json` with
+//! its image as `recipes/<id>/img.jpg`, the layout of recipe directories, whatever backend the
+//! recipes are stored in
+
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use tempfile::NamedTempFile;
+use thiserror::Error;
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{
+    crypt,
+    storage::{Storage, StorageError},
+    RecipeId,
+};
+
+/// Start of the file name of every snapshot, followed by the time it was created and a sequence
+/// number if another snapshot was created in the same second
+const PREFIX: &str = "backup-";
+
+/// Extension of snapshot archives
+const EXTENSION: &str = "zip";
+
+/// Format of the creation time in snapshot file names, in UTC
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Name of the app state in a snapshot
+const STATE_ENTRY: &str = "save.json";
+
+/// Folder holding one folder of recipe data per recipe in a snapshot
+const RECIPES_DIR: &str = "recipes";
+
+/// Name of a recipe's saved data in its folder
+const RECIPE_ENTRY: &str = "save.json";
+
+/// Name of a recipe's image in its folder
+const IMAGE_ENTRY: &str = "img.jpg";
+
+/// Name of a recipe's image in snapshots created before images were saved as JPEG
+const LEGACY_IMAGE_ENTRY: &str = "img.png";
+
+/// A snapshot archive in the backup directory
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// Path to the archive
+    pub path: PathBuf,
+    /// When the snapshot was created, read from its file name
+    pub created: DateTime<Utc>,
+    /// Number telling apart snapshots created in the same second, 0 for the first
+    sequence: u32,
+}
+
+impl Snapshot {
+    /// Get every snapshot in a backup directory, newest first
+    pub fn list(dir: &Path) -> io::Result<Vec<Self>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut snapshots = entries
+            .filter_map(|entry| Self::from_path(entry.ok()?.path()))
+            .collect::<Vec<_>>();
+        snapshots.sort_by_key(|snapshot| Reverse((snapshot.created, snapshot.sequence)));
+        Ok(snapshots)
+    }
+
+    /// Get the most recent snapshot in a backup directory
+    pub fn latest(dir: &Path) -> Option<Self> {
+        Self::list(dir).ok()?.into_iter().next()
+    }
+
+    /// Read the creation time of a snapshot from its file name, returning `None` if the file is
+    /// not a snapshot
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        if path.extension()? != EXTENSION {
+            return None;
+        }
+        let name = path.file_stem()?.to_str()?.strip_prefix(PREFIX)?;
+        //The time has one dash between the date and time of day
+        let (time, sequence) = match name.match_indices('-').nth(1) {
+            Some((i, _)) => (&name[..i], name[i + 1..].parse().ok()?),
+            None => (name, 0),
+        };
+        let created = NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok()?;
+        Some(Self {
+            created: Utc.from_utc_datetime(&created),
+            sequence,
+            path,
+        })
+    }
+
+    /// Read the app state saved in this snapshot
+    pub fn state(&self) -> Result<Option<Vec<u8>>, BackupError> {
+        read_entry(&mut self.open()?, STATE_ENTRY)
+    }
+
+    /// Get the IDs of every recipe in this snapshot without reading their data
+    pub fn ids(&self) -> Result<Vec<RecipeId>, BackupError> {
+        Ok(self.open()?.file_names().filter_map(recipe_id).collect())
+    }
+
+    /// Read the saved data of every recipe in this snapshot
+    pub fn recipes(&self) -> Result<Vec<(RecipeId, Vec<u8>)>, BackupError> {
+        let mut archive = self.open()?;
+        let ids = archive
+            .file_names()
+            .filter_map(recipe_id)
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .map(|id| {
+                let data = read_entry(&mut archive, &entry_name(id, RECIPE_ENTRY))?;
+                Ok((id, data.unwrap_or_default()))
+            })
+            .collect()
+    }
+
+    /// Read the saved data of one recipe in this snapshot
+    pub fn recipe(&self, id: RecipeId) -> Result<Option<Vec<u8>>, BackupError> {
+        read_entry(&mut self.open()?, &entry_name(id, RECIPE_ENTRY))
+    }
+
+    /// Read the encoded image of one recipe in this snapshot
+    pub fn image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, BackupError> {
+        let mut archive = self.open()?;
+        match read_entry(&mut archive, &entry_name(id, IMAGE_ENTRY))? {
+            Some(image) => Ok(Some(image)),
+            None => read_entry(&mut archive, &entry_name(id, LEGACY_IMAGE_ENTRY)),
+        }
+    }
+
+    /// Check if any recipe or image in this snapshot was backed up as plain data instead of
+    /// encrypted
+    pub fn has_plain_recipes(&self) -> Result<bool, BackupError> {
+        let mut archive = self.open()?;
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() || !file.name().starts_with(RECIPES_DIR) {
+                continue;
+            }
+            let mut data = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut data)?;
+            if !crypt::is_encrypted(&data) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Open the archive to read entries from it
+    fn open(&self) -> Result<ZipArchive<File>, BackupError> {
+        Ok(ZipArchive::new(File::open(&self.path)?)?)
+    }
+}
+
+/// Write a snapshot of every recipe in a storage backend and the given saved app state to the
+/// backup directory. The archive is written to a temporary file first so a failed backup never
+/// leaves a broken snapshot behind, and is never moved over an existing snapshot
+pub fn create(storage: &dyn Storage, state: &[u8], dir: &Path) -> Result<Snapshot, BackupError> {
+    fs::create_dir_all(dir)?;
+    let created = Utc::now();
+    let mut temp = NamedTempFile::new_in(dir)?;
+    let mut zip = ZipWriter::new(temp.as_file_mut());
+    let options = FileOptions::default();
+    //Images are already compressed
+    let image_options = options.compression_method(CompressionMethod::Stored);
+
+    zip.start_file(STATE_ENTRY, options)?;
+    zip.write_all(state)?;
+    for (id, data) in storage.load_all()? {
+        zip.start_file(entry_name(id, RECIPE_ENTRY), options)?;
+        zip.write_all(&data)?;
+        if let Some(image) = storage.get_image(id)? {
+            zip.start_file(entry_name(id, IMAGE_ENTRY), image_options)?;
+            zip.write_all(&image)?;
+        }
+    }
+    zip.finish()?;
+    drop(zip);
+
+    temp.as_file().sync_all()?;
+    let mut sequence = 0;
+    loop {
+        let path = dir.join(file_name(created, sequence));
+        match temp.persist_noclobber(&path) {
+            Ok(_) => {
+                return Ok(Snapshot {
+                    path,
+                    created,
+                    sequence,
+                })
+            }
+            Err(e) if e.error.kind() == io::ErrorKind::AlreadyExists => {
+                temp = e.file;
+                sequence += 1;
+            }
+            Err(e) => return Err(e.error.into()),
+        }
+    }
+}
+
+/// Delete the oldest snapshots in a backup directory so that at most `keep` are left, returning
+/// the number of snapshots deleted
+pub fn rotate(dir: &Path, keep: usize) -> Result<usize, BackupError> {
+    let mut deleted = 0;
+    for snapshot in Snapshot::list(dir)?.into_iter().skip(keep) {
+        log::trace!("Deleting old backup {}", snapshot.path.display());
+        fs::remove_file(&snapshot.path)?;
+        deleted += 1;
+    }
+    Ok(deleted)
+}
+
+/// Get the file name of a snapshot created at the given time, with the sequence number added if
+/// it is not the first snapshot created in that second
+fn file_name(created: DateTime<Utc>, sequence: u32) -> String {
+    match sequence {
+        0 => format!("{}{}.{}", PREFIX, created.format(TIME_FORMAT), EXTENSION),
+        _ => format!(
+            "{}{}-{}.{}",
+            PREFIX,
+            created.format(TIME_FORMAT),
+            sequence,
+            EXTENSION
+        ),
+    }
+}
+
+/// Get the name of a file in a recipe's folder in a snapshot
+fn entry_name(id: RecipeId, file: &str) -> String {
+    format!("{}/{}/{}", RECIPES_DIR, id, file)
+}
+
+/// Get the ID of the recipe that an entry holds the saved data of
+fn recipe_id(name: &str) -> Option<RecipeId> {
+    let mut parts = name.split('/');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(RECIPES_DIR), Some(id), Some(RECIPE_ENTRY), None) => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Read one entry of an archive, returning `None` if it does not exist
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<Vec<u8>>, BackupError> {
+    match archive.by_name(name) {
+        Ok(mut file) => {
+            let mut data = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut data)?;
+            Ok(Some(data))
+        }
+        Err(ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Enumeration defining all errors that can occur when creating or restoring snapshots
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("Input/output error: {}", .0)]
+    Io(#[from] io::Error),
+
+    #[error("Failed to read or write backup archive: {}", .0)]
+    Zip(#[from] ZipError),
+
+    #[error("{}", .0)]
+    Storage(#[from] StorageError),
+
+    #[error("Backup does not contain recipe {}", .0)]
+    MissingRecipe(RecipeId),
+
+    #[error("Recipe {} in the backup can't be read: {}", .id, .error)]
+    Unreadable { id: RecipeId, error: String },
+}
@@ -0,0 +1,117 @@
+//! Changes to many recipes that are [committed](super::Database::commit) to a database together,
+//! so that either every change is made or none are
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use super::RecipeId;
+use crate::recipes::recipe::Recipe;
+
+/// One change staged in a [Batch]
+#[derive(Clone, Debug)]
+pub(super) enum Change {
+    /// Add a recipe, replacing any recipe with the same ID
+    Insert(Arc<Recipe>),
+    /// Replace a recipe that is in the database
+    Update(Arc<Recipe>),
+    /// Move a recipe to the trash
+    Remove(RecipeId),
+    /// Replace the image of a recipe with encoded image data
+    Image(RecipeId, Vec<u8>),
+    /// Delete the image of a recipe
+    DeleteImage(RecipeId),
+}
+
+/// Inserts, updates, and removals of recipes and their images staged to be committed together with
+/// [Database::commit](super::Database::commit). Changes are applied in the order they were
+/// staged
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    /// Every staged change
+    pub(super) changes: Vec<Change>,
+}
+
+impl Batch {
+    /// Create a new empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage a recipe to be inserted, replacing any recipe with the same ID
+    pub fn insert(&mut self, recipe: Recipe) -> &mut Self {
+        self.changes.push(Change::Insert(Arc::new(recipe)));
+        self
+    }
+
+    /// Stage new data for a recipe, ignored if the recipe is not in the database
+    pub fn update(&mut self, recipe: Arc<Recipe>) -> &mut Self {
+        self.changes.push(Change::Update(recipe));
+        self
+    }
+
+    /// Stage a recipe to be moved to the trash
+    pub fn remove(&mut self, id: RecipeId) -> &mut Self {
+        self.changes.push(Change::Remove(id));
+        self
+    }
+
+    /// Stage encoded image data to replace the image of a recipe, ignored if the recipe is not in
+    /// the database once the other changes are made
+    pub fn image(&mut self, id: RecipeId, data: Vec<u8>) -> &mut Self {
+        self.changes.push(Change::Image(id, data));
+        self
+    }
+
+    /// Stage the image of a recipe to be deleted, ignored if the recipe is not in the database once
+    /// the other changes are made
+    pub fn delete_image(&mut self, id: RecipeId) -> &mut Self {
+        self.changes.push(Change::DeleteImage(id));
+        self
+    }
+
+    /// Get the number of staged changes
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Check if no changes are staged
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// A recipe in a committed batch, serialized and ready to be written to storage
+pub(super) enum Staged {
+    /// Write a recipe with its sealed saved data and the hash of the data before it was sealed
+    Put(Arc<Recipe>, Vec<u8>, u64),
+    /// Move a recipe to the trash
+    Remove(RecipeId),
+    /// Write the sealed image of a recipe
+    Image(RecipeId, Vec<u8>),
+    /// Delete the image of a recipe
+    DeleteImage(RecipeId),
+    /// Replace the saved data of a recipe with data that is already sealed, without changing the
+    /// recipe itself
+    Sealed(RecipeId, Vec<u8>),
+    /// Replace the saved data and image of a recipe in the trash with data that is already
+    /// sealed, keeping it in the trash
+    Trashed {
+        id: RecipeId,
+        deleted_on: DateTime<Utc>,
+        data: Vec<u8>,
+        image: Option<Vec<u8>>,
+    },
+}
+
+/// How to undo one write made while committing a batch
+pub(super) enum Undo {
+    /// Put back the data that was stored for a recipe, or delete it if nothing was stored
+    Put(RecipeId, Option<Vec<u8>>),
+    /// Move a recipe back out of the trash
+    Trash(RecipeId),
+    /// Put back the image that was stored for a recipe, or delete it if there was none
+    Image(RecipeId, Option<Vec<u8>>),
+    /// Move a recipe that was taken out of the trash back in, with the time it was deleted
+    Restore(RecipeId, DateTime<Utc>),
+}
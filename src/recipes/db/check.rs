@@ -0,0 +1,181 @@
+//! Integrity checking and repair of stored recipes
+
+use std::{fmt, path::PathBuf};
+
+use serde_json::Value;
+
+use super::{
+    crypt,
+    migrate::{self, MigrationError},
+    storage::Storage,
+    RecipeId,
+};
+use crate::recipes::recipe::Recipe;
+
+/// A problem found in stored recipe data
+#[derive(Clone, Debug)]
+pub enum Issue {
+    /// An entry in the recipe directory that is not named after a recipe ID
+    NotRecipe(PathBuf),
+    /// A recipe whose saved data can't be read or parsed
+    Unreadable { id: RecipeId, error: String },
+    /// A recipe saved by a newer version of Recipier with a format version this version can't
+    /// read, which is never repaired as the data is fine
+    TooNew { id: RecipeId, found: u64 },
+    /// A recipe stored under one ID whose saved data has a different ID
+    IdMismatch { stored: RecipeId, saved: RecipeId },
+    /// An image stored for a recipe that has no saved data
+    OrphanImage(RecipeId),
+    /// A file left in a recipe folder that is not part of a recipe, like an interrupted save
+    StrayFile(PathBuf),
+}
+
+impl Issue {
+    /// Get the ID of the recipe this issue is about, if it is about one recipe
+    pub fn id(&self) -> Option<RecipeId> {
+        match self {
+            Self::Unreadable { id, .. } | Self::TooNew { id, .. } | Self::OrphanImage(id) => {
+                Some(*id)
+            }
+            Self::IdMismatch { stored, .. } => Some(*stored),
+            Self::NotRecipe(_) | Self::StrayFile(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRecipe(path) => write!(f, "{} is not a recipe", path.display()),
+            Self::Unreadable { id, error } => write!(f, "Recipe {} can't be read: {}", id, error),
+            Self::TooNew { id, found } => write!(
+                f,
+                "Recipe {} was saved by a newer version of Recipier with format version {}, update Recipier to open it",
+                id, found
+            ),
+            Self::IdMismatch { stored, saved } => write!(
+                f,
+                "Recipe stored as {} has the ID {} in its saved data",
+                stored, saved
+            ),
+            Self::OrphanImage(id) => write!(f, "Image for recipe {} has no recipe", id),
+            Self::StrayFile(path) => write!(f, "Stray file {}", path.display()),
+        }
+    }
+}
+
+/// What was done about an issue when repairing
+#[derive(Clone, Debug)]
+pub enum Repair {
+    /// The problem was left in place, because only checking was requested or it can't be fixed
+    None,
+    /// The entry was moved to the given path in the quarantine folder
+    Quarantined(PathBuf),
+    /// The saved data was corrected
+    Fixed,
+    /// The stray file was deleted
+    Removed,
+    /// Repairing the problem failed
+    Failed(String),
+}
+
+impl Repair {
+    /// Check if the problem no longer exists after this repair
+    pub fn is_repaired(&self) -> bool {
+        matches!(self, Self::Quarantined(_) | Self::Fixed | Self::Removed)
+    }
+}
+
+/// An issue found by an integrity check and what was done about it
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// The problem that was found
+    pub issue: Issue,
+    /// What was done to repair the problem
+    pub repair: Repair,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repair {
+            Repair::None => write!(f, "{}", self.issue),
+            Repair::Quarantined(path) => {
+                write!(f, "{}, moved to {}", self.issue, path.display())
+            }
+            Repair::Fixed => write!(f, "{}, fixed", self.issue),
+            Repair::Removed => write!(f, "{}, removed", self.issue),
+            Repair::Failed(e) => write!(f, "{}, failed to repair: {}", self.issue, e),
+        }
+    }
+}
+
+/// Check the saved data of one recipe, returning the issue with it if there is one. Encrypted
+/// recipes can't be checked without the passphrase and are assumed to be fine
+pub fn check_recipe(id: RecipeId, data: &[u8]) -> Option<Issue> {
+    if crypt::is_encrypted(data) {
+        return None;
+    }
+
+    let migrated = match migrate::migrate_recipe(data) {
+        Ok(migrated) => migrated,
+        Err(MigrationError::TooNew { found, .. }) => return Some(Issue::TooNew { id, found }),
+        Err(e) => {
+            return Some(Issue::Unreadable {
+                id,
+                error: e.to_string(),
+            })
+        }
+    };
+
+    match serde_json::from_value::<Recipe>(migrated.value) {
+        Ok(recipe) if recipe.id != id => Some(Issue::IdMismatch {
+            stored: id,
+            saved: recipe.id,
+        }),
+        Ok(_) => None,
+        Err(e) => Some(Issue::Unreadable {
+            id,
+            error: e.to_string(),
+        }),
+    }
+}
+
+/// Replace the ID in saved recipe data with the ID it is stored as
+pub fn fix_id(id: RecipeId, data: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let mut value = serde_json::from_slice::<Value>(data)?;
+    if let Value::Object(ref mut object) = value {
+        object.insert("id".to_owned(), serde_json::to_value(id)?);
+    }
+    serde_json::to_vec(&value)
+}
+
+/// Check every recipe that a storage backend can load, fixing mismatched IDs if `repair` is
+/// true. Backends that can find problems with how data is laid out override
+/// [Storage::check] with a more thorough check
+pub fn check_storage<S: Storage + ?Sized>(storage: &S, repair: bool) -> Vec<Finding> {
+    let recipes = match storage.load_all() {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            log::error!("Failed to load recipes to check: {}", e);
+            return vec![];
+        }
+    };
+
+    recipes
+        .into_iter()
+        .filter_map(|(id, data)| {
+            let issue = check_recipe(id, &data)?;
+            let repair = match (&issue, repair) {
+                (Issue::IdMismatch { .. }, true) => match fix_id(id, &data)
+                    .map_err(Into::into)
+                    .and_then(|fixed| storage.put(id, &fixed))
+                {
+                    Ok(()) => Repair::Fixed,
+                    Err(e) => Repair::Failed(e.to_string()),
+                },
+                _ => Repair::None,
+            };
+            Some(Finding { issue, repair })
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+//! Recipes that were changed in two places at once and need the user to choose which copy to keep
+
+use std::sync::Arc;
+
+use crate::recipes::recipe::Recipe;
+
+use super::RecipeId;
+
+/// A recipe that has unsaved changes in the database and was also changed somewhere else
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    /// The ID of the conflicting recipe
+    pub id: RecipeId,
+    /// The other copy of the recipe, or `None` if it was deleted
+    pub theirs: Option<Arc<Recipe>>,
+    /// The encoded image of the other copy if it is not already in storage, like the image of an
+    /// imported recipe
+    pub image: Option<Arc<[u8]>>,
+    /// Where the other copy of the recipe came from
+    pub source: ConflictSource,
+}
+
+/// Where the other copy of a conflicting recipe came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictSource {
+    /// The recipe was changed in storage by another program, like a file syncing tool
+    Storage,
+    /// The recipe was imported from another cookbook with a copy that differs from ours
+    Import,
+}
+
+/// How to resolve a conflict between the database's copy of a recipe and another copy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep the database's copy and overwrite the other copy
+    KeepMine,
+    /// Replace the database's copy with the other copy
+    KeepTheirs,
+    /// Keep the other copy and save the database's copy as a new recipe
+    KeepBoth,
+}
@@ -0,0 +1,153 @@
+//! Passphrase encryption of saved recipes and images
+//!
+//! Keys are derived from the passphrase with Argon2id and data is encrypted with
+//! ChaCha20-Poly1305. Every encrypted blob starts with a marker followed by the salt its key was
+//! derived with and a random nonce, so blobs can be decrypted on their own no matter when they
+//! were written or where they were copied to
+
+use std::fmt;
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Marker at the start of every encrypted blob, never valid at the start of JSON or an image
+const MAGIC: &[u8] = b"\0recipier-encrypted-1\0";
+
+/// Length of the salt that keys are derived with
+const SALT_LEN: usize = 16;
+
+/// Length of the nonce that each blob is encrypted with
+const NONCE_LEN: usize = 12;
+
+/// Salt that a key was derived with
+type Salt = [u8; SALT_LEN];
+
+/// Encrypts and decrypts saved data with keys derived from a passphrase
+pub struct Cipher {
+    /// The passphrase, kept to derive keys for data that was encrypted with another salt and
+    /// overwritten when the cipher is dropped
+    passphrase: Zeroizing<String>,
+    /// The salt of the key that new data is encrypted with
+    salt: Salt,
+    /// Keys derived from the passphrase for every salt seen so far, deriving keys is slow
+    keys: Mutex<HashMap<Salt, ChaCha20Poly1305>>,
+}
+
+impl Cipher {
+    /// Derive a new key from a passphrase with a random salt
+    pub fn new(passphrase: &str) -> Result<Self, CryptError> {
+        let mut salt = Salt::default();
+        OsRng.fill_bytes(&mut salt);
+        let this = Self {
+            passphrase: Zeroizing::new(passphrase.to_owned()),
+            salt,
+            keys: Mutex::new(HashMap::new()),
+        };
+        this.key(&salt)?;
+        Ok(this)
+    }
+
+    /// Get the key derived from the passphrase with a salt, deriving it if it was not used yet
+    fn key(&self, salt: &Salt) -> Result<ChaCha20Poly1305, CryptError> {
+        let mut keys = self.keys.lock();
+        if let Some(key) = keys.get(salt) {
+            return Ok(key.clone());
+        }
+
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), salt, bytes.as_mut())
+            .map_err(|e| CryptError::Kdf(e.to_string()))?;
+        let key = ChaCha20Poly1305::new(Key::from_slice(bytes.as_ref()));
+        keys.insert(*salt, key.clone());
+        Ok(key)
+    }
+
+    /// Encrypt data with the key for this cipher's salt
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = self
+            .key(&self.salt)?
+            .encrypt(&nonce, data)
+            .map_err(|_| CryptError::Encrypt)?;
+
+        let mut blob = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + encrypted.len());
+        blob.extend_from_slice(MAGIC);
+        blob.extend_from_slice(&self.salt);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&encrypted);
+        Ok(blob)
+    }
+
+    /// Decrypt a blob written by [Cipher::encrypt] with the same passphrase
+    pub fn decrypt(&self, blob: &[u8]) -> Result<Vec<u8>, CryptError> {
+        let rest = blob.strip_prefix(MAGIC).ok_or(CryptError::Truncated)?;
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(CryptError::Truncated);
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+
+        let mut key_salt = Salt::default();
+        key_salt.copy_from_slice(salt);
+        self.key(&key_salt)?
+            .decrypt(Nonce::from_slice(nonce), encrypted)
+            .map_err(|_| CryptError::WrongPassphrase)
+    }
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cipher").finish_non_exhaustive()
+    }
+}
+
+/// Check if saved data was encrypted
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypt data before it is saved if a cipher is given, or leave it as plain data
+pub fn seal(cipher: Option<&Cipher>, data: Vec<u8>) -> Result<Vec<u8>, CryptError> {
+    match cipher {
+        Some(cipher) => cipher.encrypt(&data),
+        None => Ok(data),
+    }
+}
+
+/// Decrypt saved data if it was encrypted, returning plain data as it is
+pub fn unseal(cipher: Option<&Cipher>, data: Vec<u8>) -> Result<Vec<u8>, CryptError> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    match cipher {
+        Some(cipher) => cipher.decrypt(&data),
+        None => Err(CryptError::Locked),
+    }
+}
+
+/// Enumeration defining all errors that can occur when encrypting or decrypting saved data
+#[derive(Debug, Error)]
+pub enum CryptError {
+    #[error("Recipes are encrypted, enter the passphrase to open them")]
+    Locked,
+
+    #[error("Wrong passphrase or damaged encrypted data")]
+    WrongPassphrase,
+
+    #[error("Encrypted data is truncated")]
+    Truncated,
+
+    #[error("Failed to derive key from passphrase: {}", .0)]
+    Kdf(String),
+
+    #[error("Failed to encrypt data")]
+    Encrypt,
+}
@@ -0,0 +1,153 @@
+//! Finding recipes that are likely copies of each other, like the same recipe imported twice,
+//! and merging them into one recipe
+
+use std::sync::Arc;
+
+use hashbrown::HashSet;
+
+use super::RecipeId;
+use crate::recipes::recipe::Recipe;
+
+/// Lowest score of a pair of recipes that are listed as likely duplicates if no other threshold
+/// is given
+pub const DEFAULT_THRESHOLD: f32 = 0.7;
+
+/// Two recipes that are likely copies of each other
+#[derive(Clone, Debug, PartialEq)]
+pub struct Duplicate {
+    /// The ID of the older recipe
+    pub first: RecipeId,
+    /// The ID of the newer recipe
+    pub second: RecipeId,
+    /// How similar the recipes are, from 0 for nothing in common to 1 for the same name and
+    /// ingredients
+    pub score: f32,
+}
+
+/// Likely duplicates found by the last search, reused by later searches with the same threshold
+/// until recipes change
+#[derive(Debug)]
+pub(super) struct Found {
+    /// The lowest score of the listed pairs
+    pub threshold: f32,
+    /// The [revision](super::Database::revision) of the database when recipes were compared
+    pub revision: u64,
+    /// Every pair of likely duplicates, most similar first
+    pub duplicates: Arc<[Duplicate]>,
+}
+
+/// The parts of a recipe that are compared to find duplicates
+struct Fingerprint {
+    /// The ID of the recipe
+    id: RecipeId,
+    /// The normalized words in the recipe's name
+    name: HashSet<String>,
+    /// The normalized names of the recipe's ingredients
+    ingredients: HashSet<String>,
+}
+
+impl Fingerprint {
+    /// Get the parts of a recipe that are compared
+    fn new(recipe: &Recipe) -> Self {
+        Self {
+            id: recipe.id,
+            name: normalize(&recipe.name)
+                .split(' ')
+                .filter(|word| !word.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            ingredients: ingredient_names(recipe),
+        }
+    }
+
+    /// Score how similar two recipes are. Names and ingredients count equally, unless neither
+    /// recipe has ingredients and only the names are compared
+    fn score(&self, other: &Self) -> f32 {
+        let name = similarity(&self.name, &other.name);
+        match self.ingredients.is_empty() && other.ingredients.is_empty() {
+            true => name,
+            false => (name + similarity(&self.ingredients, &other.ingredients)) / 2.,
+        }
+    }
+}
+
+/// Find every pair of recipes with a score of at least `threshold`, most similar first
+pub(super) fn find(recipes: &[Arc<Recipe>], threshold: f32) -> Vec<Duplicate> {
+    let mut recipes = recipes.iter().collect::<Vec<_>>();
+    recipes.sort_by_key(|recipe| (recipe.created_on, recipe.id));
+    let fingerprints = recipes
+        .iter()
+        .map(|recipe| Fingerprint::new(recipe))
+        .collect::<Vec<_>>();
+
+    let mut duplicates = Vec::new();
+    for (i, first) in fingerprints.iter().enumerate() {
+        for second in fingerprints[i + 1..].iter() {
+            let score = first.score(second);
+            if score >= threshold {
+                duplicates.push(Duplicate {
+                    first: first.id,
+                    second: second.id,
+                    score,
+                });
+            }
+        }
+    }
+    duplicates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    duplicates
+}
+
+/// Combine two copies of a recipe into one, keeping the ID, name, and instructions of `keep`.
+/// Ingredients only in `other` are added, missing details are filled in from `other`, and
+/// instructions in `other` that differ are added after the instructions of `keep`
+pub fn merge(keep: &Recipe, other: &Recipe) -> Recipe {
+    let mut merged = keep.clone();
+    merged.created_on = keep.created_on.min(other.created_on);
+    merged.servings = keep.servings.or(other.servings);
+    merged.time = keep.time.or(other.time);
+
+    let names = ingredient_names(keep);
+    merged.ingredients.extend(
+        other
+            .ingredients
+            .iter()
+            .filter(|ingredient| !names.contains(&normalize(&ingredient.name)))
+            .cloned(),
+    );
+
+    if !other.body.trim().is_empty() && normalize(&keep.body) != normalize(&other.body) {
+        merged.body = match keep.body.trim().is_empty() {
+            true => other.body.clone(),
+            false => Arc::from(format!("{}\n\n{}", keep.body.trim_end(), other.body)),
+        };
+    }
+    merged
+}
+
+/// Get the normalized names of every ingredient in a recipe
+fn ingredient_names(recipe: &Recipe) -> HashSet<String> {
+    recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| normalize(&ingredient.name))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Normalize text so that differences in case, punctuation, and spacing are ignored
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Get the number of items two sets have in common out of the number of items in either set
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    match union {
+        0 => 0.,
+        _ => a.intersection(b).count() as f32 / union as f32,
+    }
+}
@@ -0,0 +1,195 @@
+//! Preparing recipe images to be saved and shown. Imported photos are turned upright using their
+//! EXIF orientation and downscaled, then saved as JPEG. Smaller thumbnails are made for lists of
+//! recipes, and saved images are decoded on a pool of background threads
+
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use druid::{
+    image::{
+        self, codecs::jpeg::JpegEncoder, ColorType, DynamicImage, GenericImageView, ImageResult,
+        RgbaImage,
+    },
+    ImageBuf,
+};
+use parking_lot::Mutex;
+
+/// Largest width or height of a saved image, larger images are downscaled when they are imported
+pub const MAX_SIZE: u32 = 1600;
+
+/// Largest width or height of a thumbnail shown in lists of recipes
+pub const THUMBNAIL_SIZE: u32 = 128;
+
+/// Quality that images are saved with, from 1 to 100
+const JPEG_QUALITY: u8 = 85;
+
+/// Most threads that decode images in the background at once
+const MAX_DECODERS: usize = 4;
+
+/// Which copy of a recipe's image to load
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageSize {
+    /// The full size image shown when viewing or editing a recipe
+    Full,
+    /// A thumbnail no larger than [THUMBNAIL_SIZE] shown in lists of recipes
+    Thumbnail,
+}
+
+/// A recipe's image that is loaded in the background
+#[derive(Clone, Debug)]
+pub enum ImageState {
+    /// The image is decoded and ready to be shown
+    Ready(ImageBuf),
+    /// The recipe has no image, or its image could not be read
+    Missing,
+    /// The image is being decoded
+    Loading,
+}
+
+/// A job run by the [DecodePool]
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of threads that decode images in the background, started when the first image is
+/// decoded and stopped when the pool is dropped
+#[derive(Debug, Default)]
+pub struct DecodePool {
+    /// The queue that the threads take jobs from, `None` until the threads are started
+    jobs: Mutex<Option<Sender<Job>>>,
+}
+
+impl DecodePool {
+    /// Run a job on one of the pool's threads
+    pub fn run(&self, job: impl FnOnce() + Send + 'static) {
+        let mut jobs = self.jobs.lock();
+        let sender = jobs.get_or_insert_with(Self::start);
+        if sender.send(Box::new(job)).is_err() {
+            log::error!("Image decoding threads have stopped, image will not be loaded");
+        }
+    }
+
+    /// Start the pool's threads, returning the queue to send them jobs with
+    fn start() -> Sender<Job> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let count = thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(MAX_DECODERS);
+        log::trace!("Starting {} image decoding threads", count);
+
+        for i in 0..count {
+            let receiver = receiver.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("image-decoder-{}", i))
+                .spawn(move || loop {
+                    let job = receiver.lock().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                });
+            if let Err(e) = spawned {
+                log::error!("Failed to start image decoding thread: {}", e);
+            }
+        }
+        sender
+    }
+}
+
+/// Read an image file to be used as a recipe image, turned upright and downscaled to fit in
+/// [MAX_SIZE]
+pub fn import(path: &Path) -> ImageResult<ImageBuf> {
+    let data = fs::read(path)?;
+    let image = orient(image::load_from_memory(&data)?, orientation(&data));
+    let image = match image.width() > MAX_SIZE || image.height() > MAX_SIZE {
+        true => image.resize(MAX_SIZE, MAX_SIZE, image::imageops::FilterType::Triangle),
+        false => image,
+    };
+    Ok(ImageBuf::from_dynamic_image(image))
+}
+
+/// Decode a saved image
+pub fn decode(data: &[u8]) -> ImageResult<ImageBuf> {
+    image::load_from_memory(data).map(ImageBuf::from_dynamic_image)
+}
+
+/// Decode a saved image straight to a thumbnail without keeping the full size image
+pub fn decode_thumbnail(data: &[u8]) -> ImageResult<ImageBuf> {
+    image::load_from_memory(data)
+        .map(|image| ImageBuf::from_dynamic_image(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)))
+}
+
+/// Make a thumbnail of an image that is already decoded
+pub fn thumbnail(buf: &ImageBuf) -> ImageBuf {
+    let image = DynamicImage::ImageRgba8(rgba(buf));
+    ImageBuf::from_dynamic_image(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+}
+
+/// Encode an image as JPEG to be saved. JPEG images can't be transparent, so transparent pixels
+/// are blended with white
+pub fn encode(buf: &ImageBuf) -> ImageResult<Vec<u8>> {
+    let pixels = rgba(buf)
+        .pixels()
+        .flat_map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+            [blend(r), blend(g), blend(b)]
+        })
+        .collect::<Vec<_>>();
+
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY).encode(
+        &pixels,
+        buf.width() as u32,
+        buf.height() as u32,
+        ColorType::Rgb8,
+    )?;
+    Ok(encoded)
+}
+
+/// Copy the pixels of an image buffer in any format to an RGBA image
+fn rgba(buf: &ImageBuf) -> RgbaImage {
+    let pixels = buf
+        .pixel_colors()
+        .flatten()
+        .flat_map(|color| {
+            let (r, g, b, a) = color.as_rgba8();
+            [r, g, b, a]
+        })
+        .collect();
+    RgbaImage::from_raw(buf.width() as u32, buf.height() as u32, pixels)
+        .unwrap_or_else(|| RgbaImage::new(0, 0))
+}
+
+/// Read the EXIF orientation of an image, 1 if the image is upright or has no EXIF data
+fn orientation(data: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
+
+/// Rotate and flip an image with the given EXIF orientation so that it is upright
+fn orient(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
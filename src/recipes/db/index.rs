@@ -0,0 +1,80 @@
+//! Lightweight index of every stored recipe, read at startup in place of every recipe's saved
+//! data so that recipes can be listed without loading them
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::RecipeId;
+use crate::recipes::recipe::Recipe;
+
+/// Version of the index format, indexes saved in any other version are rebuilt
+const INDEX_VERSION: u64 = 3;
+
+/// The details of one recipe kept in the index
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// The ID of the recipe
+    pub id: RecipeId,
+    /// The name of the recipe
+    pub name: Arc<str>,
+    /// When the recipe was created
+    pub created_on: DateTime<Utc>,
+    /// How long the recipe takes to make
+    pub time: Option<Duration>,
+    /// Hash of the recipe's saved data as it was last read from or written to storage, used to
+    /// tell changes made by other programs apart from our own saves
+    pub hash: Option<u64>,
+}
+
+impl IndexEntry {
+    /// Create an index entry for a recipe with the hash of its saved data
+    pub fn new(recipe: &Recipe, hash: Option<u64>) -> Self {
+        Self {
+            id: recipe.id,
+            name: recipe.name.clone(),
+            created_on: recipe.created_on,
+            time: recipe.time,
+            hash,
+        }
+    }
+}
+
+/// The index as it is saved to storage
+#[derive(Serialize, Deserialize)]
+struct SavedIndex {
+    /// The format version the index was saved in
+    version: u64,
+    /// Every indexed recipe
+    recipes: Vec<IndexEntry>,
+}
+
+/// Deserialize a saved index, returning `None` if it was saved in another format version
+pub fn parse(data: &[u8]) -> serde_json::Result<Option<HashMap<RecipeId, IndexEntry>>> {
+    let saved = serde_json::from_slice::<SavedIndex>(data)?;
+    if saved.version != INDEX_VERSION {
+        log::info!(
+            "Recipe index was saved in format version {}, rebuilding it",
+            saved.version
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(
+        saved
+            .recipes
+            .into_iter()
+            .map(|entry| (entry.id, entry))
+            .collect(),
+    ))
+}
+
+/// Serialize an index to be saved
+pub fn serialize(index: &HashMap<RecipeId, IndexEntry>) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(&SavedIndex {
+        version: INDEX_VERSION,
+        recipes: index.values().cloned().collect(),
+    })
+}
@@ -0,0 +1,433 @@
+//! Versioned save formats and the migrations that upgrade data saved by older versions
+//!
+//! Every saved recipe and the saved app state carry a `version` field. Documents saved before
+//! versioning was added have no field and are treated as version 0. When a document is loaded,
+//! each migration from its version up to the current version is applied in order
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use super::{query::SortKey, storage::StorageConfig};
+use crate::recipes::{
+    format::UnitFormat,
+    measure::{VolumeSystem, VolumeUnit},
+};
+
+/// The format version of recipes saved by this version of the application
+pub const RECIPE_VERSION: u64 = 2;
+
+/// The format version of app state saved by this version of the application
+pub const STATE_VERSION: u64 = 6;
+
+/// Name of the field holding the format version of a saved document
+const VERSION_FIELD: &str = "version";
+
+/// A function upgrading a document by one version
+type Migration = fn(&mut Map<String, Value>);
+
+/// Recipe migrations, where the migration at index `n` upgrades version `n` to `n + 1`
+const RECIPE_MIGRATIONS: &[Migration] = &[recipe_v0_to_v1, recipe_v1_to_v2];
+
+/// App state migrations, where the migration at index `n` upgrades version `n` to `n + 1`
+const STATE_MIGRATIONS: &[Migration] = &[
+    state_v0_to_v1,
+    state_v1_to_v2,
+    state_v2_to_v3,
+    state_v3_to_v4,
+    state_v4_to_v5,
+    state_v5_to_v6,
+];
+
+/// A serializable wrapper that adds a format version field to the serialized value
+#[derive(Serialize)]
+pub struct Versioned<'a, T> {
+    /// The format version of the wrapped value
+    version: u64,
+    /// The value to save
+    #[serde(flatten)]
+    data: &'a T,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    /// Wrap a recipe to save it with the current recipe format version
+    pub fn recipe(data: &'a T) -> Self {
+        Self {
+            version: RECIPE_VERSION,
+            data,
+        }
+    }
+
+    /// Wrap app state to save it with the current state format version
+    pub fn state(data: &'a T) -> Self {
+        Self {
+            version: STATE_VERSION,
+            data,
+        }
+    }
+}
+
+/// A document after it has been upgraded to the current format version
+#[derive(Debug)]
+pub struct Migrated {
+    /// The upgraded document
+    pub value: Value,
+    /// The format version the document was saved with
+    pub from_version: u64,
+}
+
+/// Enumeration defining all errors that can occur when upgrading a saved document
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Saved with format version {found}, but this version of Recipier only supports up to version {supported}, update Recipier to open it")]
+    TooNew { found: u64, supported: u64 },
+
+    #[error("Format version field is not a number: {}", .0)]
+    InvalidVersion(Value),
+
+    #[error("Saved data is not a JSON object")]
+    NotAnObject,
+
+    #[error("Saved data is not valid JSON: {}", .0)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Parse and upgrade a saved recipe to the current recipe format version
+pub fn migrate_recipe(data: &[u8]) -> Result<Migrated, MigrationError> {
+    migrate(serde_json::from_slice(data)?, RECIPE_MIGRATIONS)
+}
+
+/// Upgrade saved app state to the current state format version
+pub fn migrate_state(value: Value) -> Result<Migrated, MigrationError> {
+    migrate(value, STATE_MIGRATIONS)
+}
+
+/// Apply all migrations needed to bring a document up to the version after the last migration
+fn migrate(value: Value, migrations: &[Migration]) -> Result<Migrated, MigrationError> {
+    let current = migrations.len() as u64;
+    let mut object = match value {
+        Value::Object(object) => object,
+        _ => return Err(MigrationError::NotAnObject),
+    };
+
+    let from_version = match object.remove(VERSION_FIELD) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or(MigrationError::InvalidVersion(version))?,
+    };
+    if from_version > current {
+        return Err(MigrationError::TooNew {
+            found: from_version,
+            supported: current,
+        });
+    }
+
+    for (version, migration) in migrations.iter().enumerate().skip(from_version as usize) {
+        log::trace!("Migrating document from version {}", version);
+        migration(&mut object);
+    }
+
+    Ok(Migrated {
+        value: Value::Object(object),
+        from_version,
+    })
+}
+
+/// Insert a value into an object if there is no value with the given key
+fn set_default(object: &mut Map<String, Value>, key: &str, value: impl FnOnce() -> Value) {
+    object.entry(key).or_insert_with(value);
+}
+
+/// Insert a value into the config of app state if it has no value with the given key
+fn set_config_default(state: &mut Map<String, Value>, key: &str, value: impl FnOnce() -> Value) {
+    if let Some(Value::Object(config)) = state.get_mut("config") {
+        set_default(config, key, value);
+    }
+}
+
+/// Version 0 recipes relied on serde defaults for fields that were added after the first
+/// release, fill them in explicitly
+fn recipe_v0_to_v1(recipe: &mut Map<String, Value>) {
+    set_default(recipe, "created_on", || {
+        serde_json::to_value(chrono::Utc::now()).unwrap_or(Value::Null)
+    });
+    set_default(recipe, "servings", || Value::Null);
+    set_default(recipe, "time", || Value::Null);
+    set_default(recipe, "ingredients", || Value::Array(Vec::new()));
+
+    if let Some(Value::Array(ingredients)) = recipe.get_mut("ingredients") {
+        for ingredient in ingredients.iter_mut().filter_map(Value::as_object_mut) {
+            set_default(ingredient, "optional", || Value::Bool(false));
+        }
+    }
+}
+
+/// Version 1 recipes measured cups in US legal units and every other volume unit in US customary
+/// units. Version 2 measures units without a system in the system chosen in the config, so give
+/// the old units the system they were measured in
+fn recipe_v1_to_v2(recipe: &mut Map<String, Value>) {
+    if let Some(Value::Array(ingredients)) = recipe.get_mut("ingredients") {
+        for ingredient in ingredients.iter_mut() {
+            if let Some(unit) = ingredient.pointer_mut("/amount/Volume/unit") {
+                explicit_volume_unit(unit);
+            }
+        }
+    }
+}
+
+/// Replace a volume unit without a system with the unit of the system it was measured in before
+/// version 2 recipes
+fn explicit_volume_unit(unit: &mut Value) {
+    let old = match unit.as_str() {
+        Some("Cup") => VolumeUnit::RegionalCup(VolumeSystem::UsLegal),
+        Some("Pint") => VolumeUnit::RegionalPint(VolumeSystem::UsCustomary),
+        Some("Quart") => VolumeUnit::RegionalQuart(VolumeSystem::UsCustomary),
+        Some("Gallon") => VolumeUnit::RegionalGallon(VolumeSystem::UsCustomary),
+        Some("Teaspoon") => VolumeUnit::RegionalTeaspoon(VolumeSystem::UsCustomary),
+        Some("Tablespoon") => VolumeUnit::RegionalTablespoon(VolumeSystem::UsCustomary),
+        Some("FluidOz") => VolumeUnit::RegionalFluidOz(VolumeSystem::UsCustomary),
+        _ => return,
+    };
+    if let Ok(value) = serde_json::to_value(old) {
+        *unit = value;
+    }
+}
+
+/// Version 0 app state stored the recipe directory in the `recipes` field, move it to the
+/// storage config, or use the default storage if there was none, and upgrade the recipe kept by
+/// the delete screen. Measurements are formatted for the user's locale if no format was chosen
+fn state_v0_to_v1(state: &mut Map<String, Value>) {
+    if let Some(Value::Object(remove)) = state.get_mut("remove") {
+        if let Some(Value::Object(deleted)) = remove.get_mut("deleted") {
+            recipe_v0_to_v1(deleted);
+        }
+    }
+
+    let dir = state.remove("recipes");
+    if let Some(Value::Object(config)) = state.get_mut("config") {
+        set_default(config, "storage", || match dir {
+            Some(Value::String(dir)) => {
+                let mut storage = Map::new();
+                storage.insert("Directory".to_owned(), Value::String(dir));
+                Value::Object(storage)
+            }
+            _ => serde_json::to_value(StorageConfig::default()).unwrap_or(Value::Null),
+        });
+    }
+    set_config_default(state, "units", || {
+        serde_json::to_value(UnitFormat::system()).unwrap_or(Value::Null)
+    });
+}
+
+/// Version 2 app state added how many days deleted recipes are kept in the trash, keep them for
+/// a month
+fn state_v1_to_v2(state: &mut Map<String, Value>) {
+    set_config_default(state, "trash_days", || Value::from(30));
+}
+
+/// Version 3 app state added automatic backups, back up once a day and keep ten backups
+fn state_v2_to_v3(state: &mut Map<String, Value>) {
+    set_config_default(state, "backup_hours", || Value::from(24));
+    set_config_default(state, "backup_count", || Value::from(10));
+}
+
+/// Version 4 app state added encryption, recipes saved by older versions are never encrypted
+fn state_v3_to_v4(state: &mut Map<String, Value>) {
+    set_config_default(state, "encrypted", || Value::Bool(false));
+}
+
+/// Version 5 app state added sorting recipes on the home screen, sort them by when they were
+/// created, oldest first
+fn state_v4_to_v5(state: &mut Map<String, Value>) {
+    if let Some(Value::Object(home)) = state.get_mut("home") {
+        set_default(home, "sort", || {
+            serde_json::to_value(SortKey::Created).unwrap_or(Value::Null)
+        });
+        set_default(home, "descending", || Value::Bool(false));
+    }
+}
+
+/// Version 6 app state added the system that volume units without a system are measured in, use
+/// US customary units and give the units of the recipe being edited or deleted the system they
+/// were measured in
+fn state_v5_to_v6(state: &mut Map<String, Value>) {
+    set_config_default(state, "volume_system", || {
+        serde_json::to_value(VolumeSystem::UsCustomary).unwrap_or(Value::Null)
+    });
+
+    if let Some(Value::Object(remove)) = state.get_mut("remove") {
+        if let Some(Value::Object(deleted)) = remove.get_mut("deleted") {
+            recipe_v1_to_v2(deleted);
+        }
+    }
+    if let Some(Value::Object(ingredients)) = state
+        .get_mut("edit")
+        .and_then(|edit| edit.get_mut("ingredients"))
+    {
+        for ingredient in ingredients.values_mut() {
+            if let Some(unit) = ingredient.pointer_mut("/unit/Volume") {
+                explicit_volume_unit(unit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn recipe_without_version_is_v0() {
+        let data = json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "name": "Toast",
+            "body": "",
+            "ingredients": [{"name": "Bread", "amount": {"Count": 1.0}}],
+        });
+        let migrated = migrate_recipe(data.to_string().as_bytes()).unwrap();
+
+        assert_eq!(migrated.from_version, 0);
+        let recipe = migrated.value.as_object().unwrap();
+        assert!(recipe.contains_key("created_on"));
+        assert_eq!(recipe["servings"], Value::Null);
+        assert_eq!(recipe["time"], Value::Null);
+        assert_eq!(recipe["ingredients"][0]["optional"], false);
+        assert!(!recipe.contains_key(VERSION_FIELD));
+    }
+
+    #[test]
+    fn recipe_v1_volume_units_get_their_system() {
+        let data = json!({
+            "version": 1,
+            "ingredients": [
+                {"amount": {"Volume": {"unit": "Cup", "val": 1.0}}},
+                {"amount": {"Volume": {"unit": "Tablespoon", "val": 2.0}}},
+                {"amount": {"Volume": {"unit": "Liter", "val": 1.0}}},
+                {"amount": {"Mass": {"unit": "Gram", "val": 5.0}}},
+            ],
+        });
+        let migrated = migrate_recipe(data.to_string().as_bytes()).unwrap();
+
+        assert_eq!(migrated.from_version, 1);
+        let ingredients = &migrated.value["ingredients"];
+        assert_eq!(
+            ingredients[0]["amount"]["Volume"]["unit"],
+            json!({"RegionalCup": "UsLegal"})
+        );
+        assert_eq!(
+            ingredients[1]["amount"]["Volume"]["unit"],
+            json!({"RegionalTablespoon": "UsCustomary"})
+        );
+        assert_eq!(ingredients[2]["amount"]["Volume"]["unit"], "Liter");
+        assert_eq!(ingredients[3]["amount"]["Mass"]["unit"], "Gram");
+    }
+
+    #[test]
+    fn current_recipe_is_unchanged() {
+        let data = json!({
+            "version": RECIPE_VERSION,
+            "ingredients": [{"amount": {"Volume": {"unit": "Cup", "val": 1.0}}}],
+        });
+        let migrated = migrate_recipe(data.to_string().as_bytes()).unwrap();
+
+        assert_eq!(migrated.from_version, RECIPE_VERSION);
+        assert_eq!(
+            migrated.value["ingredients"][0]["amount"]["Volume"]["unit"],
+            "Cup"
+        );
+    }
+
+    #[test]
+    fn state_v0_moves_recipes_into_storage() {
+        let migrated = migrate_state(json!({
+            "recipes": "/home/user/recipes",
+            "config": {},
+        }))
+        .unwrap();
+
+        assert_eq!(migrated.from_version, 0);
+        assert!(migrated.value.get("recipes").is_none());
+        assert_eq!(
+            migrated.value["config"]["storage"],
+            json!({"Directory": "/home/user/recipes"})
+        );
+        assert_eq!(migrated.value["config"]["trash_days"], 30);
+        assert_eq!(migrated.value["config"]["encrypted"], false);
+    }
+
+    #[test]
+    fn state_v0_without_recipes_uses_default_storage() {
+        let migrated = migrate_state(json!({"config": {}})).unwrap();
+
+        assert_eq!(
+            migrated.value["config"]["storage"],
+            serde_json::to_value(StorageConfig::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn state_v5_edit_units_get_their_system() {
+        let migrated = migrate_state(json!({
+            "version": 5,
+            "config": {},
+            "edit": {
+                "ingredients": {
+                    "a": {"unit": {"Volume": "Cup"}},
+                    "b": {"unit": {"Volume": "Milliliter"}},
+                    "c": {"unit": {"Mass": "Gram"}},
+                },
+            },
+        }))
+        .unwrap();
+
+        let ingredients = &migrated.value["edit"]["ingredients"];
+        assert_eq!(
+            ingredients["a"]["unit"]["Volume"],
+            json!({"RegionalCup": "UsLegal"})
+        );
+        assert_eq!(ingredients["b"]["unit"]["Volume"], "Milliliter");
+        assert_eq!(ingredients["c"]["unit"]["Mass"], "Gram");
+        assert_eq!(migrated.value["config"]["volume_system"], "UsCustomary");
+    }
+
+    #[test]
+    fn too_new() {
+        let data = json!({"version": RECIPE_VERSION + 1});
+        assert!(matches!(
+            migrate_recipe(data.to_string().as_bytes()),
+            Err(MigrationError::TooNew { found, supported })
+                if found == RECIPE_VERSION + 1 && supported == RECIPE_VERSION
+        ));
+        assert!(matches!(
+            migrate_state(json!({"version": STATE_VERSION + 1})),
+            Err(MigrationError::TooNew { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert!(matches!(
+            migrate_recipe(br#"{"version": "1"}"#),
+            Err(MigrationError::InvalidVersion(Value::String(_)))
+        ));
+        assert!(matches!(
+            migrate_state(json!({"version": -1})),
+            Err(MigrationError::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn not_an_object() {
+        assert!(matches!(
+            migrate_recipe(b"[1, 2, 3]"),
+            Err(MigrationError::NotAnObject)
+        ));
+        assert!(matches!(
+            migrate_recipe(b"not json"),
+            Err(MigrationError::Json(_))
+        ));
+    }
+}
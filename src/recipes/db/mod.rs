@@ -1,333 +1,1981 @@
-//! A serializable database containing all recipes
+//! A database containing all recipes, persisted to a configurable [Storage](storage::Storage)
+//! backend
 
-use std::{borrow::Borrow, fmt, fs::File, ops::Deref, path::Path, sync::Arc};
+pub mod atomic;
+pub mod backup;
+pub mod batch;
+pub mod check;
+pub mod conflict;
+pub mod crypt;
+pub mod duplicates;
+pub mod images;
+pub mod index;
+pub mod migrate;
+pub mod query;
+pub mod stats;
+pub mod storage;
+pub mod watch;
 
-use druid::{
-    im::OrdMap,
-    image::{io::Reader as ImageReader, GenericImageView, ImageBuffer, ImageFormat, Rgba},
-    ImageBuf,
+use std::{
+    fmt, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
 };
-use hashbrown::HashMap;
+
+use chrono::{DateTime, Utc};
+use druid::ImageBuf;
+use hashbrown::{HashMap, HashSet};
 use lru::LruCache;
-use parking_lot::RwLock;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use uuid::Uuid;
 
+use self::{
+    backup::{BackupError, Snapshot},
+    batch::{Batch, Change, Staged, Undo},
+    check::Issue,
+    conflict::{Conflict, ConflictSource, Resolution},
+    crypt::{Cipher, CryptError},
+    duplicates::{Duplicate, Found},
+    images::{DecodePool, ImageSize, ImageState},
+    index::IndexEntry,
+    migrate::{MigrationError, Versioned, RECIPE_VERSION},
+    query::{Page, Query, Ranked, Ranker},
+    stats::{Collected, Stats},
+    storage::{DirStorage, MemoryStorage, Storage, StorageConfig, StorageError},
+    watch::DirWatcher,
+};
 use super::recipe::Recipe;
 
+/// Most recipes kept loaded in memory at once. Past this, the least recently used recipes
+/// without unsaved changes are dropped and loaded again from storage when they are needed
+const MAX_LOADED: usize = 256;
+
 /// A structure holding recipe ID to data pairs with methods to add, remove, and modify recipes
 ///
 /// The structure is an Arc over internal state, so is very easy to clone
 #[derive(Clone, Debug)]
 pub struct Database {
-    /// A map of recipe IDs to loaded or unloaded recipe data
-    items: Arc<RwLock<HashMap<RecipeId, Arc<Recipe>>>>,
-    /// A cache of recipe IDs to their loaded images
-    images: Arc<RwLock<LruCache<RecipeId, ImageBuf>>>,
-    /// The directory that all recipe files are stored in
-    dir: Arc<Path>,
+    /// Recently used recipes that are loaded in memory, see [MAX_LOADED]
+    items: Arc<Mutex<LruCache<RecipeId, Arc<Recipe>>>>,
+    /// The index of every recipe in the database, whether it is loaded or not
+    index: Arc<RwLock<HashMap<RecipeId, IndexEntry>>>,
+    /// If the index changed since it was last written to storage
+    index_dirty: Arc<AtomicBool>,
+    /// A cache of recipe IDs to their loaded images, or `None` for recipes without an image
+    images: Arc<RwLock<LruCache<RecipeId, Option<ImageBuf>>>>,
+    /// A cache of recipe IDs to thumbnails of their images shown in lists, kept apart from
+    /// [images](Self::images) so that scrolling through lists does not drop full size images
+    thumbnails: Arc<RwLock<LruCache<RecipeId, Option<ImageBuf>>>>,
+    /// Images that are being decoded in the background, see [Database::load_image]
+    decoding: Arc<Mutex<HashSet<(RecipeId, ImageSize)>>>,
+    /// The threads that images are decoded on
+    decoder: Arc<DecodePool>,
+    /// The backend that all recipes and images are saved to
+    storage: Arc<dyn Storage>,
+    /// IDs of recipes that were changed and have not been written to storage
+    dirty: Arc<Mutex<HashSet<RecipeId>>>,
+    /// IDs of recipes with images that were changed and have not been written to storage
+    dirty_images: Arc<Mutex<HashSet<RecipeId>>>,
+    /// Recipes that were changed in storage while they had unsaved changes
+    conflicts: Arc<Mutex<HashMap<RecipeId, Conflict>>>,
+    /// The cipher that recipes and images are encrypted with, or `None` if they are saved as
+    /// plain files
+    cipher: Arc<RwLock<Option<Arc<Cipher>>>>,
+    /// If saved recipes are encrypted and the passphrase has not been given yet, nothing is
+    /// written to storage until the database is [unlocked](Database::unlock)
+    locked: Arc<AtomicBool>,
+    /// Number of changes made to recipes or images, shared by every handle to the database
+    changes: Arc<AtomicU64>,
+    /// Scores of the last ranked [query](Database::query), so that ranking every recipe is only
+    /// done again once the query or recipes change
+    ranked: Arc<Mutex<Option<Ranked>>>,
+    /// Likely duplicates found by the last [search](Database::duplicates), so that recipes are
+    /// only compared again once the threshold or recipes change
+    found: Arc<Mutex<Option<Found>>>,
+    /// Statistics from the last [collection](Database::stats), so that recipes are only read
+    /// again once they change
+    collected: Arc<Mutex<Option<Collected>>>,
+    /// The number of changes when this handle was last [synced](Database::sync). This is kept
+    /// outside of the shared state so that druid can compare it with older copies of app state
+    revision: u64,
 }
 
-/// Name of the recipe data save file
-const SAVE_FILE: &str = "save.json";
-
-/// Name of the recipe image file in a saved recipe folder
-const IMG_FILE: &str = "img.png";
-
 impl Database {
     /// Get a recipe by UUID from this database, if the recipe is not currently loaded then it will be loaded
     pub fn get(&self, id: RecipeId) -> Option<Arc<Recipe>> {
-        let items = self.items.read();
-        items.get(&id).cloned()
+        if let Some(recipe) = self.items.lock().get(&id) {
+            return Some(recipe.clone());
+        }
+        if !self.index.read().contains_key(&id) {
+            return None;
+        }
+
+        match self.load_saved(id) {
+            Ok(recipe) => recipe,
+            Err(e) => {
+                log::error!("Failed to load recipe {}: {}", id, e);
+                None
+            }
+        }
     }
 
-    /// Search this database, returning an ordered map of scores to recipe data
-    pub fn search(&self, searcher: impl Fn(&Recipe) -> isize) -> OrdMap<isize, Arc<Recipe>> {
-        let mut results = OrdMap::new();
-        let items = self.items.read();
+    /// Get the index entry of every recipe in this database without loading any recipes
+    pub fn entries(&self) -> Vec<IndexEntry> {
+        self.index.read().values().cloned().collect()
+    }
 
-        for (_, recipe) in items.iter() {
-            results.insert(searcher(recipe.borrow()), recipe.clone());
+    /// Get one page of the recipes matching a query. Only the index is read unless the query
+    /// is [ranked](Query::rank) and recipes changed since they were last ranked with the same
+    /// key, in which case the ranker may load recipes
+    pub fn query(&self, query: &Query) -> Page {
+        match query.ranker() {
+            Some((key, rank)) => {
+                let scores = self.scores(key, rank);
+                query.run(self.index.read().values(), |id| scores.get(&id).copied())
+            }
+            None => query.run(self.index.read().values(), |_| None),
         }
+    }
 
-        results
+    /// Get the score of every recipe for a rank key, scoring every recipe if they were last
+    /// scored for another key or recipes changed since then
+    fn scores(&self, key: &Arc<str>, rank: &Ranker) -> Arc<HashMap<RecipeId, isize>> {
+        let revision = self.revision();
+        if let Some(ranked) = self.ranked.lock().as_ref() {
+            if ranked.key == *key && ranked.revision == revision {
+                return ranked.scores.clone();
+            }
+        }
+
+        //The ranker may load recipes, which reads the index, so rank a copy of it
+        let scores = Arc::new(
+            self.entries()
+                .iter()
+                .filter_map(|entry| Some((entry.id, rank(entry, &|| self.peek(entry.id))?)))
+                .collect::<HashMap<_, _>>(),
+        );
+        *self.ranked.lock() = Some(Ranked {
+            key: key.clone(),
+            revision,
+            scores: scores.clone(),
+        });
+        scores
+    }
+
+    /// Get a recipe without keeping it loaded if it is not already, so that reading through
+    /// every recipe does not drop the recently used recipes that are kept loaded
+    fn peek(&self, id: RecipeId) -> Option<Arc<Recipe>> {
+        if let Some(recipe) = self.items.lock().peek(&id) {
+            return Some(recipe.clone());
+        }
+
+        let loaded = self.storage.get(id).and_then(|data| match data {
+            Some(data) => Ok(Some(self.load_recipe(id, &self.unseal(data)?)?)),
+            None => Ok(None),
+        });
+        match loaded {
+            Ok(recipe) => recipe.map(Arc::new),
+            Err(e) => {
+                log::error!("Failed to load recipe {}: {}", id, e);
+                None
+            }
+        }
     }
 
-    /// Update a recipe with new data
-    pub fn update(&self, recipe: Arc<Recipe>) {
-        let mut items = self.items.write();
-        if let Some(entry) = items.get_mut(&recipe.id) {
-            *entry = recipe;
+    /// Collect statistics about every recipe in this database. Every recipe is read to count its
+    /// ingredients and servings, so this should be run on a background thread. The statistics
+    /// are kept until recipes change, see [collected_stats](Self::collected_stats)
+    pub fn stats(&self) -> Arc<Stats> {
+        if let Some(stats) = self.collected_stats() {
+            return stats;
         }
+
+        let revision = self.revision();
+        let stats = Arc::new(Stats::collect(self.entries().iter(), |id| self.peek(id)));
+        *self.collected.lock() = Some(Collected {
+            revision,
+            stats: stats.clone(),
+        });
+        stats
+    }
+
+    /// Get the statistics from the last [collection](Self::stats) if recipes have not changed
+    /// since
+    pub fn collected_stats(&self) -> Option<Arc<Stats>> {
+        self.collected
+            .lock()
+            .as_ref()
+            .filter(|collected| collected.revision == self.revision())
+            .map(|collected| collected.stats.clone())
+    }
+
+    /// Find pairs of recipes that are likely copies of each other, with a similarity score of
+    /// at least `threshold` from 0 to 1. Every recipe is read to compare its ingredients, so this
+    /// should be run on a background thread. The pairs are kept until recipes change, see
+    /// [found_duplicates](Self::found_duplicates)
+    pub fn duplicates(&self, threshold: f32) -> Arc<[Duplicate]> {
+        if let Some(found) = self.found_duplicates(threshold) {
+            return found;
+        }
+
+        let revision = self.revision();
+        let recipes = self
+            .entries()
+            .iter()
+            .filter_map(|entry| self.peek(entry.id))
+            .collect::<Vec<_>>();
+        let duplicates = Arc::<[Duplicate]>::from(duplicates::find(&recipes, threshold));
+        *self.found.lock() = Some(Found {
+            threshold,
+            revision,
+            duplicates: duplicates.clone(),
+        });
+        duplicates
+    }
+
+    /// Get the pairs found by the last [search for duplicates](Self::duplicates) if it used the
+    /// same threshold and recipes have not changed since
+    pub fn found_duplicates(&self, threshold: f32) -> Option<Arc<[Duplicate]>> {
+        self.found
+            .lock()
+            .as_ref()
+            .filter(|found| found.threshold == threshold && found.revision == self.revision())
+            .map(|found| found.duplicates.clone())
+    }
+
+    /// [Merge](duplicates::merge) two copies of a recipe into the recipe with the ID `keep` and
+    /// move the other copy to the trash. The other copy's image is kept if `keep` has no image
+    pub fn merge(&self, keep: RecipeId, other: RecipeId) -> Result<(), StorageError> {
+        let (kept, removed) = match (self.get(keep), self.get(other)) {
+            (Some(kept), Some(removed)) if keep != other => (kept, removed),
+            _ => return Ok(()),
+        };
+        //Save changed images first so that the stored images are the latest
+        if self.dirty_images.lock().contains(&keep) || self.dirty_images.lock().contains(&other) {
+            let _ = self.save();
+        }
+        let image = match self.storage.get_image(keep)? {
+            Some(_) => None,
+            None => self
+                .storage
+                .get_image(other)?
+                .map(|data| self.unseal(data))
+                .transpose()?,
+        };
+
+        let mut batch = Batch::new();
+        batch
+            .update(Arc::new(duplicates::merge(&kept, &removed)))
+            .remove(other);
+        if let Some(image) = image {
+            batch.image(keep, image);
+        }
+        self.commit(batch)
+    }
+
+    /// Update a recipe with new data and save it
+    pub fn update(&self, recipe: Arc<Recipe>) -> Result<(), StorageError> {
+        if !self.index.read().contains_key(&recipe.id) {
+            return Ok(());
+        }
+
+        self.dirty.lock().insert(recipe.id);
+        self.index_recipe(&recipe, None);
+        self.cache(recipe.clone());
+        self.changed();
+        self.save_recipe(&recipe)
+    }
+
+    /// Check if a recipe is in this database without loading it
+    pub fn contains(&self, id: RecipeId) -> bool {
+        self.index.read().contains_key(&id)
+    }
+
+    /// Get the number of changes made to recipes or images since the database was opened
+    pub fn revision(&self) -> u64 {
+        self.changes.load(Ordering::SeqCst)
+    }
+
+    /// Catch this handle up with changes made through any handle to the database, returning
+    /// true if anything changed since it was last synced. Druid only sees changes to the
+    /// database once the handle in app state is synced
+    pub fn sync(&mut self) -> bool {
+        let revision = self.revision();
+        std::mem::replace(&mut self.revision, revision) != revision
+    }
+
+    /// Record a change to recipes or images
+    fn changed(&self) {
+        self.changes.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get the number of recipes in this database
     pub fn len(&self) -> usize {
-        self.items.read().len()
+        self.index.read().len()
     }
 
-    /// Create a new empty database
-    pub fn new(path: impl AsRef<Path>) -> Self {
+    /// Create a new empty database that saves to the given storage backend
+    pub fn new(storage: impl Storage + 'static) -> Self {
         Self {
-            items: Arc::new(RwLock::new(HashMap::new())),
+            items: Arc::new(Mutex::new(LruCache::unbounded())),
+            index: Arc::new(RwLock::new(HashMap::new())),
+            index_dirty: Arc::new(AtomicBool::new(false)),
             images: Arc::new(RwLock::new(LruCache::new(50))),
-            dir: Arc::from(path.as_ref()),
+            thumbnails: Arc::new(RwLock::new(LruCache::new(250))),
+            decoding: Arc::new(Mutex::new(HashSet::new())),
+            decoder: Arc::new(DecodePool::default()),
+            storage: Arc::new(storage),
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+            dirty_images: Arc::new(Mutex::new(HashSet::new())),
+            conflicts: Arc::new(Mutex::new(HashMap::new())),
+            cipher: Arc::new(RwLock::new(None)),
+            locked: Arc::new(AtomicBool::new(false)),
+            changes: Arc::new(AtomicU64::new(0)),
+            ranked: Arc::new(Mutex::new(None)),
+            found: Arc::new(Mutex::new(None)),
+            collected: Arc::new(Mutex::new(None)),
+            revision: 0,
         }
     }
 
-    /// Insert a recipe into the database, automatically creating an ID and returning it
-    pub fn insert(&self, recipe: Recipe) {
-        let mut data = self.items.write();
-        match data.contains_key(&recipe.id) {
-            true => {
-                log::trace!(
-                    "Database already contains recipe with ID {}, updating instead...",
-                    recipe.id
-                );
-                data.insert(recipe.id, Arc::new(recipe));
+    /// Open the configured storage backend without loading any recipes from it, see
+    /// [Database::load]
+    pub fn open(config: &StorageConfig) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Arc::from(config.open()?),
+            ..Self::default()
+        })
+    }
+
+    /// Load the index of every stored recipe in place of any loaded recipes, returning an error
+    /// for every recipe that could not be loaded. Recipes are only read from storage once they
+    /// are [needed](Self::get), unless there is no usable index and every recipe has to be
+    /// loaded to rebuild it. Entries of recipes changed by other programs while the index was
+    /// not being kept up to date are corrected when those recipes are loaded
+    ///
+    /// Recipes that fail to load are left untouched in storage. If recipes are encrypted and no
+    /// passphrase was given, nothing is loaded and the database stays locked
+    pub fn load(&self) -> Result<Vec<LoadError>, StorageError> {
+        self.load_with_progress(|_, _| ())
+    }
+
+    /// [Load](Self::load) recipes, calling `progress` with the number of recipes loaded so far
+    /// and the number of recipes to load as recipes are read from storage
+    pub fn load_with_progress(
+        &self,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<LoadError>, StorageError> {
+        let index = match self
+            .storage
+            .get_index()
+            .and_then(|data| data.map(|data| self.unseal(data)).transpose())
+        {
+            Ok(index) => index,
+            Err(StorageError::Crypt(CryptError::Locked)) => {
+                self.locked.store(true, Ordering::SeqCst);
+                return Err(CryptError::Locked.into());
             }
-            false => {
-                log::trace!("inserting recipe with ID {} into database...", recipe.id);
-                data.insert(recipe.id, Arc::new(recipe));
+            Err(e) => {
+                log::warn!("Failed to read recipe index, rebuilding it: {}", e);
+                None
             }
+        };
+        let index = index.and_then(|data| match index::parse(&data) {
+            Ok(index) => index,
+            Err(e) => {
+                log::warn!("Failed to parse recipe index, rebuilding it: {}", e);
+                None
+            }
+        });
+
+        let errors = match index {
+            Some(index) => self.load_index(index, &mut progress)?,
+            None => self.rebuild_index(&mut progress)?,
+        };
+        if let Err(e) = self.save_index() {
+            log::error!("Failed to save recipe index: {}", e);
         }
+        Ok(errors)
     }
 
-    /// Save this database to a directory of files and a path to the directory
-    pub fn save<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
-        if let Err(e) = std::fs::create_dir_all(&self.dir) {
-            log::error!(
-                "Failed to create directory {} to save recipes to: {}",
-                self.dir.display(),
-                e
+    /// Use a saved index in place of any loaded recipes, dropping recipes that are no longer
+    /// stored and loading stored recipes that are missing from the index
+    fn load_index(
+        &self,
+        mut index: HashMap<RecipeId, IndexEntry>,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Vec<LoadError>, StorageError> {
+        let stored = self.storage.ids()?;
+        let indexed = index.len();
+        let stored_ids = stored.iter().copied().collect::<HashSet<_>>();
+        index.retain(|id, _| stored_ids.contains(id));
+        let missing = stored
+            .into_iter()
+            .filter(|id| !index.contains_key(id))
+            .collect::<Vec<_>>();
+        if index.len() != indexed || !missing.is_empty() {
+            log::info!(
+                "Recipe index is out of date, {} recipes were removed and {} were added",
+                indexed - index.len(),
+                missing.len()
             );
-            return self.dir.serialize(ser);
+            self.index_dirty.store(true, Ordering::SeqCst);
         }
 
-        let items = self.items.read();
-        for (id, recipe) in items.iter() {
-            let dir = self.dir.join(id.to_string());
-            if !dir.exists() {
-                if let Err(e) = std::fs::create_dir(&dir) {
-                    log::error!(
-                        "Failed to create directory for recipe {} at {}: {}",
-                        id,
-                        dir.display(),
-                        e
-                    );
-                    continue;
+        self.locked.store(false, Ordering::SeqCst);
+        self.items.lock().clear();
+        self.forget_images();
+        *self.index.write() = index;
+
+        let mut errors = Vec::new();
+        let total = missing.len();
+        for (loaded, id) in missing.into_iter().enumerate() {
+            progress(loaded, total);
+            match self.load_saved(id) {
+                Ok(_) => (),
+                Err(StorageError::Load(e)) => {
+                    log::error!("{}", e);
+                    errors.push(e);
                 }
+                Err(e) => log::error!("Failed to load recipe {}: {}", id, e),
             }
-            let save_file = dir.join(SAVE_FILE);
+        }
+        progress(total, total);
+        Ok(errors)
+    }
 
-            match File::create(&save_file) {
-                Ok(file) => {
-                    if let Err(e) = serde_json::to_writer(file, recipe) {
-                        log::error!(
-                            "Failed to serialize recipe {} to {}: {}",
-                            id,
-                            save_file.display(),
-                            e
-                        );
-                    }
+    /// Load every stored recipe in place of any loaded recipes to rebuild the index from scratch
+    fn rebuild_index(
+        &self,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Vec<LoadError>, StorageError> {
+        log::info!("Rebuilding recipe index");
+        let ids = self.storage.ids()?;
+        self.locked.store(false, Ordering::SeqCst);
+        self.items.lock().clear();
+        self.forget_images();
+        self.index.write().clear();
+        self.index_dirty.store(true, Ordering::SeqCst);
+
+        let mut errors = Vec::new();
+        let (mut decrypted, mut undecryptable) = (0, 0);
+        for (loaded, &id) in ids.iter().enumerate() {
+            progress(loaded, ids.len());
+            let data = match self.storage.get(id) {
+                Ok(Some(data)) => data,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::error!("Failed to read recipe {}: {}", id, e);
+                    continue;
+                }
+            };
+
+            let encrypted = crypt::is_encrypted(&data);
+            let data = match self.unseal(data) {
+                Ok(data) => {
+                    decrypted += encrypted as usize;
+                    data
+                }
+                Err(StorageError::Crypt(CryptError::Locked)) => {
+                    self.locked.store(true, Ordering::SeqCst);
+                    return Err(CryptError::Locked.into());
                 }
                 Err(e) => {
-                    log::error!(
-                        "Failed to create / overwrite file {}: {}",
-                        save_file.display(),
-                        e
-                    );
+                    log::error!("Failed to decrypt recipe {}: {}", id, e);
+                    undecryptable += 1;
+                    continue;
+                }
+            };
+            match self.load_recipe(id, &data) {
+                Ok(recipe) => self.cache(Arc::new(recipe)),
+                Err(e) => {
+                    log::error!("{}", e);
+                    errors.push(e);
                 }
             }
         }
+        progress(ids.len(), ids.len());
+
+        //A wrong passphrase can't decrypt anything, unlike a few damaged files
+        if undecryptable > 0 && decrypted == 0 {
+            return Err(CryptError::WrongPassphrase.into());
+        }
+        Ok(errors)
+    }
+
+    /// Load all recipes, decrypting them with a passphrase that new data is encrypted with as
+    /// well. The database stays locked if the passphrase is wrong
+    pub fn unlock(&self, passphrase: &str) -> Result<Vec<LoadError>, StorageError> {
+        *self.cipher.write() = Some(Arc::new(Cipher::new(passphrase)?));
+        self.load().inspect_err(|_| {
+            *self.cipher.write() = None;
+            self.locked.store(true, Ordering::SeqCst);
+        })
+    }
+
+    /// Keep anything from being written to storage until the database is
+    /// [unlocked](Self::unlock), for when recipes should be encrypted but there are no encrypted
+    /// recipes saved to tell that from
+    pub fn require_passphrase(&self) {
+        self.locked.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if the database is waiting for a passphrase to decrypt recipes
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    /// Check if recipes are encrypted when they are saved
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.read().is_some()
+    }
+
+    /// Encrypt every saved recipe and image with a new passphrase, or decrypt them back to plain
+    /// files if `passphrase` is `None`. Recipes in the trash are rewritten as well, recipes that
+    /// could not be loaded are left as they are. Everything is rewritten or nothing is, and the
+    /// new passphrase is only used once every write succeeded. Backups of the rewritten data are
+    /// removed, and the copies that are still plain data are returned when encrypting, including
+    /// snapshots in the `backups` directory
+    pub fn set_passphrase(
+        &self,
+        passphrase: Option<&str>,
+        backups: &Path,
+    ) -> Result<PlainCopies, StorageError> {
+        let new = passphrase.map(Cipher::new).transpose()?.map(Arc::new);
+        //Nothing else is written while recipes are rewritten, so that no recipe is saved with the
+        //old key after it was rewritten with the new one
+        if self.locked.swap(true, Ordering::SeqCst) {
+            return Err(CryptError::Locked.into());
+        }
+        let resealed = self.reseal_all(self.cipher().as_deref(), new.as_deref());
+        if resealed.is_ok() {
+            *self.cipher.write() = new;
+        }
+        self.locked.store(false, Ordering::SeqCst);
+        let trashed = resealed?;
+
+        self.index_dirty.store(true, Ordering::SeqCst);
+        //Every recipe is already written with the new key, so failing now must not make the
+        //caller think the old key is still used
+        if let Err(e) = self.save_index() {
+            log::error!("Failed to save recipe index: {}", e);
+        }
+        //Replaced files are kept as backups, which would leave plain copies of encrypted data
+        if let Err(e) = self.storage.remove_backups() {
+            log::error!("Failed to remove backups of rewritten recipes: {}", e);
+        }
+
+        match passphrase {
+            Some(_) => log::info!("Encrypted all saved recipes with a new passphrase"),
+            None => {
+                log::info!("Decrypted all saved recipes");
+                return Ok(PlainCopies::default());
+            }
+        }
 
-        self.dir.serialize(ser)
-    }
-
-    /// Load this database from a path item and directory with recipe data
-    pub fn load<'de, D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
-        let dir_path = Arc::<Path>::deserialize(de)?; //Load the directory path
-        let this = Self::new(&dir_path);
-        //this.insert(Recipe::top_ramen());
-
-        match std::fs::read_dir(&dir_path) {
-            Ok(dir) => {
-                for item in dir {
-                    match item {
-                        Ok(item) => {
-                            if let Some(name) = item.file_name().to_str() {
-                                match Uuid::parse_str(name) {
-                                    Ok(id) => {
-                                        log::trace!("Adding recipe file {} to db...", id);
-
-                                        match File::open(item.path().join(SAVE_FILE)) {
-                                            Ok(file) => match serde_json::from_reader(file) {
-                                                Ok(recipe) => {
-                                                    let recipe: Arc<Recipe> = Arc::new(recipe);
-                                                    this.items.write().insert(RecipeId(id), recipe);
-                                                }
-                                                Err(e) => {
-                                                    log::error!("Failed to deserialize recipe from folder {}: {}", id, e);
-                                                }
-                                            },
-                                            Err(e) => {
-                                                log::error!(
-                                                    "Failed to load recipe from folder {}: {}",
-                                                    id,
-                                                    e
-                                                );
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        log::warn!("Failed to parse directory item {} as UUID, not adding as entry...", e);
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!(
-                                "Failed to get directory entry in {}: {}",
-                                dir_path.display(),
-                                e
-                            );
-                        }
+        let mut plain = PlainCopies {
+            trashed,
+            quarantine: self.storage.quarantine_path(),
+            snapshots: Vec::new(),
+        };
+        match Snapshot::list(backups) {
+            Ok(snapshots) => {
+                for snapshot in snapshots {
+                    match snapshot.has_plain_recipes() {
+                        Ok(false) => (),
+                        Ok(true) => plain.snapshots.push(snapshot.path),
+                        Err(e) => log::warn!(
+                            "Failed to check if backup {} is encrypted: {}",
+                            snapshot.path.display(),
+                            e
+                        ),
                     }
                 }
             }
-            Err(e) => {
-                log::error!(
-                    "Failed to open recipe directory {}: {}",
-                    dir_path.display(),
-                    e
+            Err(e) => log::warn!("Failed to list backups in {}: {}", backups.display(), e),
+        }
+        if !plain.is_empty() {
+            log::warn!("{}", plain);
+        }
+        Ok(plain)
+    }
+
+    /// Rewrite every saved recipe and image sealed with the `old` cipher sealed with the `new`
+    /// one, undoing every write if any of them fails. Returns the deleted recipes that were left
+    /// in the trash as plain data because a recipe with the same ID is stored
+    fn reseal_all(
+        &self,
+        old: Option<&Cipher>,
+        new: Option<&Cipher>,
+    ) -> Result<Vec<RecipeId>, StorageError> {
+        let reseal = |data: Vec<u8>| -> Result<Vec<u8>, StorageError> {
+            Ok(crypt::seal(new, crypt::unseal(old, data)?)?)
+        };
+
+        //Everything is read and resealed before anything is written, so data that can't be
+        //decrypted stops the change before any of it is made. Unsaved changes are written with
+        //the new key once they are saved
+        let mut writes = Vec::new();
+        let mut plain = Vec::new();
+        for id in self.ids().iter().copied() {
+            if let Some(data) = self.storage.get(id)? {
+                writes.push(Staged::Sealed(id, reseal(data)?));
+            }
+            if let Some(image) = self.storage.get_image(id)? {
+                writes.push(Staged::Image(id, reseal(image)?));
+            }
+        }
+        //Recipes that could not be loaded are stored as well, and can't be restored over
+        let stored = self.storage.ids()?.into_iter().collect::<HashSet<_>>();
+        for entry in self.storage.list_trash()? {
+            if stored.contains(&entry.id) || self.index.read().contains_key(&entry.id) {
+                log::warn!(
+                    "Deleted recipe {} is also stored, leaving it in the trash as it is",
+                    entry.id
                 );
-                return Ok(this);
+                if !crypt::is_encrypted(&entry.data) {
+                    plain.push(entry.id);
+                }
+                continue;
             }
+            writes.push(Staged::Trashed {
+                id: entry.id,
+                deleted_on: entry.deleted_on,
+                data: reseal(entry.data)?,
+                image: self
+                    .storage
+                    .get_trashed_image(entry.id)?
+                    .map(reseal)
+                    .transpose()?,
+            });
         }
 
-        Ok(this)
+        let mut undo = Vec::new();
+        if let Err(e) = self.write_batch(&writes, &mut undo) {
+            log::error!(
+                "Failed to rewrite saved recipes with the new passphrase, undoing {} writes: {}",
+                undo.len(),
+                e
+            );
+            self.undo_writes(undo);
+            return Err(e);
+        }
+        Ok(plain)
     }
 
-    /// Get the image for a specified recipe ID and return an owned image buffer by cloning a
-    /// cached image or loading the image
-    pub fn get_image(&self, id: RecipeId) -> Option<ImageBuf> {
-        let mut images = self.images.write();
+    /// Get the cipher that saved data is encrypted with, if encryption is enabled
+    fn cipher(&self) -> Option<Arc<Cipher>> {
+        self.cipher.read().clone()
+    }
 
-        match images.get(&id) {
-            Some(cached) => Some(cached.clone()),
+    /// Encrypt data before it is written to storage if encryption is enabled
+    fn seal(&self, data: Vec<u8>) -> Result<Vec<u8>, StorageError> {
+        if self.is_locked() {
+            return Err(CryptError::Locked.into());
+        }
+        Ok(crypt::seal(self.cipher().as_deref(), data)?)
+    }
+
+    /// Decrypt data read from storage if it was encrypted
+    fn unseal(&self, data: Vec<u8>) -> Result<Vec<u8>, StorageError> {
+        Ok(crypt::unseal(self.cipher().as_deref(), data)?)
+    }
+
+    /// Read, upgrade, and deserialize one recipe from storage and keep it loaded, returning
+    /// `None` and removing it from the index if it is not stored
+    fn load_saved(&self, id: RecipeId) -> Result<Option<Arc<Recipe>>, StorageError> {
+        let data = match self.storage.get(id)? {
+            Some(data) => self.unseal(data)?,
             None => {
-                let image = ImageReader::open(self.dir.join(id.to_string()).join(IMG_FILE))
-                    .ok()?
-                    .decode()
-                    .ok()?;
-
-                log::trace!("Image data for {} dropped from cache, reloaded image", id);
-                let width = image.width() as usize;
-                let height = image.height() as usize;
-
-                let buf = ImageBuf::from_raw(
-                    &image.into_rgba8().as_raw()[..],
-                    druid::piet::ImageFormat::RgbaSeparate,
-                    width,
-                    height,
-                );
-                images.put(id, buf.clone());
+                self.unindex(id);
+                return Ok(None);
+            }
+        };
 
-                Some(buf)
+        log::trace!("Loading recipe {} from storage", id);
+        let recipe = Arc::new(self.load_recipe(id, &data)?);
+        self.cache(recipe.clone());
+        Ok(Some(recipe))
+    }
+
+    /// Keep a recipe loaded, dropping the least recently used recipes without unsaved changes if
+    /// more than [MAX_LOADED] recipes are loaded
+    fn cache(&self, recipe: Arc<Recipe>) {
+        self.cache_all(std::iter::once(recipe))
+    }
+
+    /// Keep many recipes loaded, see [cache](Self::cache)
+    fn cache_all(&self, recipes: impl IntoIterator<Item = Arc<Recipe>>) {
+        let mut pinned = self.dirty.lock().clone();
+        pinned.extend(self.conflicts.lock().keys().copied());
+
+        let mut items = self.items.lock();
+        for recipe in recipes {
+            items.put(recipe.id, recipe);
+        }
+        let mut kept = Vec::new();
+        while items.len() > MAX_LOADED {
+            match items.pop_lru() {
+                Some((id, recipe)) if pinned.contains(&id) => kept.push((id, recipe)),
+                Some((id, _)) => log::trace!("Unloading recipe {}", id),
+                None => break,
             }
         }
+        for (id, recipe) in kept {
+            items.put(id, recipe);
+        }
     }
 
-    /// Set the image file for the given recipe
-    pub fn set_image(&self, id: RecipeId, buf: ImageBuf) {
-        let recipes = self.items.read();
-        if !recipes.contains_key(&id) {
-            return;
+    /// Add or update the index entry of a recipe with the hash of its saved data, keeping the
+    /// hash it already has if `hash` is `None`
+    fn index_recipe(&self, recipe: &Recipe, hash: Option<u64>) {
+        let mut index = self.index.write();
+        let hash = hash.or_else(|| index.get(&recipe.id).and_then(|entry| entry.hash));
+        index.insert(recipe.id, IndexEntry::new(recipe, hash));
+        self.index_dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Remove a recipe from the index
+    fn unindex(&self, id: RecipeId) {
+        if self.index.write().remove(&id).is_some() {
+            self.index_dirty.store(true, Ordering::SeqCst);
+            self.changed();
         }
-        drop(recipes);
+    }
 
-        //Convert the imagebuf to a buffer from the image crate, this is cheap because only the Arc needs to be cloned, not the entire image
-        let img: ImageBuffer<Rgba<u8>, Arc<[u8]>> = ImageBuffer::from_raw(
-            buf.width() as u32,
-            buf.height() as u32,
-            buf.raw_pixels_shared(),
-        )
-        .unwrap();
+    /// Write the index to storage if it changed since it was last written
+    fn save_index(&self) -> Result<(), StorageError> {
+        if self.is_locked() || !self.index_dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
 
-        let dir = self.dir.join(id.to_string());
-        if !dir.exists() {
-            if let Err(e) = std::fs::create_dir(&dir) {
-                log::error!(
-                    "Failed to create recipe directory when adding recipe {} image: {}",
+        let result = index::serialize(&self.index.read())
+            .map_err(StorageError::from)
+            .and_then(|data| self.storage.put_index(&self.seal(data)?));
+        if result.is_err() {
+            self.index_dirty.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// Upgrade and deserialize saved recipe data and add it to the index, backing up and
+    /// resaving the recipe if it was saved by an older version
+    fn load_recipe(&self, id: RecipeId, data: &[u8]) -> Result<Recipe, LoadError> {
+        let error = |source| LoadError { id, source };
+        let migrated = migrate::migrate_recipe(data).map_err(error)?;
+        let from_version = migrated.from_version;
+        let mut recipe = serde_json::from_value::<Recipe>(migrated.value)
+            .map_err(|e| error(MigrationError::Json(e)))?;
+        if recipe.id != id {
+            log::warn!(
+                "Recipe stored as {} has ID {} in its saved data, using the stored ID",
+                id,
+                recipe.id
+            );
+            recipe.id = id;
+        }
+        self.index_recipe(&recipe, Some(data_hash(data)));
+
+        if from_version != RECIPE_VERSION {
+            log::info!(
+                "Upgrading recipe {} from format version {} to {}",
+                id,
+                from_version,
+                RECIPE_VERSION
+            );
+            let backup = self
+                .seal(data.to_vec())
+                .and_then(|sealed| self.storage.put_backup(id, from_version, &sealed));
+            match backup {
+                //Only overwrite the original once it is safely backed up
+                Ok(()) => {
+                    let _ = self.save_recipe(&recipe);
+                }
+                Err(e) => log::error!(
+                    "Failed to back up recipe {} before upgrading it, keeping the original: {}",
                     id,
                     e
-                );
-                return;
+                ),
             }
         }
-        let img_path = dir.join(IMG_FILE);
 
-        if let Err(e) = img.save_with_format(&img_path, ImageFormat::Png) {
+        Ok(recipe)
+    }
+
+    /// Watch the storage backend for changes made by other programs, calling `on_change` with the
+    /// IDs of changed recipes that should be [reloaded](Self::reload). Returns `None` if the
+    /// backend can't be watched
+    pub fn watch(&self, on_change: impl Fn(Vec<RecipeId>) + Send + 'static) -> Option<DirWatcher> {
+        let path = self.storage.watch_path()?;
+        if let Err(e) = fs::create_dir_all(path) {
             log::error!(
-                "Failed to set image data for recipe {} at {}: {}",
-                id,
-                img_path.display(),
+                "Failed to create recipe directory {}: {}",
+                path.display(),
                 e
             );
+            return None;
+        }
+
+        match DirWatcher::new(path, on_change) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log::error!("Failed to watch recipe directory {}: {}", path.display(), e);
+                None
+            }
         }
     }
 
-    /// Remove the recipe with the specified ID from the recipes database
-    pub fn remove(&self, id: RecipeId) {
-        self.items.write().remove(&id);
-        let recipe_dir = self.dir.join(id.to_string());
-        //Remove the recipe file as well
-        if let Err(e) = std::fs::remove_dir_all(&recipe_dir) {
-            log::warn!(
-                "Failed to remove recipe {} save folder {}: {}",
+    /// Reload recipes that were changed in storage by another program, returning the number of
+    /// recipes that had unsaved changes and are now [conflicts](Self::conflicts)
+    pub fn reload(&self, ids: &[RecipeId]) -> usize {
+        let mut new_conflicts = 0;
+        for &id in ids {
+            let data = match self
+                .storage
+                .get(id)
+                .and_then(|data| data.map(|data| self.unseal(data)).transpose())
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    log::warn!("Failed to read changed recipe {}: {}", id, e);
+                    continue;
+                }
+            };
+
+            //Our own saves and changes that were already reloaded have a matching hash
+            let hash = data.as_deref().map(data_hash);
+            if hash == self.index.read().get(&id).and_then(|entry| entry.hash) {
+                continue;
+            }
+
+            let theirs = match data {
+                Some(data) => match self.load_recipe(id, &data) {
+                    Ok(recipe) => Some(Arc::new(recipe)),
+                    Err(e) => {
+                        //The recipe may still be being written, wait for the next change
+                        log::warn!("Changed recipe can't be loaded: {}", e);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            if hash.is_none() {
+                if let Some(entry) = self.index.write().get_mut(&id) {
+                    entry.hash = None;
+                }
+            }
+
+            let has_changes =
+                self.dirty.lock().contains(&id) || self.dirty_images.lock().contains(&id);
+            if has_changes {
+                log::warn!(
+                    "Recipe {} was changed in storage and has unsaved changes",
+                    id
+                );
+                self.conflicts.lock().insert(
+                    id,
+                    Conflict {
+                        id,
+                        theirs,
+                        image: None,
+                        source: ConflictSource::Storage,
+                    },
+                );
+                new_conflicts += 1;
+                continue;
+            }
+
+            log::trace!("Reloading recipe {} changed in storage", id);
+            self.forget_image(id);
+            match theirs {
+                Some(recipe) => self.cache(recipe),
+                None => {
+                    self.items.lock().pop(&id);
+                    self.unindex(id);
+                }
+            }
+        }
+
+        new_conflicts
+    }
+
+    /// Get every recipe that was changed in two places and needs to be [resolved](Self::resolve)
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.conflicts.lock().values().cloned().collect()
+    }
+
+    /// Resolve a conflict by choosing which copy of the recipe to keep
+    pub fn resolve(&self, id: RecipeId, resolution: Resolution) -> Result<(), StorageError> {
+        let conflict = match self.conflicts.lock().remove(&id) {
+            Some(conflict) => conflict,
+            None => return Ok(()),
+        };
+        log::trace!("Resolving conflict for recipe {} with {:?}", id, resolution);
+        let import = conflict.source == ConflictSource::Import;
+
+        match (resolution, conflict.theirs) {
+            (Resolution::KeepMine, _) | (Resolution::KeepBoth, None) => {
+                self.dirty.lock().insert(id);
+                self.save()
+            }
+            (Resolution::KeepTheirs, theirs) => {
+                self.dirty.lock().remove(&id);
+                self.dirty_images.lock().remove(&id);
+                self.forget_image(id);
+                match theirs {
+                    Some(theirs) => {
+                        self.index_recipe(&theirs, None);
+                        self.cache(theirs);
+                    }
+                    None => {
+                        self.items.lock().pop(&id);
+                        self.unindex(id);
+                    }
+                }
+                match import {
+                    true => self.save_imported(id, conflict.image),
+                    false => Ok(()),
+                }
+            }
+            (Resolution::KeepBoth, Some(theirs)) => {
+                let mine = self.get(id);
+                self.dirty.lock().remove(&id);
+                self.index_recipe(&theirs, None);
+                self.cache(theirs);
+                let image = match self.dirty_images.lock().remove(&id) {
+                    true => self.forget_image(id),
+                    //The stored image of our copy is replaced by the imported image
+                    false if import => self.get_image(id),
+                    false => None,
+                };
+                if import {
+                    self.save_imported(id, conflict.image)?;
+                }
+
+                if let Some(mine) = mine {
+                    let copy = Recipe {
+                        id: RecipeId::new(),
+                        ..Recipe::clone(&mine)
+                    };
+                    let copy_id = copy.id;
+                    self.insert(copy)?;
+                    if let Some(image) = image {
+                        self.set_image(copy_id, image)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Save an imported recipe that was chosen over our copy along with its image
+    fn save_imported(&self, id: RecipeId, image: Option<Arc<[u8]>>) -> Result<(), StorageError> {
+        if let Some(recipe) = self.get(id) {
+            self.save_recipe(&recipe)?;
+        }
+        if let Some(image) = image {
+            self.storage.put_image(id, &self.seal(image.to_vec())?)?;
+            self.forget_image(id);
+        }
+        Ok(())
+    }
+
+    /// Merge the recipes in another cookbook's recipe directory into this database. Recipes
+    /// with new IDs are added, recipes identical to ours are skipped, and recipes that differ
+    /// from ours become [conflicts](Self::conflicts) to resolve
+    pub fn import(&self, dir: &Path) -> Result<ImportSummary, StorageError> {
+        log::info!("Importing recipes from {}", dir.display());
+        let other = DirStorage::new(dir);
+        let mut summary = ImportSummary::default();
+        let mut added = Batch::new();
+
+        for (id, data) in other.load_all()? {
+            //Cookbooks encrypted with the same passphrase can be imported as well
+            let theirs = match self
+                .unseal(data)
+                .map_err(|e| e.to_string())
+                .and_then(|data| parse_recipe(id, &data).map_err(|e| e.to_string()))
+            {
+                Ok(theirs) => theirs,
+                Err(e) => {
+                    log::warn!("Failed to load imported recipe {}: {}", id, e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+            let image = match other.get_image(id)?.map(|image| self.unseal(image)) {
+                Some(Ok(image)) => Some(image),
+                Some(Err(e)) => {
+                    log::warn!("Failed to decrypt imported image for recipe {}: {}", id, e);
+                    None
+                }
+                None => None,
+            };
+
+            let mine = match self.get(id) {
+                Some(mine) => mine,
+                None => {
+                    added.insert(theirs);
+                    if let Some(image) = image {
+                        added.image(id, image);
+                    }
+                    summary.added += 1;
+                    continue;
+                }
+            };
+
+            let same_image = match image {
+                Some(ref image) => {
+                    let mine = self.storage.get_image(id)?;
+                    mine.map(|mine| self.unseal(mine)).transpose()?.as_ref() == Some(image)
+                }
+                None => true,
+            };
+            if *mine == theirs && same_image {
+                summary.identical += 1;
+                continue;
+            }
+
+            let mut conflicts = self.conflicts.lock();
+            if conflicts.contains_key(&id) {
+                log::warn!(
+                    "Imported recipe {} already has a conflict to resolve, skipping it",
+                    id
+                );
+                summary.failed += 1;
+                continue;
+            }
+            conflicts.insert(
                 id,
-                recipe_dir.display(),
+                Conflict {
+                    id,
+                    theirs: Some(Arc::new(theirs)),
+                    image: image.map(Arc::from),
+                    source: ConflictSource::Import,
+                },
+            );
+            summary.conflicts += 1;
+        }
+
+        self.commit(added)?;
+        log::info!("{}", summary);
+        Ok(summary)
+    }
+
+    /// Insert a recipe into the database, replacing any recipe with the same ID, and save it
+    pub fn insert(&self, recipe: Recipe) -> Result<(), StorageError> {
+        let recipe = Arc::new(recipe);
+        match self.index.read().contains_key(&recipe.id) {
+            true => log::trace!(
+                "Database already contains recipe with ID {}, updating instead...",
+                recipe.id
+            ),
+            false => log::trace!("inserting recipe with ID {} into database...", recipe.id),
+        }
+
+        self.dirty.lock().insert(recipe.id);
+        self.index_recipe(&recipe, None);
+        self.cache(recipe.clone());
+        self.changed();
+        self.save_recipe(&recipe)
+    }
+
+    /// Make every change staged in a batch, or none of them if any change can't be written to
+    /// storage. Recipes are written to storage before anything changes in memory, and writes
+    /// that were already made are undone if a later write fails
+    pub fn commit(&self, batch: Batch) -> Result<(), StorageError> {
+        let changes = batch.len();
+        if changes == 0 {
+            return Ok(());
+        }
+
+        //The state of each changed recipe after every change, in the order they were changed
+        let mut order = Vec::new();
+        let mut staged = HashMap::<RecipeId, Option<Arc<Recipe>>>::new();
+        let mut images = Vec::new();
+        for change in batch.changes {
+            let (id, recipe, insert) = match change {
+                Change::Insert(recipe) => (recipe.id, Some(recipe), true),
+                Change::Update(recipe) => (recipe.id, Some(recipe), false),
+                Change::Remove(id) => (id, None, false),
+                Change::Image(id, data) => {
+                    images.push((id, Some(data)));
+                    continue;
+                }
+                Change::DeleteImage(id) => {
+                    images.push((id, None));
+                    continue;
+                }
+            };
+            let exists = staged
+                .get(&id)
+                .map_or_else(|| self.contains(id), Option::is_some);
+            if (insert || exists) && staged.insert(id, recipe).is_none() {
+                order.push(id);
+            }
+        }
+
+        //Images are written after the recipes, and only for recipes that are left after the batch
+        images.retain(|(id, _)| {
+            staged
+                .get(id)
+                .map_or_else(|| self.contains(*id), Option::is_some)
+        });
+        if let Some(id) = order
+            .iter()
+            .chain(images.iter().map(|(id, _)| id))
+            .find(|id| self.conflicts.lock().contains_key(*id))
+        {
+            return Err(StorageError::Conflict(*id));
+        }
+        //Make sure the copies in the trash have the latest changes
+        let removed_dirty = order.iter().any(|id| {
+            staged[id].is_none()
+                && (self.dirty.lock().contains(id) || self.dirty_images.lock().contains(id))
+        });
+        if removed_dirty {
+            let _ = self.save();
+        }
+
+        let mut writes = Vec::with_capacity(order.len());
+        for id in order {
+            writes.push(match staged.remove(&id).flatten() {
+                Some(recipe) => {
+                    let data = serde_json::to_vec(&Versioned::recipe(&recipe))?;
+                    let hash = data_hash(&data);
+                    Staged::Put(recipe, self.seal(data)?, hash)
+                }
+                None if self.contains(id) => Staged::Remove(id),
+                //Inserted and removed in the same batch
+                None => continue,
+            });
+        }
+        for (id, data) in images {
+            writes.push(match data {
+                Some(data) => Staged::Image(id, self.seal(data)?),
+                None => Staged::DeleteImage(id),
+            });
+        }
+
+        let mut undo = Vec::new();
+        if let Err(e) = self.write_batch(&writes, &mut undo) {
+            log::error!(
+                "Failed to commit {} changes, undoing {} writes: {}",
+                changes,
+                undo.len(),
                 e
             );
-        } else {
-            log::trace!("Removed save folder and db entry for recipe {}", id);
+            self.undo_writes(undo);
+            return Err(e);
+        }
+
+        let mut puts = Vec::new();
+        let mut removed = Vec::new();
+        let mut imaged = Vec::new();
+        {
+            let mut index = self.index.write();
+            for write in writes {
+                match write {
+                    Staged::Put(recipe, _, hash) => {
+                        index.insert(recipe.id, IndexEntry::new(&recipe, Some(hash)));
+                        puts.push(recipe);
+                    }
+                    Staged::Remove(id) => {
+                        index.remove(&id);
+                        removed.push(id);
+                    }
+                    Staged::Image(id, _) | Staged::DeleteImage(id) => imaged.push(id),
+                    //Only written when changing the passphrase, never staged by a batch
+                    Staged::Sealed(..) | Staged::Trashed { .. } => (),
+                }
+            }
+        }
+        self.index_dirty.store(true, Ordering::SeqCst);
+        {
+            let mut dirty = self.dirty.lock();
+            let mut dirty_images = self.dirty_images.lock();
+            for id in puts.iter().map(|recipe| &recipe.id).chain(&removed) {
+                dirty.remove(id);
+            }
+            for id in removed.iter().chain(&imaged) {
+                dirty_images.remove(id);
+            }
+        }
+        {
+            let mut items = self.items.lock();
+            let mut images = self.images.write();
+            let mut thumbnails = self.thumbnails.write();
+            for id in &removed {
+                items.pop(id);
+            }
+            for id in removed.iter().chain(&imaged) {
+                images.pop(id);
+                thumbnails.pop(id);
+            }
+        }
+        self.decoding
+            .lock()
+            .retain(|(id, _)| !removed.contains(id) && !imaged.contains(id));
+        self.cache_all(puts);
+        self.changed();
+
+        if let Err(e) = self.save_index() {
+            log::error!("Failed to save recipe index: {}", e);
+        }
+        log::info!("Committed {} changes to recipes", changes);
+        Ok(())
+    }
+
+    /// Write the recipes in a batch to storage, recording how to undo each write
+    fn write_batch(&self, writes: &[Staged], undo: &mut Vec<Undo>) -> Result<(), StorageError> {
+        for write in writes {
+            match write {
+                Staged::Put(recipe, data, _) => {
+                    let previous = self.storage.get(recipe.id)?;
+                    self.storage.put(recipe.id, data)?;
+                    undo.push(Undo::Put(recipe.id, previous));
+                }
+                //Recipes that failed to save are only in memory
+                Staged::Remove(id) if self.storage.get(*id)?.is_none() => (),
+                Staged::Remove(id) => {
+                    self.storage.trash(*id, Utc::now())?;
+                    undo.push(Undo::Trash(*id));
+                }
+                Staged::Image(id, data) => {
+                    let previous = self.storage.get_image(*id)?;
+                    self.storage.put_image(*id, data)?;
+                    undo.push(Undo::Image(*id, previous));
+                }
+                Staged::DeleteImage(id) => {
+                    if let Some(previous) = self.storage.get_image(*id)? {
+                        self.storage.delete_image(*id)?;
+                        undo.push(Undo::Image(*id, Some(previous)));
+                    }
+                }
+                Staged::Sealed(id, data) => {
+                    let previous = self.storage.get(*id)?;
+                    self.storage.put(*id, data)?;
+                    undo.push(Undo::Put(*id, previous));
+                }
+                //Storage can only write recipes that are not deleted, so take it out of the trash
+                //to rewrite it
+                Staged::Trashed {
+                    id,
+                    deleted_on,
+                    data,
+                    image,
+                } => {
+                    self.storage.restore(*id)?;
+                    undo.push(Undo::Restore(*id, *deleted_on));
+                    let previous = self.storage.get(*id)?;
+                    self.storage.put(*id, data)?;
+                    undo.push(Undo::Put(*id, previous));
+                    if let Some(image) = image {
+                        let previous = self.storage.get_image(*id)?;
+                        self.storage.put_image(*id, image)?;
+                        undo.push(Undo::Image(*id, previous));
+                    }
+                    self.storage.trash(*id, *deleted_on)?;
+                    undo.push(Undo::Trash(*id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo the writes made while committing a batch that failed, most recent first
+    fn undo_writes(&self, undo: Vec<Undo>) {
+        for undo in undo.into_iter().rev() {
+            let (id, result) = match undo {
+                Undo::Put(id, Some(data)) => (id, self.storage.put(id, &data)),
+                Undo::Put(id, None) => (id, self.storage.delete(id)),
+                Undo::Trash(id) => (id, self.storage.restore(id)),
+                Undo::Image(id, Some(data)) => (id, self.storage.put_image(id, &data)),
+                Undo::Image(id, None) => (id, self.storage.delete_image(id)),
+                Undo::Restore(id, deleted_on) => (id, self.storage.trash(id, deleted_on)),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to undo write to recipe {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Serialize one recipe and write it to storage, marking it as clean if it was written.
+    /// Recipes with unresolved conflicts are not written
+    fn save_recipe(&self, recipe: &Recipe) -> Result<(), StorageError> {
+        if self.conflicts.lock().contains_key(&recipe.id) {
+            return Err(StorageError::Conflict(recipe.id));
+        }
+
+        serde_json::to_vec(&Versioned::recipe(recipe))
+            .map_err(StorageError::from)
+            .and_then(|data| {
+                let hash = data_hash(&data);
+                self.storage.put(recipe.id, &self.seal(data)?)?;
+                Ok(hash)
+            })
+            .map(|hash| {
+                self.index_recipe(recipe, Some(hash));
+                self.dirty.lock().remove(&recipe.id);
+            })
+            .map_err(|e| {
+                log::error!("Failed to save recipe {}: {}", recipe.id, e);
+                e
+            })
+    }
+
+    /// Encode an image and write it to storage, marking it as clean if it was written
+    fn save_image(&self, id: RecipeId, buf: &ImageBuf) -> Result<(), StorageError> {
+        if self.conflicts.lock().contains_key(&id) {
+            return Err(StorageError::Conflict(id));
+        }
+
+        match self
+            .seal(images::encode(buf)?)
+            .and_then(|sealed| self.storage.put_image(id, &sealed))
+        {
+            Ok(()) => {
+                self.dirty_images.lock().remove(&id);
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("Failed to set image data for recipe {}: {}", id, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Check if any recipes or images have changes that are not written to storage
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.lock().is_empty() || !self.dirty_images.lock().is_empty()
+    }
+
+    /// Write every recipe and image that changed since it was last written to storage,
+    /// returning the last error encountered
+    pub fn save(&self) -> Result<(), StorageError> {
+        let dirty = self.dirty.lock().iter().copied().collect::<Vec<_>>();
+        let dirty_images = self.dirty_images.lock().iter().copied().collect::<Vec<_>>();
+        let mut result = Ok(());
+
+        for id in dirty {
+            match self.get(id) {
+                //Errors are logged by save_recipe, keep saving the rest of the recipes
+                Some(recipe) => {
+                    if let Err(e) = self.save_recipe(&recipe) {
+                        result = Err(e);
+                    }
+                }
+                None => {
+                    self.dirty.lock().remove(&id);
+                }
+            }
+        }
+
+        for id in dirty_images {
+            let cached = self.images.write().get(&id).cloned().flatten();
+            match cached {
+                Some(buf) => {
+                    if let Err(e) = self.save_image(id, &buf) {
+                        result = Err(e);
+                    }
+                }
+                None => {
+                    log::error!(
+                        "Changed image for recipe {} was dropped from the cache before it could be saved",
+                        id
+                    );
+                    self.dirty_images.lock().remove(&id);
+                }
+            }
+        }
+
+        if let Err(e) = self.save_index() {
+            log::error!("Failed to save recipe index: {}", e);
+            result = Err(e);
+        }
+        result
+    }
+
+    /// Write every recipe in this database to storage whether it changed or not, returning the
+    /// last error encountered. Images are only written if they changed
+    pub fn rewrite_all(&self) -> Result<(), StorageError> {
+        log::info!("Rewriting all {} recipes", self.len());
+        let mut result = Ok(());
+        for id in self.ids().iter() {
+            if let Some(recipe) = self.get(*id) {
+                if let Err(e) = self.save_recipe(&recipe) {
+                    result = Err(e);
+                }
+            }
+        }
+
+        self.save().and(result)
+    }
+
+    /// Check the stored recipes for problems, repairing them if `repair` is true. Repaired
+    /// recipes are loaded again, or removed from the database if they were quarantined
+    pub fn check(&self, repair: bool) -> Vec<check::Finding> {
+        let findings = self.storage.check(repair);
+        log::info!("Database check found {} problems", findings.len());
+
+        //Repairs change storage behind the database's back, like another program would
+        let repaired = findings
+            .iter()
+            .filter(|finding| finding.repair.is_repaired())
+            .filter_map(|finding| finding.issue.id())
+            .collect::<Vec<_>>();
+        if !repaired.is_empty() {
+            self.reload(&repaired);
+        }
+        findings
+    }
+
+    /// Get the image for a specified recipe ID and return an owned image buffer by cloning a
+    /// cached image or loading the image. This decodes the image on the calling thread, widgets
+    /// should use [load_image](Self::load_image) instead
+    pub fn get_image(&self, id: RecipeId) -> Option<ImageBuf> {
+        if let Some(cached) = self.images.write().get(&id) {
+            return cached.clone();
+        }
+
+        let buf = self.read_image(id, images::decode);
+        log::trace!("Image data for {} dropped from cache, reloaded image", id);
+        self.images.write().put(id, buf.clone());
+        buf
+    }
+
+    /// Get the image or thumbnail of a recipe if it is cached. Otherwise the image is decoded on
+    /// a background thread and `loaded` is called once it is cached, unless it is already being
+    /// decoded
+    pub fn load_image(
+        &self,
+        id: RecipeId,
+        size: ImageSize,
+        loaded: impl FnOnce() + Send + 'static,
+    ) -> ImageState {
+        if let Some(cached) = self.image_cache(size).write().get(&id) {
+            return cached
+                .clone()
+                .map_or(ImageState::Missing, ImageState::Ready);
+        }
+        if !self.decoding.lock().insert((id, size)) {
+            return ImageState::Loading;
+        }
+
+        let db = self.clone();
+        self.decoder.run(move || {
+            let buf = match size {
+                ImageSize::Full => db.read_image(id, images::decode),
+                ImageSize::Thumbnail => {
+                    let decoded = db.images.read().peek(&id).cloned();
+                    match decoded {
+                        Some(decoded) => decoded.as_ref().map(images::thumbnail),
+                        None => db.read_thumbnail(id),
+                    }
+                }
+            };
+            //The image was changed while it was decoded, keep the new image in the cache
+            if db.decoding.lock().remove(&(id, size)) {
+                db.image_cache(size).write().put(id, buf);
+            }
+            loaded();
+        });
+        ImageState::Loading
+    }
+
+    /// Get the cache that images of the given size are kept in
+    fn image_cache(&self, size: ImageSize) -> &RwLock<LruCache<RecipeId, Option<ImageBuf>>> {
+        match size {
+            ImageSize::Full => &self.images,
+            ImageSize::Thumbnail => &self.thumbnails,
+        }
+    }
+
+    /// Read and decode the stored image for a recipe, logging any errors
+    fn read_image(
+        &self,
+        id: RecipeId,
+        decode: impl FnOnce(&[u8]) -> druid::image::ImageResult<ImageBuf>,
+    ) -> Option<ImageBuf> {
+        let data = match self
+            .storage
+            .get_image(id)
+            .and_then(|data| data.map(|data| self.unseal(data)).transpose())
+        {
+            Ok(data) => data?,
+            Err(e) => {
+                log::error!("Failed to read image for recipe {}: {}", id, e);
+                return None;
+            }
+        };
+
+        decode(&data)
+            .map_err(|e| log::error!("Failed to decode image for recipe {}: {}", id, e))
+            .ok()
+    }
+
+    /// Read the stored thumbnail of a recipe's image, logging any errors. If no thumbnail was
+    /// stored since the image changed, one is made from the image and stored for next time
+    fn read_thumbnail(&self, id: RecipeId) -> Option<ImageBuf> {
+        let stored = self
+            .storage
+            .get_thumbnail(id)
+            .and_then(|data| data.map(|data| self.unseal(data)).transpose());
+        match stored.map(|data| data.map(|data| images::decode(&data))) {
+            Ok(Some(Ok(thumbnail))) => return Some(thumbnail),
+            Ok(Some(Err(e))) => log::warn!("Failed to decode thumbnail for recipe {}: {}", id, e),
+            Ok(None) => (),
+            Err(e) => log::warn!("Failed to read thumbnail for recipe {}: {}", id, e),
+        }
+
+        let thumbnail = self.read_image(id, images::decode_thumbnail)?;
+        let stored = images::encode(&thumbnail)
+            .map_err(StorageError::from)
+            .and_then(|data| self.seal(data))
+            .and_then(|sealed| self.storage.put_thumbnail(id, &sealed));
+        if let Err(e) = stored {
+            log::warn!("Failed to save thumbnail for recipe {}: {}", id, e);
         }
+        Some(thumbnail)
+    }
+
+    /// Drop the cached image and thumbnail for a recipe after its image changed, returning the
+    /// cached image
+    fn forget_image(&self, id: RecipeId) -> Option<ImageBuf> {
+        self.changed();
+        self.decoding.lock().retain(|(decoding, _)| *decoding != id);
+        self.thumbnails.write().pop(&id);
+        self.images.write().pop(&id).flatten()
+    }
+
+    /// Drop every cached image and thumbnail
+    fn forget_images(&self) {
+        self.changed();
+        self.decoding.lock().clear();
+        self.thumbnails.write().clear();
+        self.images.write().clear();
+    }
+
+    /// Set the image file for the given recipe and save it
+    pub fn set_image(&self, id: RecipeId, buf: ImageBuf) -> Result<(), StorageError> {
+        if !self.index.read().contains_key(&id) {
+            return Ok(());
+        }
+
+        self.forget_image(id);
+        self.images.write().put(id, Some(buf.clone()));
+        self.dirty_images.lock().insert(id);
+        self.save_image(id, &buf)
+    }
+
+    /// Delete the image of the given recipe from storage, dropping any change to it that was not
+    /// saved yet
+    pub fn delete_image(&self, id: RecipeId) -> Result<(), StorageError> {
+        if !self.index.read().contains_key(&id) {
+            return Ok(());
+        }
+        if self.is_locked() {
+            return Err(CryptError::Locked.into());
+        }
+        if self.conflicts.lock().contains_key(&id) {
+            return Err(StorageError::Conflict(id));
+        }
+
+        if let Err(e) = self.storage.delete_image(id) {
+            log::error!("Failed to delete image of recipe {}: {}", id, e);
+            return Err(e);
+        }
+        self.forget_image(id);
+        self.images.write().put(id, None);
+        self.dirty_images.lock().remove(&id);
+        Ok(())
+    }
+
+    /// Remove the recipe with the specified ID from the recipes database and move it to the
+    /// trash, where it can be [restored](Self::restore) until it is purged
+    pub fn remove(&self, id: RecipeId) -> Result<(), StorageError> {
+        //Make sure the copy in the trash has the latest changes
+        if self.dirty.lock().contains(&id) || self.dirty_images.lock().contains(&id) {
+            let _ = self.save();
+        }
+
+        //Move the saved recipe to the trash first, so that it is kept if that fails
+        if let Err(e) = self.storage.trash(id, Utc::now()) {
+            log::warn!("Failed to move saved recipe {} to the trash: {}", id, e);
+            return Err(e);
+        }
+        log::trace!("Moved saved data for recipe {} to the trash", id);
+
+        self.items.lock().pop(&id);
+        self.forget_image(id);
+        self.dirty.lock().remove(&id);
+        self.dirty_images.lock().remove(&id);
+        self.unindex(id);
+        self.conflicts.lock().remove(&id);
+        Ok(())
+    }
+
+    /// Get every recipe in the trash, most recently deleted first
+    pub fn trash(&self) -> Result<Vec<TrashedRecipe>, StorageError> {
+        let mut trash = self
+            .storage
+            .list_trash()?
+            .into_iter()
+            .filter_map(|entry| {
+                let id = entry.id;
+                let recipe = self
+                    .unseal(entry.data)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| parse_recipe(id, &data).map_err(|e| e.to_string()));
+                match recipe {
+                    Ok(recipe) => Some(TrashedRecipe {
+                        recipe: Arc::new(recipe),
+                        deleted_on: entry.deleted_on,
+                    }),
+                    Err(e) => {
+                        log::warn!("Failed to load deleted recipe {}: {}", entry.id, e);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        trash.sort_by_key(|trashed| std::cmp::Reverse(trashed.deleted_on));
+        Ok(trash)
+    }
+
+    /// Move a recipe out of the trash and back into the database. Fails if a recipe with the same
+    /// ID is in the database, which is never replaced
+    pub fn restore(&self, id: RecipeId) -> Result<(), StorageError> {
+        if self.contains(id) {
+            return Err(StorageError::AlreadyStored(id));
+        }
+        self.storage.restore(id)?;
+        let data = self.storage.get(id)?.ok_or(StorageError::NotInTrash(id))?;
+        let data = self.unseal(data)?;
+        let recipe = self.load_recipe(id, &data)?;
+
+        log::trace!("Restored recipe {} from the trash", id);
+        self.cache(Arc::new(recipe));
+        self.changed();
+        Ok(())
+    }
+
+    /// Permanently delete a recipe in the trash
+    pub fn purge(&self, id: RecipeId) -> Result<(), StorageError> {
+        log::trace!("Permanently deleting recipe {}", id);
+        self.storage.purge(id)
+    }
+
+    /// Permanently delete every recipe that has been in the trash for longer than the given
+    /// number of days, returning the number of recipes deleted
+    pub fn purge_older_than(&self, days: u32) -> Result<usize, StorageError> {
+        let cutoff = Utc::now() - chrono::Duration::days(days.into());
+        let mut purged = 0;
+        for entry in self.storage.list_trash()? {
+            if entry.deleted_on < cutoff {
+                self.purge(entry.id)?;
+                purged += 1;
+            }
+        }
+
+        if purged > 0 {
+            log::info!("Purged {} recipes deleted over {} days ago", purged, days);
+        }
+        Ok(purged)
+    }
+
+    /// Save any unsaved changes and write a snapshot of every stored recipe and the given saved
+    /// app state to a backup directory
+    pub fn backup(&self, state: &[u8], dir: &Path) -> Result<Snapshot, BackupError> {
+        if let Err(e) = self.save() {
+            log::warn!("Backing up recipes that could not all be saved: {}", e);
+        }
+
+        let snapshot = backup::create(self.storage.as_ref(), state, dir)?;
+        log::info!(
+            "Backed up {} recipes to {}",
+            self.len(),
+            snapshot.path.display()
+        );
+        Ok(snapshot)
+    }
+
+    /// Replace one recipe and its image with the copy saved in a snapshot, adding it back if it
+    /// was removed
+    pub fn restore_backup(&self, snapshot: &Snapshot, id: RecipeId) -> Result<(), BackupError> {
+        let data = snapshot.recipe(id)?.ok_or(BackupError::MissingRecipe(id))?;
+        let image = snapshot.image(id)?;
+        self.restore_saved(id, data, image)
+    }
+
+    /// Replace every recipe with the recipes saved in a snapshot, moving recipes that are not in
+    /// the snapshot to the trash. Every change is committed in one [Batch], so nothing is changed
+    /// if any recipe can't be restored. Returns the number of recipes restored
+    pub fn restore_all_backup(&self, snapshot: &Snapshot) -> Result<usize, BackupError> {
+        let recipes = snapshot.recipes()?;
+        let restored = recipes.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+        let mut batch = Batch::new();
+        for id in self.ids().iter().filter(|id| !restored.contains(*id)) {
+            batch.remove(*id);
+        }
+        for (id, data) in recipes {
+            let data = self.unseal(data)?;
+            let recipe = parse_recipe(id, &data).map_err(|e| BackupError::Unreadable {
+                id,
+                error: e.to_string(),
+            })?;
+            batch.insert(recipe);
+            match snapshot.image(id)? {
+                Some(image) => batch.image(id, self.unseal(image)?),
+                None => batch.delete_image(id),
+            };
+        }
+
+        //Every recipe is replaced or removed, so no conflicts or unsaved images are kept once the
+        //batch is committed. The batch can't be committed while they are pending, so they are
+        //put back if it fails
+        let conflicts = std::mem::take(&mut *self.conflicts.lock());
+        if let Err(e) = self.commit(batch) {
+            let mut pending = self.conflicts.lock();
+            for (id, conflict) in conflicts {
+                pending.entry(id).or_insert(conflict);
+            }
+            return Err(e.into());
+        }
+        self.dirty_images.lock().retain(|id| !restored.contains(id));
+
+        let restored = restored.len();
+
+        log::info!(
+            "Restored {} recipes from backup {}",
+            restored,
+            snapshot.path.display()
+        );
+        Ok(restored)
+    }
+
+    /// Read every recipe saved in a snapshot without restoring them, skipping recipes that can't
+    /// be read
+    pub fn backup_recipes(&self, snapshot: &Snapshot) -> Result<Vec<Recipe>, BackupError> {
+        Ok(snapshot
+            .recipes()?
+            .into_iter()
+            .filter_map(|(id, data)| {
+                let recipe = self
+                    .unseal(data)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| parse_recipe(id, &data).map_err(|e| e.to_string()));
+                match recipe {
+                    Ok(recipe) => Some(recipe),
+                    Err(e) => {
+                        log::warn!("Failed to read recipe {} in backup: {}", id, e);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Write saved recipe data and its image to storage and load it, replacing any unsaved or
+    /// conflicting changes to the recipe
+    fn restore_saved(
+        &self,
+        id: RecipeId,
+        data: Vec<u8>,
+        image: Option<Vec<u8>>,
+    ) -> Result<(), BackupError> {
+        let data = self.unseal(data)?;
+        let image = image.map(|image| self.unseal(image)).transpose()?;
+        match check::check_recipe(id, &data) {
+            Some(Issue::Unreadable { id, error }) => {
+                return Err(BackupError::Unreadable { id, error })
+            }
+            Some(issue @ Issue::TooNew { .. }) => {
+                return Err(BackupError::Unreadable {
+                    id,
+                    error: issue.to_string(),
+                })
+            }
+            _ => (),
+        }
+
+        self.conflicts.lock().remove(&id);
+        self.dirty.lock().remove(&id);
+        self.dirty_images.lock().remove(&id);
+        self.forget_image(id);
+
+        self.storage.put(id, &self.seal(data.clone())?)?;
+        if let Some(image) = image {
+            self.storage.put_image(id, &self.seal(image)?)?;
+        }
+        let recipe = self.load_recipe(id, &data).map_err(StorageError::from)?;
+        self.cache(Arc::new(recipe));
+        Ok(())
     }
 
     /// Get an iterator over all ids for recipes in this database
     pub fn ids(&self) -> Arc<[RecipeId]> {
-        let items = self.items.read();
-        items.iter().map(|(id, _)| *id).collect()
+        self.index.read().keys().copied().collect()
     }
 }
 
+/// Upgrade and deserialize saved recipe data without saving it, using the ID it is stored as
+pub fn parse_recipe(id: RecipeId, data: &[u8]) -> Result<Recipe, MigrationError> {
+    let migrated = migrate::migrate_recipe(data)?;
+    let recipe = serde_json::from_value::<Recipe>(migrated.value)?;
+    Ok(Recipe { id, ..recipe })
+}
+
+/// Hash saved recipe data to tell if it was changed. Hashes are saved in the index, so this uses
+/// 64 bit FNV-1a, which gives the same hash on every platform and Rust version
+fn data_hash(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    data.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
 impl druid::widget::ListIter<Recipe> for Database {
     fn for_each(&self, mut cb: impl FnMut(&Recipe, usize)) {
-        let items = self.items.read();
-        for (i, (_, recipe)) in items.iter().enumerate() {
-            cb(recipe.deref(), i)
+        for (i, id) in self.ids().iter().enumerate() {
+            if let Some(recipe) = self.get(*id) {
+                cb(recipe.deref(), i)
+            }
         }
     }
 
     fn for_each_mut(&mut self, mut cb: impl FnMut(&mut Recipe, usize)) {
-        let mut items = self.items.write();
-        for (i, (_, recipe)) in items.iter_mut().enumerate() {
-            let recipe = Arc::make_mut(recipe);
-            cb(recipe, i)
+        for (i, id) in self.ids().iter().enumerate() {
+            if let Some(old) = self.get(*id) {
+                let mut recipe = old.clone();
+                cb(Arc::make_mut(&mut recipe), i);
+                if old != recipe {
+                    self.dirty.lock().insert(*id);
+                    self.index_recipe(&recipe, None);
+                    self.cache(recipe);
+                }
+            }
         }
     }
 
     fn data_len(&self) -> usize {
-        self.items.read().len()
+        self.len()
+    }
+}
+
+impl Default for Database {
+    /// Create an empty database that is only kept in memory
+    fn default() -> Self {
+        Self::new(MemoryStorage::default())
     }
 }
 
 impl PartialEq for Database {
     fn eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.items, &other.items)
+        Arc::ptr_eq(&self.items, &other.items) && self.revision == other.revision
+    }
+}
+
+/// A deleted recipe in the trash
+#[derive(Clone, Debug)]
+pub struct TrashedRecipe {
+    /// The deleted recipe
+    pub recipe: Arc<Recipe>,
+    /// When the recipe was deleted
+    pub deleted_on: DateTime<Utc>,
+}
+
+/// How many recipes were added, skipped, or left to resolve when importing another cookbook
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportSummary {
+    /// Recipes that were not in this database and were added
+    pub added: usize,
+    /// Recipes that were already in this database with the same contents
+    pub identical: usize,
+    /// Recipes that differ from our copy and need to be resolved
+    pub conflicts: usize,
+    /// Recipes that could not be imported
+    pub failed: usize,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} new recipes, skipped {} identical recipes, {} recipes differ from ours",
+            self.added, self.identical, self.conflicts
+        )?;
+        if self.failed > 0 {
+            write!(f, ", {} recipes could not be imported", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies of recipes that are still plain data after every saved recipe was encrypted, which
+/// the user has to remove themselves
+#[derive(Clone, Debug, Default)]
+pub struct PlainCopies {
+    /// Deleted recipes left in the trash because a recipe with the same ID is stored
+    pub trashed: Vec<RecipeId>,
+    /// The folder that unreadable recipes were set aside in when repairing, if any are there
+    pub quarantine: Option<PathBuf>,
+    /// Snapshots of recipes backed up before they were encrypted
+    pub snapshots: Vec<PathBuf>,
+}
+
+impl PlainCopies {
+    /// Check if no plain copies are left
+    pub fn is_empty(&self) -> bool {
+        self.trashed.is_empty() && self.quarantine.is_none() && self.snapshots.is_empty()
     }
 }
 
+impl fmt::Display for PlainCopies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut places = Vec::new();
+        if !self.trashed.is_empty() {
+            places.push(format!(
+                "{} deleted recipes that are also stored",
+                self.trashed.len()
+            ));
+        }
+        if let Some(quarantine) = &self.quarantine {
+            places.push(format!("the quarantine folder {}", quarantine.display()));
+        }
+        if !self.snapshots.is_empty() {
+            places.push(format!(
+                "{} backups made before recipes were encrypted",
+                self.snapshots.len()
+            ));
+        }
+        write!(
+            f,
+            "Unencrypted copies of recipes are still kept in {}",
+            places.join(", ")
+        )
+    }
+}
+
+/// An error that prevented a saved recipe from being loaded
+#[derive(Debug, Error)]
+#[error("Failed to load recipe {id}: {source}")]
+pub struct LoadError {
+    /// The ID of the recipe that failed to load
+    pub id: RecipeId,
+    /// Why the recipe could not be loaded
+    #[source]
+    pub source: MigrationError,
+}
+
 /// A unique identifier for a recipe in a database
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct RecipeId(Uuid);
@@ -345,6 +1993,14 @@ impl Default for RecipeId {
     }
 }
 
+impl FromStr for RecipeId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
 impl fmt::Display for RecipeId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -0,0 +1,465 @@
+//! Queries that filter, sort, and page through the recipes in a [Database](super::Database)
+//! using the [index](super::index) without loading every recipe
+
+use std::{cmp::Ordering, fmt, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use druid::Data;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::{index::IndexEntry, RecipeId};
+use crate::recipes::recipe::Recipe;
+
+/// Number of recipes in a page of results if no limit is given
+const DEFAULT_LIMIT: usize = 10;
+
+/// What the results of a query are sorted by. Recipes can't be sorted by rating because they
+/// don't have one, [Recipe] has no rating field and nothing in the app asks for one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Data, Serialize, Deserialize)]
+pub enum SortKey {
+    /// Alphabetically by recipe name, ignoring case
+    Name,
+    /// By when the recipe was created, oldest first
+    #[default]
+    Created,
+    /// By how long the recipe takes to make, quickest first with untimed recipes last
+    Time,
+}
+
+impl SortKey {
+    /// Every sort key in the order they are cycled through
+    pub const ALL: [Self; 3] = [Self::Name, Self::Created, Self::Time];
+
+    /// Get the sort key after this one in [ALL](Self::ALL), wrapping around to the first
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|key| *key == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Name => "Name",
+                Self::Created => "Date",
+                Self::Time => "Time",
+            }
+        )
+    }
+}
+
+/// The value of a recipe's sort key
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Name(String),
+    Created(DateTime<Utc>),
+    /// `None` sorts after every time
+    Time(Option<Duration>),
+}
+
+impl SortValue {
+    /// Get the value of a sort key for an indexed recipe
+    fn of(key: SortKey, entry: &IndexEntry) -> Self {
+        match key {
+            SortKey::Name => Self::Name(entry.name.to_lowercase()),
+            SortKey::Created => Self::Created(entry.created_on),
+            SortKey::Time => Self::Time(entry.time),
+        }
+    }
+
+    /// Compare two values, in reverse if `descending` is true. Untimed recipes are sorted last
+    /// in either order
+    fn compare(&self, other: &Self, descending: bool) -> Ordering {
+        match (self, other) {
+            (Self::Time(Some(_)), Self::Time(None)) => Ordering::Less,
+            (Self::Time(None), Self::Time(Some(_))) => Ordering::Greater,
+            _ if descending => other.cmp(self),
+            _ => self.cmp(other),
+        }
+    }
+}
+
+/// A position in the results of a query to continue the next page from, see [Query::after]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+    /// Relevance of the last result if the query was ranked
+    score: Option<isize>,
+    /// Value of the sort key of the last result
+    value: SortValue,
+    /// ID of the last result, keeping results with the same sort value in a stable order
+    id: RecipeId,
+}
+
+/// Checks if an indexed recipe should be in the results of a query
+type Filter = Arc<dyn Fn(&IndexEntry) -> bool + Send + Sync>;
+
+/// Scores how relevant a recipe is to a query, given its index entry and a function to load
+/// the full recipe. Recipes scored `None` are left out of the results
+pub type Ranker =
+    Arc<dyn Fn(&IndexEntry, &dyn Fn() -> Option<Arc<Recipe>>) -> Option<isize> + Send + Sync>;
+
+/// Scores of every recipe ranked by the last ranked query, reused by later pages and queries
+/// with the same rank key until recipes change
+#[derive(Debug)]
+pub(super) struct Ranked {
+    /// The rank key of the query that recipes were scored for
+    pub key: Arc<str>,
+    /// The [revision](super::Database::revision) of the database when recipes were scored
+    pub revision: u64,
+    /// Scores of every recipe that was scored, recipes that are not in the map were left out
+    pub scores: Arc<HashMap<RecipeId, isize>>,
+}
+
+/// A composable query of the recipes in a database, run with
+/// [Database::query](super::Database::query) to get one page of results at a time
+#[derive(Clone)]
+pub struct Query {
+    /// Every filter that a recipe must pass
+    filters: Vec<Filter>,
+    /// Relevance that results are sorted by before the sort key, and the key that recipes are
+    /// ranked for
+    rank: Option<(Arc<str>, Ranker)>,
+    /// The key that results are sorted by
+    sort: SortKey,
+    /// If results are sorted in reverse
+    descending: bool,
+    /// Only return results after this position
+    after: Option<Cursor>,
+    /// Most results to return in one page
+    limit: usize,
+}
+
+impl Query {
+    /// Create a query for every recipe sorted by when it was created
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            rank: None,
+            sort: SortKey::default(),
+            descending: false,
+            after: None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// Builder method to only return recipes that pass a filter
+    pub fn filter(mut self, filter: impl Fn(&IndexEntry) -> bool + Send + Sync + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Builder method to score recipes by relevance, returning the most relevant recipes first
+    /// and leaving out recipes that are not scored. Scores are kept until recipes change and
+    /// reused by every query with the same `key`, like the search term, so rankers given the
+    /// same key must score recipes the same way
+    pub fn rank(mut self, key: impl Into<Arc<str>>, rank: Ranker) -> Self {
+        self.rank = Some((key.into(), rank));
+        self
+    }
+
+    /// Builder method to set the key that results are sorted by
+    pub fn sort_by(mut self, sort: SortKey) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Builder method to sort results in reverse
+    pub fn descending(mut self, descending: bool) -> Self {
+        self.descending = descending;
+        self
+    }
+
+    /// Builder method to continue from the end of a page returned by the same query
+    pub fn after(mut self, cursor: Option<Cursor>) -> Self {
+        self.after = cursor;
+        self
+    }
+
+    /// Builder method to set the most results returned in one page
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Get the rank key and ranker of this query if it is ranked
+    pub(super) fn ranker(&self) -> Option<(&Arc<str>, &Ranker)> {
+        self.rank.as_ref().map(|(key, rank)| (key, rank))
+    }
+
+    /// Compare two positions in the results of this query
+    fn compare(&self, a: &Cursor, b: &Cursor) -> Ordering {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.value.compare(&b.value, self.descending))
+            .then_with(|| a.id.cmp(&b.id))
+    }
+
+    /// Get the position of an indexed recipe in the results of this query, or `None` if it is
+    /// not in the results
+    fn position(&self, entry: &IndexEntry, score: Option<isize>) -> Option<Cursor> {
+        if !self.filters.iter().all(|filter| filter(entry)) {
+            return None;
+        }
+        let score = match self.rank {
+            Some(_) => Some(score?),
+            None => None,
+        };
+        Some(Cursor {
+            score,
+            value: SortValue::of(self.sort, entry),
+            id: entry.id,
+        })
+    }
+
+    /// Run this query over indexed recipes with the scores given by its ranker, keeping only one
+    /// page of results at a time
+    pub(super) fn run<'a>(
+        &self,
+        entries: impl Iterator<Item = &'a IndexEntry>,
+        score: impl Fn(RecipeId) -> Option<isize>,
+    ) -> Page {
+        let mut page = Vec::<Cursor>::with_capacity(self.limit + 1);
+        let mut more = false;
+
+        for entry in entries {
+            let position = match self.position(entry, score(entry.id)) {
+                Some(position) => position,
+                None => continue,
+            };
+            if let Some(ref after) = self.after {
+                if self.compare(&position, after) != Ordering::Greater {
+                    continue;
+                }
+            }
+
+            let at = page
+                .binary_search_by(|other| self.compare(other, &position))
+                .unwrap_or_else(|at| at);
+            if at < self.limit {
+                page.insert(at, position);
+            }
+            if page.len() > self.limit || at >= self.limit {
+                page.truncate(self.limit);
+                more = true;
+            }
+        }
+
+        let next = match more {
+            true => page.last().cloned(),
+            false => None,
+        };
+        Page {
+            ids: page.into_iter().map(|position| position.id).collect(),
+            next,
+        }
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Query")
+            .field("filters", &self.filters.len())
+            .field("ranked", &self.rank.is_some())
+            .field("sort", &self.sort)
+            .field("descending", &self.descending)
+            .field("after", &self.after)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// One page of the results of a query
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    /// IDs of the recipes in this page, in order
+    pub ids: Vec<RecipeId>,
+    /// Where the next page starts, `None` if this is the last page
+    pub next: Option<Cursor>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use hashbrown::HashSet;
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// Index entries with many recipes sharing a name, creation time, and time to make
+    fn entries(count: u128) -> Vec<IndexEntry> {
+        (0..count)
+            .map(|i| IndexEntry {
+                id: RecipeId(Uuid::from_u128(i * 7919 % 1000)),
+                name: Arc::from(format!("Recipe {}", i % 4)),
+                created_on: Utc.timestamp(1_600_000_000 + (i % 5) as i64 * 60, 0),
+                time: match i % 3 {
+                    0 => None,
+                    n => Some(Duration::from_secs(n as u64 * 600)),
+                },
+                hash: None,
+            })
+            .collect()
+    }
+
+    /// Run a query one page at a time until there are no more pages, checking that every page
+    /// is full except the last
+    fn page_through(query: &Query, entries: &[IndexEntry]) -> Vec<RecipeId> {
+        let mut ids = Vec::new();
+        let mut after = None;
+        loop {
+            let page = query.clone().after(after).run(entries.iter(), |_| None);
+            assert!(page.ids.len() <= query.limit);
+            ids.extend(page.ids.iter().copied());
+            match page.next {
+                Some(next) => {
+                    assert_eq!(page.ids.len(), query.limit);
+                    assert!(ids.len() <= entries.len(), "paging never ends");
+                    after = Some(next);
+                }
+                None => return ids,
+            }
+        }
+    }
+
+    /// Get the index entries of the given recipes in order
+    fn lookup<'a>(entries: &'a [IndexEntry], ids: &[RecipeId]) -> Vec<&'a IndexEntry> {
+        ids.iter()
+            .map(|id| entries.iter().find(|entry| entry.id == *id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pages_have_no_gaps_or_duplicates() {
+        for count in [0, 1, 23, 24] {
+            let entries = entries(count);
+            for sort in SortKey::ALL {
+                for descending in [false, true] {
+                    let query = Query::new().sort_by(sort).descending(descending).limit(4);
+                    let paged = page_through(&query, &entries);
+                    let all = query
+                        .clone()
+                        .limit(entries.len().max(1))
+                        .run(entries.iter(), |_| None);
+
+                    assert_eq!(paged, all.ids, "{:?} descending {}", sort, descending);
+                    assert_eq!(all.next, None);
+                    let unique = paged.iter().collect::<HashSet<_>>();
+                    assert_eq!(unique.len(), entries.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sorts_in_either_order() {
+        let entries = entries(20);
+        let ascending = page_through(&Query::new().limit(3), &entries);
+        let descending = page_through(&Query::new().descending(true).limit(3), &entries);
+
+        let created = |ids| {
+            lookup(&entries, ids)
+                .iter()
+                .map(|entry| entry.created_on)
+                .collect::<Vec<_>>()
+        };
+        assert!(created(&ascending).windows(2).all(|w| w[0] <= w[1]));
+        assert!(created(&descending).windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn ties_are_ordered_by_id() {
+        let entries = entries(20);
+        for descending in [false, true] {
+            let query = Query::new()
+                .sort_by(SortKey::Name)
+                .descending(descending)
+                .limit(3);
+            let sorted = lookup(&entries, &page_through(&query, &entries));
+            for pair in sorted.windows(2) {
+                if pair[0].name == pair[1].name {
+                    assert!(pair[0].id < pair[1].id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn untimed_recipes_are_last() {
+        let entries = entries(20);
+        for descending in [false, true] {
+            let query = Query::new()
+                .sort_by(SortKey::Time)
+                .descending(descending)
+                .limit(3);
+            let times = lookup(&entries, &page_through(&query, &entries))
+                .iter()
+                .map(|entry| entry.time)
+                .collect::<Vec<_>>();
+
+            let untimed = times.iter().position(Option::is_none).unwrap();
+            assert!(times[..untimed].iter().all(Option::is_some));
+            assert!(times[untimed..].iter().all(Option::is_none));
+            let timed = times[..untimed].windows(2);
+            match descending {
+                false => assert!(timed.clone().all(|w| w[0] <= w[1])),
+                true => assert!(timed.clone().all(|w| w[0] >= w[1])),
+            }
+        }
+    }
+
+    #[test]
+    fn ranked_pages_skip_unscored_recipes() {
+        let entries = entries(20);
+        let scores = entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 4 != 0)
+            .map(|(i, entry)| (entry.id, (i % 3) as isize))
+            .collect::<HashMap<_, _>>();
+        let ranker: Ranker = Arc::new(|_, _| None);
+        let query = Query::new().rank("key", ranker).limit(4);
+
+        let mut ids = Vec::new();
+        let mut after = None;
+        loop {
+            let page = query
+                .clone()
+                .after(after)
+                .run(entries.iter(), |id| scores.get(&id).copied());
+            ids.extend(page.ids);
+            match page.next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(ids.len(), scores.len());
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), scores.len());
+        assert!(ids.windows(2).all(|w| scores[&w[0]] >= scores[&w[1]]));
+    }
+
+    #[test]
+    fn filters_leave_out_recipes() {
+        let entries = entries(20);
+        let query = Query::new().filter(|entry| entry.time.is_some()).limit(4);
+        let ids = page_through(&query, &entries);
+
+        assert_eq!(
+            ids.len(),
+            entries.iter().filter(|entry| entry.time.is_some()).count()
+        );
+        assert!(lookup(&entries, &ids)
+            .iter()
+            .all(|entry| entry.time.is_some()));
+    }
+}
@@ -0,0 +1,124 @@
+//! Statistics about every recipe in a [Database](super::Database), like how many recipes were
+//! added each month and which ingredients are used the most
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use chrono::{Datelike, Local};
+use hashbrown::HashMap;
+
+use super::{index::IndexEntry, RecipeId};
+use crate::recipes::recipe::Recipe;
+
+/// Most ingredients listed in [Stats::ingredients]
+pub const MOST_USED: usize = 10;
+
+/// Statistics collected by the last [collection](super::Database::stats), reused until recipes
+/// change
+#[derive(Debug)]
+pub(super) struct Collected {
+    /// The [revision](super::Database::revision) of the database when statistics were collected
+    pub revision: u64,
+    /// The collected statistics
+    pub stats: Arc<Stats>,
+}
+
+/// A month of a year, ordered by date
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Month {
+    /// The year, like 2022
+    pub year: i32,
+    /// The month of the year from 1 to 12
+    pub month: u32,
+}
+
+/// Totals collected from every recipe in a database
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of recipes in the database
+    pub total: usize,
+    /// Number of recipes created in each month, in order, with months that have no recipes left
+    /// out
+    pub per_month: Vec<(Month, usize)>,
+    /// The [MOST_USED] ingredient names and the number of recipes using them, most used first.
+    /// Names are compared ignoring case and surrounding whitespace
+    pub ingredients: Vec<(Arc<str>, usize)>,
+    /// Average time taken to make the recipes that have a time, `None` if no recipes do
+    pub average_time: Option<Duration>,
+    /// Number of recipes making each number of servings, rounded to the nearest whole serving
+    pub servings: Vec<(u32, usize)>,
+    /// Number of recipes that don't say how many servings they make
+    pub no_servings: usize,
+    /// Number of recipes that could not be loaded and are only counted in [total](Self::total)
+    pub unreadable: usize,
+}
+
+impl Stats {
+    /// Collect statistics for indexed recipes, loading each recipe with `load` to count its
+    /// ingredients and servings
+    pub(super) fn collect<'a>(
+        entries: impl Iterator<Item = &'a IndexEntry>,
+        load: impl Fn(RecipeId) -> Option<Arc<Recipe>>,
+    ) -> Self {
+        let mut stats = Self::default();
+        let mut months = BTreeMap::<Month, usize>::new();
+        let mut ingredients = HashMap::<String, (Arc<str>, usize)>::new();
+        let mut servings = BTreeMap::<u32, usize>::new();
+        let (mut total_time, mut timed) = (Duration::ZERO, 0u32);
+
+        for entry in entries {
+            stats.total += 1;
+            let created = entry.created_on.with_timezone(&Local);
+            *months
+                .entry(Month {
+                    year: created.year(),
+                    month: created.month(),
+                })
+                .or_default() += 1;
+            if let Some(time) = entry.time {
+                total_time += time;
+                timed += 1;
+            }
+
+            let recipe = match load(entry.id) {
+                Some(recipe) => recipe,
+                None => {
+                    stats.unreadable += 1;
+                    continue;
+                }
+            };
+            match recipe.servings {
+                Some(count) => *servings.entry(count.round().max(0.) as u32).or_default() += 1,
+                None => stats.no_servings += 1,
+            }
+
+            //Count each ingredient once per recipe, even if it is listed more than once
+            let mut used = recipe
+                .ingredients
+                .iter()
+                .map(|ingredient| (ingredient.name.trim().to_lowercase(), &ingredient.name))
+                .filter(|(key, _)| !key.is_empty())
+                .collect::<Vec<_>>();
+            used.sort_by(|a, b| a.0.cmp(&b.0));
+            used.dedup_by(|a, b| a.0 == b.0);
+            for (key, name) in used {
+                ingredients
+                    .entry(key)
+                    .or_insert_with(|| (Arc::from(name.trim()), 0))
+                    .1 += 1;
+            }
+        }
+
+        stats.per_month = months.into_iter().collect();
+        stats.servings = servings.into_iter().collect();
+        stats.average_time = (timed > 0).then(|| total_time / timed);
+
+        let mut ingredients = ingredients
+            .into_iter()
+            .map(|(_, used)| used)
+            .collect::<Vec<_>>();
+        ingredients.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ingredients.truncate(MOST_USED);
+        stats.ingredients = ingredients;
+        stats
+    }
+}
@@ -0,0 +1,586 @@
+//! Storage keeping one folder per recipe, each holding the recipe's save file and image
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::de::IgnoredAny;
+use uuid::Uuid;
+
+use super::{RecipeId, Storage, StorageError, TrashEntry};
+use crate::recipes::db::{
+    atomic,
+    check::{self, Finding, Issue, Repair},
+    crypt,
+};
+
+/// Name of the recipe data save file
+const SAVE_FILE: &str = "save.json";
+
+/// Name of the recipe image file in a saved recipe folder
+const IMG_FILE: &str = "img.jpg";
+
+/// Name of the recipe image file in folders saved before images were saved as JPEG. It is read if
+/// there is no [IMG_FILE] and removed once a new image is saved
+const LEGACY_IMG_FILE: &str = "img.png";
+
+/// Name of the file in a saved recipe folder holding a thumbnail of the recipe's image
+const THUMBNAIL_FILE: &str = "thumbnail.jpg";
+
+/// Name of the folder in the recipe directory that broken entries are moved to
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// Name of the folder in the recipe directory that deleted recipes are moved to
+const TRASH_DIR: &str = "trash";
+
+/// Name of the file in a deleted recipe's folder holding when it was deleted
+const DELETED_FILE: &str = "deleted.json";
+
+/// Prefix of temporary files left behind by interrupted saves
+const TEMP_PREFIX: &str = ".tmp";
+
+/// Name of the file in the recipe directory holding the index of every stored recipe
+const INDEX_FILE: &str = "index.json";
+
+/// Storage backend that keeps each recipe in a folder named after its ID
+#[derive(Debug)]
+pub struct DirStorage {
+    /// The directory that all recipe folders are stored in
+    dir: PathBuf,
+}
+
+impl DirStorage {
+    /// Create a new directory storage in the given directory, which is created when the first
+    /// recipe is saved
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    /// Get the folder that a recipe is stored in
+    fn recipe_dir(&self, id: RecipeId) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+
+    /// Get the folder that a deleted recipe is kept in
+    fn trash_dir(&self, id: RecipeId) -> PathBuf {
+        self.dir.join(TRASH_DIR).join(id.to_string())
+    }
+
+    /// Check if an entry in the recipe directory is one of the folders or files used by the
+    /// storage instead of a recipe
+    fn is_reserved(name: &std::ffi::OsStr) -> bool {
+        name == QUARANTINE_DIR
+            || name == TRASH_DIR
+            || name == INDEX_FILE
+            || name == atomic::backup_path(Path::new(INDEX_FILE))
+    }
+
+    /// Get the ID and folder of every recipe folder in the recipe directory
+    fn recipe_dirs(&self) -> Result<Vec<(RecipeId, PathBuf)>, StorageError> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut recipes = Vec::new();
+        for item in dir {
+            let item = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to get directory entry in {}: {}",
+                        self.dir.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            if Self::is_reserved(&item.file_name()) {
+                continue;
+            }
+
+            match item.file_name().to_str().map(Uuid::parse_str) {
+                Some(Ok(id)) => recipes.push((RecipeId(id), item.path())),
+                Some(Err(e)) => {
+                    log::warn!(
+                        "Failed to parse directory item {} as UUID, not adding as entry: {}",
+                        item.path().display(),
+                        e
+                    );
+                }
+                None => (),
+            }
+        }
+
+        Ok(recipes)
+    }
+
+    /// Read a deleted recipe from its folder in the trash
+    fn read_trashed(path: &Path) -> io::Result<TrashEntry> {
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<RecipeId>().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "folder name is not an ID")
+            })?;
+        let deleted_on = serde_json::from_slice(&fs::read(path.join(DELETED_FILE))?)?;
+        let data = Self::read_json(&path.join(SAVE_FILE))?;
+
+        Ok(TrashEntry {
+            id,
+            deleted_on,
+            data,
+        })
+    }
+
+    /// Read a file's bytes, recovering from the last good copy if it is not valid JSON or
+    /// encrypted data
+    fn read_json(path: &Path) -> io::Result<Vec<u8>> {
+        atomic::read(path, |mut file| -> io::Result<Vec<u8>> {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            if !crypt::is_encrypted(&data) {
+                serde_json::from_slice::<IgnoredAny>(&data)?;
+            }
+            Ok(data)
+        })
+    }
+
+    /// Check if a file's bytes are valid JSON or encrypted data, and can be kept as its last good
+    /// copy
+    fn valid_json(data: &[u8]) -> bool {
+        crypt::is_encrypted(data) || serde_json::from_slice::<IgnoredAny>(data).is_ok()
+    }
+
+    /// Check if a file's bytes are an image in a known format or encrypted data, and can be kept
+    /// as its last good copy
+    fn valid_image(data: &[u8]) -> bool {
+        crypt::is_encrypted(data) || druid::image::guess_format(data).is_ok()
+    }
+
+    /// Read a file's bytes, returning `None` if the file does not exist
+    fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check if a file in a recipe folder is a save file, image, or a backup of one of them
+    fn is_recipe_file(name: &str) -> bool {
+        let name = name.strip_suffix(".bak").unwrap_or(name);
+        name == SAVE_FILE
+            || name == IMG_FILE
+            || name == LEGACY_IMG_FILE
+            || name == THUMBNAIL_FILE
+            || Self::is_version_backup(name)
+    }
+
+    /// Check if a file in a recipe folder is a copy of the recipe saved by an older format
+    /// version
+    fn is_version_backup(name: &str) -> bool {
+        name.strip_prefix("save.v")
+            .and_then(|version| version.strip_suffix(".json"))
+            .is_some_and(|version| version.parse::<u64>().is_ok())
+    }
+
+    /// Securely remove the last good copies and older format backups in a recipe folder
+    fn remove_backups_in(dir: &Path) -> io::Result<()> {
+        for item in fs::read_dir(dir)?.flatten() {
+            let path = item.path();
+            let name = item.file_name().to_string_lossy().into_owned();
+            match name.strip_suffix(".bak") {
+                Some(file) => atomic::remove_backup(&dir.join(file))?,
+                None if Self::is_version_backup(&name) => atomic::shred(&path)?,
+                None => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Move a file or folder from the recipe directory into the quarantine folder, keeping its
+    /// path relative to the recipe directory
+    fn quarantine(&self, path: &Path) -> io::Result<PathBuf> {
+        let relative = path.strip_prefix(&self.dir).unwrap_or(path);
+        let mut dest = self.dir.join(QUARANTINE_DIR).join(relative);
+        if dest.exists() {
+            let mut name = dest.file_name().unwrap_or_default().to_owned();
+            name.push(format!("-{}", chrono::Local::now().format("%Y%m%d%H%M%S")));
+            dest.set_file_name(name);
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        log::warn!("Quarantining {} to {}", path.display(), dest.display());
+        fs::rename(path, &dest)?;
+        Ok(dest)
+    }
+
+    /// Run a repair if repairing was requested
+    fn repair(repair: bool, run: impl FnOnce() -> Result<Repair, StorageError>) -> Repair {
+        match repair {
+            true => run().unwrap_or_else(|e| Repair::Failed(e.to_string())),
+            false => Repair::None,
+        }
+    }
+
+    /// Check the files in one recipe folder, returning every problem found
+    fn check_folder(&self, id: RecipeId, path: &Path, repair: bool) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        if let Ok(files) = fs::read_dir(path) {
+            for file in files.flatten() {
+                let name = file.file_name();
+                let name = name.to_string_lossy();
+                if Self::is_recipe_file(&name) {
+                    continue;
+                }
+
+                let file = file.path();
+                let repair = Self::repair(repair, || {
+                    match name.starts_with(TEMP_PREFIX) {
+                        true => fs::remove_file(&file).map(|()| Repair::Removed),
+                        false => self.quarantine(&file).map(Repair::Quarantined),
+                    }
+                    .map_err(Into::into)
+                });
+                findings.push(Finding {
+                    issue: Issue::StrayFile(file),
+                    repair,
+                });
+            }
+        }
+
+        let issue = match Self::read_json(&path.join(SAVE_FILE)) {
+            Ok(data) => match check::check_recipe(id, &data) {
+                Some(issue @ Issue::IdMismatch { .. }) => {
+                    let repair = Self::repair(repair, || {
+                        let fixed = check::fix_id(id, &data)?;
+                        self.write(id, SAVE_FILE, Self::valid_json, &fixed)?;
+                        Ok(Repair::Fixed)
+                    });
+                    findings.push(Finding { issue, repair });
+                    return findings;
+                }
+                //Recipes saved by a newer version of Recipier are fine and are left alone
+                Some(issue @ Issue::TooNew { .. }) => {
+                    findings.push(Finding {
+                        issue,
+                        repair: Repair::None,
+                    });
+                    return findings;
+                }
+                Some(issue) => issue,
+                None => return findings,
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                match path.join(IMG_FILE).exists() || path.join(LEGACY_IMG_FILE).exists() {
+                    true => Issue::OrphanImage(id),
+                    false => Issue::NotRecipe(path.to_owned()),
+                }
+            }
+            Err(e) => Issue::Unreadable {
+                id,
+                error: e.to_string(),
+            },
+        };
+
+        let repair = Self::repair(repair, || Ok(Repair::Quarantined(self.quarantine(path)?)));
+        findings.push(Finding { issue, repair });
+        findings
+    }
+
+    /// Atomically write a file in a recipe's folder, creating the folder if needed. The replaced
+    /// file is kept as the last good copy if `valid` accepts it
+    fn write(
+        &self,
+        id: RecipeId,
+        file: &str,
+        valid: fn(&[u8]) -> bool,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let dir = self.recipe_dir(id);
+        fs::create_dir_all(&dir)?;
+        atomic::write(&dir.join(file), valid, |writer| {
+            io::Write::write_all(writer, data)
+        })
+    }
+}
+
+impl Storage for DirStorage {
+    fn load_all(&self) -> Result<Vec<(RecipeId, Vec<u8>)>, StorageError> {
+        let mut recipes = Vec::new();
+        for (id, path) in self.recipe_dirs()? {
+            log::trace!("Adding recipe file {} to db...", id);
+            match Self::read_json(&path.join(SAVE_FILE)) {
+                Ok(data) => recipes.push((id, data)),
+                Err(e) => log::error!("Failed to load recipe from folder {}: {}", id, e),
+            }
+        }
+
+        Ok(recipes)
+    }
+
+    fn ids(&self) -> Result<Vec<RecipeId>, StorageError> {
+        Ok(self
+            .recipe_dirs()?
+            .into_iter()
+            .filter(|(_, path)| {
+                let save = path.join(SAVE_FILE);
+                save.exists() || atomic::backup_path(&save).exists()
+            })
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    fn get(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        match Self::read_json(&self.recipe_dir(id).join(SAVE_FILE)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        Ok(self.write(id, SAVE_FILE, Self::valid_json, data)?)
+    }
+
+    fn delete(&self, id: RecipeId) -> Result<(), StorageError> {
+        match fs::remove_dir_all(self.recipe_dir(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn trash(&self, id: RecipeId, deleted_on: DateTime<Utc>) -> Result<(), StorageError> {
+        let dir = self.recipe_dir(id);
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let trashed = self.trash_dir(id);
+        if trashed.exists() {
+            fs::remove_dir_all(&trashed)?;
+        }
+        fs::create_dir_all(self.dir.join(TRASH_DIR))?;
+        self.write(
+            id,
+            DELETED_FILE,
+            Self::valid_json,
+            &serde_json::to_vec(&deleted_on)?,
+        )?;
+        fs::rename(dir, trashed)?;
+        Ok(())
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, StorageError> {
+        let dir = match fs::read_dir(self.dir.join(TRASH_DIR)) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for item in dir.flatten() {
+            match Self::read_trashed(&item.path()) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!(
+                    "Failed to read deleted recipe {}: {}",
+                    item.path().display(),
+                    e
+                ),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn restore(&self, id: RecipeId) -> Result<(), StorageError> {
+        let trashed = self.trash_dir(id);
+        if !trashed.exists() {
+            return Err(StorageError::NotInTrash(id));
+        }
+
+        let dir = self.recipe_dir(id);
+        if dir.exists() {
+            return Err(StorageError::AlreadyStored(id));
+        }
+        fs::rename(&trashed, &dir)?;
+        if let Err(e) = fs::remove_file(dir.join(DELETED_FILE)) {
+            log::warn!(
+                "Failed to remove deletion time of restored recipe {}: {}",
+                id,
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn purge(&self, id: RecipeId) -> Result<(), StorageError> {
+        match fs::remove_dir_all(self.trash_dir(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        let dir = self.recipe_dir(id);
+        match Self::read_optional(&dir.join(IMG_FILE))? {
+            Some(data) => Ok(Some(data)),
+            None => Ok(Self::read_optional(&dir.join(LEGACY_IMG_FILE))?),
+        }
+    }
+
+    fn get_trashed_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        let dir = self.trash_dir(id);
+        match Self::read_optional(&dir.join(IMG_FILE))? {
+            Some(data) => Ok(Some(data)),
+            None => Ok(Self::read_optional(&dir.join(LEGACY_IMG_FILE))?),
+        }
+    }
+
+    fn put_image(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.write(id, IMG_FILE, Self::valid_image, data)?;
+        let dir = self.recipe_dir(id);
+        for old in [THUMBNAIL_FILE, LEGACY_IMG_FILE] {
+            match fs::remove_file(dir.join(old)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn delete_image(&self, id: RecipeId) -> Result<(), StorageError> {
+        let dir = self.recipe_dir(id);
+        for old in [IMG_FILE, THUMBNAIL_FILE, LEGACY_IMG_FILE] {
+            match fs::remove_file(dir.join(old)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn get_thumbnail(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        //Another program may have changed the image after the thumbnail was made
+        let dir = self.recipe_dir(id);
+        let modified = |file: &str| fs::metadata(dir.join(file)).and_then(|meta| meta.modified());
+        match (modified(THUMBNAIL_FILE), modified(IMG_FILE)) {
+            (Ok(thumbnail), Ok(image)) if thumbnail >= image => {
+                Ok(Self::read_optional(&dir.join(THUMBNAIL_FILE))?)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn put_thumbnail(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        match self.recipe_dir(id).join(IMG_FILE).exists() {
+            true => Ok(self.write(id, THUMBNAIL_FILE, Self::valid_image, data)?),
+            false => Ok(()),
+        }
+    }
+
+    fn put_backup(&self, id: RecipeId, version: u64, data: &[u8]) -> Result<(), StorageError> {
+        Ok(self.write(
+            id,
+            &format!("save.v{}.json", version),
+            Self::valid_json,
+            data,
+        )?)
+    }
+
+    fn remove_backups(&self) -> Result<(), StorageError> {
+        let mut dirs = self
+            .recipe_dirs()?
+            .into_iter()
+            .map(|(_, dir)| dir)
+            .collect::<Vec<_>>();
+        match fs::read_dir(self.dir.join(TRASH_DIR)) {
+            Ok(trash) => dirs.extend(trash.flatten().map(|item| item.path())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+
+        for dir in dirs {
+            Self::remove_backups_in(&dir)?;
+        }
+        Ok(atomic::remove_backup(&self.dir.join(INDEX_FILE))?)
+    }
+
+    fn get_index(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        match Self::read_json(&self.dir.join(INDEX_FILE)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put_index(&self, data: &[u8]) -> Result<(), StorageError> {
+        fs::create_dir_all(&self.dir)?;
+        Ok(atomic::write(
+            &self.dir.join(INDEX_FILE),
+            Self::valid_json,
+            |writer| io::Write::write_all(writer, data),
+        )?)
+    }
+
+    fn watch_path(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+
+    fn quarantine_path(&self) -> Option<PathBuf> {
+        let dir = self.dir.join(QUARANTINE_DIR);
+        let mut entries = fs::read_dir(&dir).ok()?;
+        entries.next().is_some().then_some(dir)
+    }
+
+    fn check(&self, repair: bool) -> Vec<Finding> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    log::error!(
+                        "Failed to read recipe directory {}: {}",
+                        self.dir.display(),
+                        e
+                    );
+                }
+                return Vec::new();
+            }
+        };
+
+        let mut findings = Vec::new();
+        for item in dir.flatten() {
+            let path = item.path();
+            if Self::is_reserved(&item.file_name()) {
+                continue;
+            }
+
+            match item.file_name().to_str().map(str::parse::<RecipeId>) {
+                Some(Ok(id)) if path.is_dir() => {
+                    findings.extend(self.check_folder(id, &path, repair))
+                }
+                _ => {
+                    let repair =
+                        Self::repair(repair, || Ok(Repair::Quarantined(self.quarantine(&path)?)));
+                    findings.push(Finding {
+                        issue: Issue::NotRecipe(path),
+                        repair,
+                    });
+                }
+            }
+        }
+
+        findings
+    }
+}
@@ -0,0 +1,149 @@
+//! Storage that keeps recipes in memory only, used for tests and throwaway cookbooks
+
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+
+use super::{RecipeId, Storage, StorageError, TrashEntry};
+
+/// Storage backend that keeps all data in memory and loses it when dropped
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    /// Saved recipe data by ID
+    recipes: RwLock<HashMap<RecipeId, Vec<u8>>>,
+    /// Encoded images by recipe ID
+    images: RwLock<HashMap<RecipeId, Vec<u8>>>,
+    /// Encoded thumbnails of images by recipe ID
+    thumbnails: RwLock<HashMap<RecipeId, Vec<u8>>>,
+    /// Recipe data saved by older format versions by recipe ID and version
+    backups: RwLock<HashMap<(RecipeId, u64), Vec<u8>>>,
+    /// Deleted recipes by ID
+    trash: RwLock<HashMap<RecipeId, TrashEntry>>,
+    /// Images of deleted recipes by ID
+    trashed_images: RwLock<HashMap<RecipeId, Vec<u8>>>,
+    /// The saved recipe index
+    index: RwLock<Option<Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn load_all(&self) -> Result<Vec<(RecipeId, Vec<u8>)>, StorageError> {
+        Ok(self
+            .recipes
+            .read()
+            .iter()
+            .map(|(id, data)| (*id, data.clone()))
+            .collect())
+    }
+
+    fn ids(&self) -> Result<Vec<RecipeId>, StorageError> {
+        Ok(self.recipes.read().keys().copied().collect())
+    }
+
+    fn get(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.recipes.read().get(&id).cloned())
+    }
+
+    fn put(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.recipes.write().insert(id, data.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, id: RecipeId) -> Result<(), StorageError> {
+        self.recipes.write().remove(&id);
+        self.images.write().remove(&id);
+        self.thumbnails.write().remove(&id);
+        Ok(())
+    }
+
+    fn trash(&self, id: RecipeId, deleted_on: DateTime<Utc>) -> Result<(), StorageError> {
+        self.thumbnails.write().remove(&id);
+        if let Some(image) = self.images.write().remove(&id) {
+            self.trashed_images.write().insert(id, image);
+        }
+        if let Some(data) = self.recipes.write().remove(&id) {
+            let entry = TrashEntry {
+                id,
+                deleted_on,
+                data,
+            };
+            self.trash.write().insert(id, entry);
+        }
+        Ok(())
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, StorageError> {
+        Ok(self.trash.read().values().cloned().collect())
+    }
+
+    fn restore(&self, id: RecipeId) -> Result<(), StorageError> {
+        if self.recipes.read().contains_key(&id) {
+            return Err(StorageError::AlreadyStored(id));
+        }
+        let entry = self
+            .trash
+            .write()
+            .remove(&id)
+            .ok_or(StorageError::NotInTrash(id))?;
+        self.recipes.write().insert(id, entry.data);
+        if let Some(image) = self.trashed_images.write().remove(&id) {
+            self.images.write().insert(id, image);
+        }
+        Ok(())
+    }
+
+    fn purge(&self, id: RecipeId) -> Result<(), StorageError> {
+        self.trash.write().remove(&id);
+        self.trashed_images.write().remove(&id);
+        Ok(())
+    }
+
+    fn get_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.images.read().get(&id).cloned())
+    }
+
+    fn get_trashed_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.trashed_images.read().get(&id).cloned())
+    }
+
+    fn put_image(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.images.write().insert(id, data.to_vec());
+        self.thumbnails.write().remove(&id);
+        Ok(())
+    }
+
+    fn delete_image(&self, id: RecipeId) -> Result<(), StorageError> {
+        self.images.write().remove(&id);
+        self.thumbnails.write().remove(&id);
+        Ok(())
+    }
+
+    fn get_thumbnail(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.thumbnails.read().get(&id).cloned())
+    }
+
+    fn put_thumbnail(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        if self.images.read().contains_key(&id) {
+            self.thumbnails.write().insert(id, data.to_vec());
+        }
+        Ok(())
+    }
+
+    fn put_backup(&self, id: RecipeId, version: u64, data: &[u8]) -> Result<(), StorageError> {
+        self.backups.write().insert((id, version), data.to_vec());
+        Ok(())
+    }
+
+    fn remove_backups(&self) -> Result<(), StorageError> {
+        self.backups.write().clear();
+        Ok(())
+    }
+
+    fn get_index(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.index.read().clone())
+    }
+
+    fn put_index(&self, data: &[u8]) -> Result<(), StorageError> {
+        *self.index.write() = Some(data.to_vec());
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! Storage backends that recipe data and images are persisted to
+
+pub mod dir;
+pub mod memory;
+pub mod sqlite;
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    check::{self, Finding},
+    crypt::CryptError,
+    LoadError, RecipeId,
+};
+use crate::paths::Paths;
+
+pub use self::{dir::DirStorage, memory::MemoryStorage, sqlite::SqliteStorage};
+
+/// A place that serialized recipes and their encoded images are stored
+///
+/// Recipes and images are stored as opaque bytes, the [Database](super::Database) handles
+/// serializing and deserializing them
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// Load the saved data of every stored recipe
+    fn load_all(&self) -> Result<Vec<(RecipeId, Vec<u8>)>, StorageError>;
+
+    /// Get the IDs of every stored recipe without reading their data
+    fn ids(&self) -> Result<Vec<RecipeId>, StorageError> {
+        Ok(self.load_all()?.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Get the saved data of one recipe, if it is stored
+    fn get(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store the data of a recipe, replacing any data already stored for it
+    fn put(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Delete a recipe and its image
+    fn delete(&self, id: RecipeId) -> Result<(), StorageError>;
+
+    /// Move a recipe and its image to the trash, recording when it was deleted
+    fn trash(&self, id: RecipeId, deleted_on: DateTime<Utc>) -> Result<(), StorageError>;
+
+    /// Get the saved data of every recipe in the trash
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, StorageError>;
+
+    /// Move a recipe out of the trash, failing without changing anything if a recipe with the same
+    /// ID is stored
+    fn restore(&self, id: RecipeId) -> Result<(), StorageError>;
+
+    /// Permanently delete a recipe in the trash
+    fn purge(&self, id: RecipeId) -> Result<(), StorageError>;
+
+    /// Get the encoded image of a recipe, if it has one
+    fn get_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Get the encoded image of a recipe in the trash, if it has one
+    fn get_trashed_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store the encoded image of a recipe, replacing any existing image and removing its
+    /// thumbnail
+    fn put_image(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Delete the image of a recipe and its thumbnail, if it has one
+    fn delete_image(&self, id: RecipeId) -> Result<(), StorageError>;
+
+    /// Get the encoded thumbnail of a recipe's image, if one was stored since the image changed
+    fn get_thumbnail(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store the encoded thumbnail of a recipe's image, or nothing if the recipe has no stored
+    /// image
+    fn put_thumbnail(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Keep a copy of a recipe's data as saved by an older format version before it is
+    /// overwritten with a migrated copy
+    fn put_backup(&self, id: RecipeId, version: u64, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Securely remove every last good copy and older format backup of stored and deleted
+    /// recipes, so that no copies of data from before it was encrypted are left behind
+    fn remove_backups(&self) -> Result<(), StorageError>;
+
+    /// Get the saved [index](super::index) of every stored recipe, if one was saved
+    fn get_index(&self) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store the [index](super::index) of every stored recipe, replacing any saved index
+    fn put_index(&self, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Get the directory that recipes are stored in, if this backend stores recipes as files
+    /// that other programs may change
+    fn watch_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Get the folder that unreadable recipes were set aside in when repairing, if any are left
+    /// there
+    fn quarantine_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Check all stored recipes for problems, repairing them if `repair` is true
+    fn check(&self, repair: bool) -> Vec<Finding> {
+        check::check_storage(self, repair)
+    }
+}
+
+/// A deleted recipe kept in the trash of a storage backend
+#[derive(Clone, Debug)]
+pub struct TrashEntry {
+    /// The ID of the deleted recipe
+    pub id: RecipeId,
+    /// When the recipe was moved to the trash
+    pub deleted_on: DateTime<Utc>,
+    /// The saved data of the recipe
+    pub data: Vec<u8>,
+}
+
+/// Which storage backend to keep recipes in, chosen in the [Config](crate::gui::data::config::Config)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageConfig {
+    /// One folder per recipe in the given directory, see [DirStorage]. Relative paths are
+    /// relative to the data directory
+    Directory(PathBuf),
+    /// An SQLite database file at the given path, see [SqliteStorage]
+    Sqlite(PathBuf),
+    /// Recipes are only kept in memory and lost on exit, see [MemoryStorage]
+    Memory,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self::Directory(PathBuf::from("recipes"))
+    }
+}
+
+impl StorageConfig {
+    /// Open the storage backend described by this configuration, with relative paths resolved
+    /// against the [data directory](crate::paths::Paths)
+    pub fn open(&self) -> Result<Box<dyn Storage>, StorageError> {
+        let paths = Paths::get();
+        Ok(match self {
+            Self::Directory(path) => Box::new(DirStorage::new(paths.data_path(path))),
+            Self::Sqlite(path) => Box::new(SqliteStorage::open(paths.data_path(path))?),
+            Self::Memory => Box::new(MemoryStorage::default()),
+        })
+    }
+}
+
+/// Enumeration defining all errors that can occur when reading or writing stored recipes
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Input/output error: {}", .0)]
+    Io(#[from] io::Error),
+
+    #[error("SQLite error: {}", .0)]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Failed to serialize or deserialize recipe: {}", .0)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to encode or decode image: {}", .0)]
+    Image(#[from] druid::image::ImageError),
+
+    #[error("{}", .0)]
+    Load(#[from] LoadError),
+
+    #[error("Recipe {} is not in the trash", .0)]
+    NotInTrash(RecipeId),
+
+    #[error("Recipe {} is already stored, delete it before restoring the copy in the trash", .0)]
+    AlreadyStored(RecipeId),
+
+    #[error("Recipe {} was also changed somewhere else, choose which copy to keep", .0)]
+    Conflict(RecipeId),
+
+    #[error("{}", .0)]
+    Crypt(#[from] CryptError),
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Run every check of the [Storage] contract against a backend created by `$open`
+    macro_rules! storage_tests {
+        ($name:ident, $open:expr) => {
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn recipes() {
+                    super::recipes(&$open);
+                }
+
+                #[test]
+                fn trash() {
+                    super::trash(&$open);
+                }
+
+                #[test]
+                fn images() {
+                    super::images(&$open);
+                }
+
+                #[test]
+                fn index() {
+                    super::index(&$open);
+                }
+            }
+        };
+    }
+
+    storage_tests!(memory, MemoryStorage::default());
+    storage_tests!(sqlite, SqliteStorage::open(":memory:").unwrap());
+
+    fn recipes(storage: &dyn Storage) {
+        let id = RecipeId::new();
+        assert_eq!(storage.get(id).unwrap(), None);
+
+        storage.put(id, b"first").unwrap();
+        assert_eq!(storage.get(id).unwrap().as_deref(), Some(&b"first"[..]));
+        storage.put(id, b"second").unwrap();
+        assert_eq!(storage.get(id).unwrap().as_deref(), Some(&b"second"[..]));
+        assert_eq!(storage.ids().unwrap(), vec![id]);
+        assert_eq!(storage.load_all().unwrap(), vec![(id, b"second".to_vec())]);
+
+        storage.delete(id).unwrap();
+        assert_eq!(storage.get(id).unwrap(), None);
+        assert!(storage.ids().unwrap().is_empty());
+    }
+
+    fn trash(storage: &dyn Storage) {
+        let id = RecipeId::new();
+        let deleted_on = Utc.timestamp(1_600_000_000, 0);
+        storage.put(id, b"recipe").unwrap();
+        storage.put_image(id, b"image").unwrap();
+
+        storage.trash(id, deleted_on).unwrap();
+        assert_eq!(storage.get(id).unwrap(), None);
+        assert_eq!(storage.get_image(id).unwrap(), None);
+        let trash = storage.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, id);
+        assert_eq!(trash[0].deleted_on, deleted_on);
+        assert_eq!(trash[0].data, b"recipe");
+        assert_eq!(
+            storage.get_trashed_image(id).unwrap().as_deref(),
+            Some(&b"image"[..])
+        );
+
+        storage.restore(id).unwrap();
+        assert_eq!(storage.get(id).unwrap().as_deref(), Some(&b"recipe"[..]));
+        assert_eq!(
+            storage.get_image(id).unwrap().as_deref(),
+            Some(&b"image"[..])
+        );
+        assert!(storage.list_trash().unwrap().is_empty());
+        let missing = RecipeId::new();
+        assert!(matches!(
+            storage.restore(missing),
+            Err(StorageError::NotInTrash(restored)) if restored == missing
+        ));
+
+        //A stored recipe is never replaced by the copy in the trash
+        storage.trash(id, deleted_on).unwrap();
+        storage.put(id, b"stored").unwrap();
+        assert!(matches!(
+            storage.restore(id),
+            Err(StorageError::AlreadyStored(restored)) if restored == id
+        ));
+        assert_eq!(storage.get(id).unwrap().as_deref(), Some(&b"stored"[..]));
+        assert_eq!(storage.list_trash().unwrap().len(), 1);
+
+        storage.purge(id).unwrap();
+        assert!(storage.list_trash().unwrap().is_empty());
+        assert_eq!(storage.get_trashed_image(id).unwrap(), None);
+    }
+
+    fn images(storage: &dyn Storage) {
+        let id = RecipeId::new();
+        storage.put(id, b"recipe").unwrap();
+
+        //Thumbnails are only kept for stored images
+        storage.put_thumbnail(id, b"thumbnail").unwrap();
+        assert_eq!(storage.get_thumbnail(id).unwrap(), None);
+
+        storage.put_image(id, b"image").unwrap();
+        storage.put_thumbnail(id, b"thumbnail").unwrap();
+        assert_eq!(
+            storage.get_image(id).unwrap().as_deref(),
+            Some(&b"image"[..])
+        );
+        assert_eq!(
+            storage.get_thumbnail(id).unwrap().as_deref(),
+            Some(&b"thumbnail"[..])
+        );
+
+        //A new image makes the thumbnail of the old one stale
+        storage.put_image(id, b"new image").unwrap();
+        assert_eq!(
+            storage.get_image(id).unwrap().as_deref(),
+            Some(&b"new image"[..])
+        );
+        assert_eq!(storage.get_thumbnail(id).unwrap(), None);
+
+        storage.put_thumbnail(id, b"thumbnail").unwrap();
+        storage.delete_image(id).unwrap();
+        assert_eq!(storage.get_image(id).unwrap(), None);
+        assert_eq!(storage.get_thumbnail(id).unwrap(), None);
+        assert_eq!(storage.get(id).unwrap().as_deref(), Some(&b"recipe"[..]));
+
+        storage.put_image(id, b"image").unwrap();
+        storage.delete(id).unwrap();
+        assert_eq!(storage.get_image(id).unwrap(), None);
+    }
+
+    fn index(storage: &dyn Storage) {
+        assert_eq!(storage.get_index().unwrap(), None);
+        storage.put_index(b"index").unwrap();
+        storage.put_index(b"new index").unwrap();
+        assert_eq!(
+            storage.get_index().unwrap().as_deref(),
+            Some(&b"new index"[..])
+        );
+    }
+}
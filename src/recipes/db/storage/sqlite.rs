@@ -0,0 +1,378 @@
+//! Storage keeping all recipes in a single SQLite database file, suited to large collections
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{RecipeId, Storage, StorageError, TrashEntry};
+use crate::recipes::db::check::{self, Finding, Issue, Repair};
+
+/// Statements creating the tables used to store recipes
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS recipes (
+        id TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS images (
+        id TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS thumbnails (
+        id TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        id TEXT PRIMARY KEY NOT NULL,
+        deleted_on TEXT NOT NULL,
+        data BLOB NOT NULL,
+        image BLOB
+    );
+    CREATE TABLE IF NOT EXISTS backups (
+        id TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (id, version)
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+";
+
+/// Key of the recipe index in the meta table
+const INDEX_KEY: &str = "index";
+
+/// Storage backend keeping recipes and images as rows of an SQLite database
+#[derive(Debug)]
+pub struct SqliteStorage {
+    /// The open database connection, which can only be used by one thread at a time
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Open or create an SQLite database at the given path
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Create the recipe tables in an open connection if they don't already exist
+    fn from_connection(conn: Connection) -> Result<Self, StorageError> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        //Overwrite replaced and deleted data so that plain recipes don't linger in free pages
+        //after they are encrypted
+        conn.pragma_update(None, "secure_delete", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Get one blob column from the given table by recipe ID
+    fn get_blob(&self, table: &str, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .conn
+            .lock()
+            .query_row(
+                &format!("SELECT data FROM {} WHERE id = ?1", table),
+                params![id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Insert or replace one blob in the given table
+    fn put_blob(&self, table: &str, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            &format!(
+                "INSERT INTO {} (id, data) VALUES (?1, ?2)
+                ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+                table
+            ),
+            params![id.to_string(), data],
+        )?;
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load_all(&self) -> Result<Vec<(RecipeId, Vec<u8>)>, StorageError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT id, data FROM recipes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+
+        let mut recipes = Vec::new();
+        for row in rows {
+            let (id, data) = row?;
+            match id.parse() {
+                Ok(id) => recipes.push((id, data)),
+                Err(e) => log::warn!("Recipe row has invalid ID {}: {}, skipping", id, e),
+            }
+        }
+        Ok(recipes)
+    }
+
+    fn ids(&self) -> Result<Vec<RecipeId>, StorageError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT id FROM recipes")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| match id.parse() {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    log::warn!("Recipe row has invalid ID {}: {}, skipping", id, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn get(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        self.get_blob("recipes", id)
+    }
+
+    fn put(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.put_blob("recipes", id, data)
+    }
+
+    fn delete(&self, id: RecipeId) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM recipes WHERE id = ?1", params![id.to_string()])?;
+        tx.execute("DELETE FROM images WHERE id = ?1", params![id.to_string()])?;
+        tx.execute(
+            "DELETE FROM thumbnails WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn trash(&self, id: RecipeId, deleted_on: DateTime<Utc>) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO trash (id, deleted_on, data, image)
+            SELECT recipes.id, ?2, recipes.data, images.data FROM recipes
+            LEFT JOIN images ON images.id = recipes.id WHERE recipes.id = ?1",
+            params![id.to_string(), deleted_on.to_rfc3339()],
+        )?;
+        tx.execute("DELETE FROM recipes WHERE id = ?1", params![id.to_string()])?;
+        tx.execute("DELETE FROM images WHERE id = ?1", params![id.to_string()])?;
+        tx.execute(
+            "DELETE FROM thumbnails WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashEntry>, StorageError> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT id, deleted_on, data FROM trash")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, deleted_on, data) = row?;
+            match (id.parse(), DateTime::parse_from_rfc3339(&deleted_on)) {
+                (Ok(id), Ok(deleted_on)) => entries.push(TrashEntry {
+                    id,
+                    deleted_on: deleted_on.with_timezone(&Utc),
+                    data,
+                }),
+                _ => log::warn!("Trash row for recipe {} is invalid, skipping", id),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn restore(&self, id: RecipeId) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        let stored = tx
+            .query_row(
+                "SELECT 1 FROM recipes WHERE id = ?1",
+                params![id.to_string()],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if stored.is_some() {
+            return Err(StorageError::AlreadyStored(id));
+        }
+        let restored = tx.execute(
+            "INSERT INTO recipes (id, data) SELECT id, data FROM trash WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        if restored == 0 {
+            return Err(StorageError::NotInTrash(id));
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO images (id, data)
+            SELECT id, image FROM trash WHERE id = ?1 AND image IS NOT NULL",
+            params![id.to_string()],
+        )?;
+        tx.execute("DELETE FROM trash WHERE id = ?1", params![id.to_string()])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn purge(&self, id: RecipeId) -> Result<(), StorageError> {
+        self.conn
+            .lock()
+            .execute("DELETE FROM trash WHERE id = ?1", params![id.to_string()])?;
+        Ok(())
+    }
+
+    fn get_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        self.get_blob("images", id)
+    }
+
+    fn get_trashed_image(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .conn
+            .lock()
+            .query_row(
+                "SELECT image FROM trash WHERE id = ?1",
+                params![id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    fn put_image(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO images (id, data) VALUES (?1, ?2)
+            ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![id.to_string(), data],
+        )?;
+        tx.execute(
+            "DELETE FROM thumbnails WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_image(&self, id: RecipeId) -> Result<(), StorageError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM images WHERE id = ?1", params![id.to_string()])?;
+        tx.execute(
+            "DELETE FROM thumbnails WHERE id = ?1",
+            params![id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_thumbnail(&self, id: RecipeId) -> Result<Option<Vec<u8>>, StorageError> {
+        self.get_blob("thumbnails", id)
+    }
+
+    fn put_thumbnail(&self, id: RecipeId, data: &[u8]) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "INSERT INTO thumbnails (id, data)
+            SELECT ?1, ?2 WHERE EXISTS (SELECT 1 FROM images WHERE id = ?1)
+            ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![id.to_string(), data],
+        )?;
+        Ok(())
+    }
+
+    fn check(&self, repair: bool) -> Vec<Finding> {
+        let mut findings = check::check_storage(self, repair);
+
+        let orphans = self
+            .conn
+            .lock()
+            .prepare("SELECT id FROM images WHERE id NOT IN (SELECT id FROM recipes)")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        let orphans = match orphans {
+            Ok(orphans) => orphans,
+            Err(e) => {
+                log::error!("Failed to find orphaned images: {}", e);
+                return findings;
+            }
+        };
+
+        for id in orphans.iter().filter_map(|id| id.parse::<RecipeId>().ok()) {
+            let repair = match repair {
+                true => match self
+                    .conn
+                    .lock()
+                    .execute("DELETE FROM images WHERE id = ?1", params![id.to_string()])
+                {
+                    Ok(_) => Repair::Removed,
+                    Err(e) => Repair::Failed(e.to_string()),
+                },
+                false => Repair::None,
+            };
+            findings.push(Finding {
+                issue: Issue::OrphanImage(id),
+                repair,
+            });
+        }
+
+        findings
+    }
+
+    fn put_backup(&self, id: RecipeId, version: u64, data: &[u8]) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO backups (id, version, data) VALUES (?1, ?2, ?3)",
+            params![id.to_string(), version as i64, data],
+        )?;
+        Ok(())
+    }
+
+    fn remove_backups(&self) -> Result<(), StorageError> {
+        //Rebuild the database file and empty the write-ahead log so no old pages are left in
+        //either of them
+        let conn = self.conn.lock();
+        conn.execute_batch("DELETE FROM backups; VACUUM;")?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn get_index(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .conn
+            .lock()
+            .query_row(
+                "SELECT data FROM meta WHERE key = ?1",
+                params![INDEX_KEY],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn put_index(&self, data: &[u8]) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "INSERT INTO meta (key, data) VALUES (?1, ?2)
+            ON CONFLICT(key) DO UPDATE SET data = excluded.data",
+            params![INDEX_KEY, data],
+        )?;
+        Ok(())
+    }
+}
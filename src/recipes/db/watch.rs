@@ -0,0 +1,73 @@
+//! Watching a recipe directory for changes made by other programs
+
+use std::{path::Path, sync::mpsc, time::Duration};
+
+use hashbrown::HashSet;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::RecipeId;
+
+/// How long to wait for more changes after a change before reporting changed recipes, so that
+/// all the files written by one save are reported together
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A handle to a running directory watcher, which stops watching when dropped
+pub struct DirWatcher {
+    /// The file system watcher, kept alive as long as this handle is
+    _watcher: RecommendedWatcher,
+}
+
+impl DirWatcher {
+    /// Watch a recipe directory in the background, calling `on_change` with the IDs of recipes
+    /// whose folders were changed
+    pub fn new(
+        dir: &Path,
+        on_change: impl Fn(Vec<RecipeId>) + Send + 'static,
+    ) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+
+        let dir = dir.to_owned();
+        std::thread::spawn(move || {
+            //Stops when the watcher is dropped and the channel is disconnected
+            while let Ok(event) = rx.recv() {
+                let mut changed = HashSet::new();
+                collect_ids(&dir, event, &mut changed);
+                while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+                    collect_ids(&dir, event, &mut changed);
+                }
+
+                if !changed.is_empty() {
+                    log::trace!("{} recipes changed in {}", changed.len(), dir.display());
+                    on_change(changed.into_iter().collect());
+                }
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Add the IDs of all recipe folders that a file system event affected to a set
+fn collect_ids(dir: &Path, event: notify::Result<notify::Event>, changed: &mut HashSet<RecipeId>) {
+    let event = match event {
+        Ok(event) if !event.kind.is_access() => event,
+        Ok(_) => return,
+        Err(e) => {
+            log::warn!("Error watching recipe directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    changed.extend(event.paths.iter().filter_map(|path| {
+        path.strip_prefix(dir)
+            .ok()?
+            .components()
+            .next()?
+            .as_os_str()
+            .to_str()?
+            .parse::<RecipeId>()
+            .ok()
+    }));
+}
@@ -0,0 +1,498 @@
+//! Formatting of measurements for display, with long or abbreviated unit names,
+//! pluralization, rounding, and locale-dependent decimal separators
+
+use std::time::Duration;
+
+use druid::Data;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    measure::{MassUnit, TimeUnit, VolumeSystem, VolumeUnit},
+    recipe::{Ingredient, IngredientAmount},
+};
+
+/// A unit of measurement that can be named in singular, plural, and abbreviated form
+pub trait Unit {
+    /// Full name of one of this unit, like "tablespoon"
+    fn singular(&self) -> &'static str;
+    /// Full name of many of this unit, like "tablespoons"
+    fn plural(&self) -> &'static str;
+    /// Short name of this unit that is the same for any amount, like "tbsp"
+    fn abbreviation(&self) -> &'static str;
+}
+
+/// How unit names are written when formatting a measurement
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+pub enum UnitStyle {
+    /// Full unit names like "2 tablespoons"
+    Long,
+    /// Abbreviated unit names like "2 tbsp"
+    Short,
+}
+
+/// The character placed between the whole and fractional parts of a number
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// A decimal point, like "1.5"
+    Point,
+    /// A decimal comma, like "1,5"
+    Comma,
+}
+
+/// Languages that write numbers with a decimal comma
+const COMMA_LANGUAGES: &[&str] = &[
+    "af", "be", "bg", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fo", "fr", "gl", "hr",
+    "hu", "id", "is", "it", "kk", "lt", "lv", "mk", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru",
+    "sk", "sl", "sq", "sr", "sv", "tr", "uk", "vi",
+];
+
+impl DecimalSeparator {
+    /// Get the decimal separator used by a locale name like `de_DE.UTF-8` or `fr-FR`
+    pub fn for_locale(locale: &str) -> Self {
+        let language = locale
+            .split(&['_', '-', '.', '@'][..])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match COMMA_LANGUAGES.contains(&language.as_str()) {
+            true => Self::Comma,
+            false => Self::Point,
+        }
+    }
+
+    /// Get the decimal separator of the user's locale from the environment
+    pub fn system() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .map(|locale| Self::for_locale(&locale))
+            .unwrap_or(Self::Point)
+    }
+
+    /// Get the character that this separator is written as
+    pub const fn as_char(&self) -> char {
+        match self {
+            Self::Point => '.',
+            Self::Comma => ',',
+        }
+    }
+}
+
+/// Settings used to format measurements, the [Default] is long unit names with a decimal point
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+pub struct UnitFormat {
+    /// If unit names are written out or abbreviated
+    pub style: UnitStyle,
+    /// The decimal separator used when writing numbers
+    pub separator: DecimalSeparator,
+}
+
+impl Default for UnitFormat {
+    fn default() -> Self {
+        Self {
+            style: UnitStyle::Long,
+            separator: DecimalSeparator::Point,
+        }
+    }
+}
+
+impl UnitFormat {
+    /// Long unit names with the decimal separator of the user's locale
+    pub fn system() -> Self {
+        Self {
+            style: UnitStyle::Long,
+            separator: DecimalSeparator::system(),
+        }
+    }
+
+    /// Round a number to a precision that makes sense for its size
+    fn round(val: f32) -> f32 {
+        let scale = match val.abs() {
+            v if v >= 100. => 1.,
+            v if v >= 10. => 10.,
+            _ => 100.,
+        };
+        let rounded = (val * scale).round() / scale;
+        //Avoid printing negative zero
+        if rounded == 0. {
+            0.
+        } else {
+            rounded
+        }
+    }
+
+    /// Format a number, rounding it and stripping trailing zeros
+    pub fn number(&self, val: f32) -> String {
+        let mut num = format!("{:.2}", Self::round(val));
+        if num.contains('.') {
+            let trimmed = num.trim_end_matches('0').trim_end_matches('.').len();
+            num.truncate(trimmed);
+        }
+
+        match self.separator {
+            DecimalSeparator::Point => num,
+            DecimalSeparator::Comma => num.replace('.', ","),
+        }
+    }
+
+    /// Check if an amount reads as one of something once rounded, like "1 cup" or "0.5 cup"
+    pub fn is_singular(&self, val: f32) -> bool {
+        let rounded = Self::round(val);
+        rounded > 0. && rounded <= 1.
+    }
+
+    /// Get the name of a unit to use after the given amount
+    pub fn unit_name(&self, unit: &impl Unit, val: f32) -> &'static str {
+        match (self.style, self.is_singular(val)) {
+            (UnitStyle::Short, _) => unit.abbreviation(),
+            (UnitStyle::Long, true) => unit.singular(),
+            (UnitStyle::Long, false) => unit.plural(),
+        }
+    }
+
+    /// Format an amount of the given unit, like "1.5 cups"
+    pub fn measure(&self, val: f32, unit: &impl Unit) -> String {
+        format!("{} {}", self.number(val), self.unit_name(unit, val))
+    }
+
+    /// Format an ingredient amount, returning an empty string if there is no amount
+    pub fn amount(&self, amount: &IngredientAmount) -> String {
+        match amount {
+            IngredientAmount::Count(count) => self.number(*count),
+            IngredientAmount::Mass(mass) => self.measure(mass.val, &mass.unit),
+            IngredientAmount::Volume(vol) => self.measure(vol.val, &vol.unit),
+            IngredientAmount::None => String::new(),
+        }
+    }
+
+    /// Format an ingredient's name followed by its amount, like "flour 1.5 cups"
+    pub fn ingredient(&self, ingredient: &Ingredient) -> String {
+        match ingredient.amount {
+            IngredientAmount::None => ingredient.name.to_string(),
+            _ => format!("{} {}", ingredient.name, self.amount(&ingredient.amount)),
+        }
+    }
+
+    /// Format a duration in the largest time unit that it fills at least one of
+    pub fn duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs_f32();
+        let unit = [TimeUnit::Day, TimeUnit::Hour, TimeUnit::Minute]
+            .iter()
+            .copied()
+            .find(|unit| secs >= unit.seconds())
+            .unwrap_or(TimeUnit::Second);
+
+        self.measure(secs / unit.seconds(), &unit)
+    }
+}
+
+impl Unit for TimeUnit {
+    fn singular(&self) -> &'static str {
+        match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    fn plural(&self) -> &'static str {
+        match self {
+            Self::Second => "seconds",
+            Self::Minute => "minutes",
+            Self::Hour => "hours",
+            Self::Day => "days",
+        }
+    }
+
+    fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Second => "sec",
+            Self::Minute => "min",
+            Self::Hour => "hr",
+            Self::Day => "d",
+        }
+    }
+}
+
+impl Unit for MassUnit {
+    fn singular(&self) -> &'static str {
+        match self {
+            Self::Gram => "gram",
+            Self::Kilogram => "kilogram",
+            Self::Milligram => "milligram",
+            Self::Ounce => "ounce",
+            Self::Pound => "pound",
+        }
+    }
+
+    fn plural(&self) -> &'static str {
+        match self {
+            Self::Gram => "grams",
+            Self::Kilogram => "kilograms",
+            Self::Milligram => "milligrams",
+            Self::Ounce => "ounces",
+            Self::Pound => "pounds",
+        }
+    }
+
+    fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Gram => "g",
+            Self::Kilogram => "kg",
+            Self::Milligram => "mg",
+            Self::Ounce => "oz",
+            Self::Pound => "lb",
+        }
+    }
+}
+
+impl Unit for VolumeUnit {
+    fn singular(&self) -> &'static str {
+        match self {
+            Self::Cup => "cup",
+            Self::Liter => "liter",
+            Self::Milliliter => "milliliter",
+            Self::Teaspoon => "teaspoon",
+            Self::Tablespoon => "tablespoon",
+            Self::Pint => "pint",
+            Self::Quart => "quart",
+            Self::Gallon => "gallon",
+            Self::FluidOz => "fluid ounce",
+            Self::RegionalCup(system) => match system {
+                VolumeSystem::UsCustomary => "US cup",
+                VolumeSystem::UsLegal => "US legal cup",
+                VolumeSystem::Imperial => "imperial cup",
+                VolumeSystem::Metric => "metric cup",
+            },
+            Self::RegionalPint(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US pint",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial pint",
+            },
+            Self::RegionalQuart(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US quart",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial quart",
+            },
+            Self::RegionalGallon(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US gallon",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial gallon",
+            },
+            Self::RegionalTeaspoon(system) => match system {
+                VolumeSystem::UsCustomary => "US teaspoon",
+                VolumeSystem::UsLegal => "US legal teaspoon",
+                VolumeSystem::Imperial => "imperial teaspoon",
+                VolumeSystem::Metric => "metric teaspoon",
+            },
+            Self::RegionalTablespoon(system) => match system {
+                VolumeSystem::UsCustomary => "US tablespoon",
+                VolumeSystem::UsLegal => "US legal tablespoon",
+                VolumeSystem::Imperial => "imperial tablespoon",
+                VolumeSystem::Metric => "metric tablespoon",
+            },
+            Self::RegionalFluidOz(system) => match system {
+                VolumeSystem::UsCustomary => "US fluid ounce",
+                VolumeSystem::UsLegal => "US legal fluid ounce",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial fluid ounce",
+            },
+        }
+    }
+
+    fn plural(&self) -> &'static str {
+        match self {
+            Self::Cup => "cups",
+            Self::Liter => "liters",
+            Self::Milliliter => "milliliters",
+            Self::Teaspoon => "teaspoons",
+            Self::Tablespoon => "tablespoons",
+            Self::Pint => "pints",
+            Self::Quart => "quarts",
+            Self::Gallon => "gallons",
+            Self::FluidOz => "fluid ounces",
+            Self::RegionalCup(system) => match system {
+                VolumeSystem::UsCustomary => "US cups",
+                VolumeSystem::UsLegal => "US legal cups",
+                VolumeSystem::Imperial => "imperial cups",
+                VolumeSystem::Metric => "metric cups",
+            },
+            Self::RegionalPint(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US pints",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial pints",
+            },
+            Self::RegionalQuart(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US quarts",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial quarts",
+            },
+            Self::RegionalGallon(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US gallons",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial gallons",
+            },
+            Self::RegionalTeaspoon(system) => match system {
+                VolumeSystem::UsCustomary => "US teaspoons",
+                VolumeSystem::UsLegal => "US legal teaspoons",
+                VolumeSystem::Imperial => "imperial teaspoons",
+                VolumeSystem::Metric => "metric teaspoons",
+            },
+            Self::RegionalTablespoon(system) => match system {
+                VolumeSystem::UsCustomary => "US tablespoons",
+                VolumeSystem::UsLegal => "US legal tablespoons",
+                VolumeSystem::Imperial => "imperial tablespoons",
+                VolumeSystem::Metric => "metric tablespoons",
+            },
+            Self::RegionalFluidOz(system) => match system {
+                VolumeSystem::UsCustomary => "US fluid ounces",
+                VolumeSystem::UsLegal => "US legal fluid ounces",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imperial fluid ounces",
+            },
+        }
+    }
+
+    fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Cup => "c",
+            Self::Liter => "L",
+            Self::Milliliter => "mL",
+            Self::Teaspoon => "tsp",
+            Self::Tablespoon => "tbsp",
+            Self::Pint => "pt",
+            Self::Quart => "qt",
+            Self::Gallon => "gal",
+            Self::FluidOz => "fl oz",
+            Self::RegionalCup(system) => match system {
+                VolumeSystem::UsCustomary => "US c",
+                VolumeSystem::UsLegal => "US legal c",
+                VolumeSystem::Imperial => "imp c",
+                VolumeSystem::Metric => "metric c",
+            },
+            Self::RegionalPint(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US pt",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imp pt",
+            },
+            Self::RegionalQuart(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US qt",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imp qt",
+            },
+            Self::RegionalGallon(system) => match system {
+                VolumeSystem::UsCustomary | VolumeSystem::UsLegal => "US gal",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imp gal",
+            },
+            Self::RegionalTeaspoon(system) => match system {
+                VolumeSystem::UsCustomary => "US tsp",
+                VolumeSystem::UsLegal => "US legal tsp",
+                VolumeSystem::Imperial => "imp tsp",
+                VolumeSystem::Metric => "metric tsp",
+            },
+            Self::RegionalTablespoon(system) => match system {
+                VolumeSystem::UsCustomary => "US tbsp",
+                VolumeSystem::UsLegal => "US legal tbsp",
+                VolumeSystem::Imperial => "imp tbsp",
+                VolumeSystem::Metric => "metric tbsp",
+            },
+            Self::RegionalFluidOz(system) => match system {
+                VolumeSystem::UsCustomary => "US fl oz",
+                VolumeSystem::UsLegal => "US legal fl oz",
+                VolumeSystem::Imperial | VolumeSystem::Metric => "imp fl oz",
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::measure::{Mass, Volume};
+
+    const SHORT: UnitFormat = UnitFormat {
+        style: UnitStyle::Short,
+        separator: DecimalSeparator::Point,
+    };
+    const COMMA: UnitFormat = UnitFormat {
+        style: UnitStyle::Long,
+        separator: DecimalSeparator::Comma,
+    };
+
+    #[test]
+    fn rounding() {
+        let format = UnitFormat::default();
+        assert_eq!(format.number(3.), "3");
+        assert_eq!(format.number(2.5), "2.5");
+        assert_eq!(format.number(1. / 3.), "0.33");
+        assert_eq!(format.number(12.345), "12.3");
+        assert_eq!(format.number(1234.56), "1235");
+        assert_eq!(format.number(0.999), "1");
+        assert_eq!(format.number(0.001), "0");
+        assert_eq!(format.number(-0.001), "0");
+    }
+
+    #[test]
+    fn pluralization() {
+        let format = UnitFormat::default();
+        assert_eq!(format.measure(1., &VolumeUnit::Cup), "1 cup");
+        assert_eq!(format.measure(0.5, &VolumeUnit::Cup), "0.5 cup");
+        assert_eq!(format.measure(1.001, &VolumeUnit::Cup), "1 cup");
+        assert_eq!(format.measure(2., &VolumeUnit::Cup), "2 cups");
+        assert_eq!(format.measure(0., &VolumeUnit::Cup), "0 cups");
+        assert_eq!(format.measure(0.001, &MassUnit::Gram), "0 grams");
+        assert_eq!(
+            format.measure(1.5, &VolumeUnit::RegionalFluidOz(VolumeSystem::Imperial)),
+            "1.5 imperial fluid ounces"
+        );
+        assert_eq!(SHORT.measure(1., &VolumeUnit::Tablespoon), "1 tbsp");
+        assert_eq!(SHORT.measure(2., &VolumeUnit::Tablespoon), "2 tbsp");
+    }
+
+    #[test]
+    fn decimal_separator() {
+        assert_eq!(COMMA.number(1.5), "1,5");
+        assert_eq!(COMMA.number(1234.), "1234");
+        assert_eq!(
+            COMMA.amount(&IngredientAmount::Mass(Mass::new(MassUnit::Kilogram, 0.25))),
+            "0,25 kilogram"
+        );
+        assert_eq!(COMMA.duration(Duration::from_secs(5400)), "1,5 hours");
+
+        assert_eq!(
+            DecimalSeparator::for_locale("de_DE.UTF-8"),
+            DecimalSeparator::Comma
+        );
+        assert_eq!(
+            DecimalSeparator::for_locale("fr-FR"),
+            DecimalSeparator::Comma
+        );
+        assert_eq!(
+            DecimalSeparator::for_locale("PT_br"),
+            DecimalSeparator::Comma
+        );
+        assert_eq!(
+            DecimalSeparator::for_locale("en_US.UTF-8"),
+            DecimalSeparator::Point
+        );
+        assert_eq!(DecimalSeparator::for_locale("C"), DecimalSeparator::Point);
+        assert_eq!(DecimalSeparator::for_locale(""), DecimalSeparator::Point);
+    }
+
+    #[test]
+    fn amounts_and_durations() {
+        let format = UnitFormat::default();
+        let milk = Ingredient {
+            name: "milk".into(),
+            amount: IngredientAmount::Volume(Volume::new(VolumeUnit::Cup, 1.5)),
+            optional: false,
+        };
+        assert_eq!(format.ingredient(&milk), "milk 1.5 cups");
+        assert_eq!(SHORT.ingredient(&milk), "milk 1.5 c");
+        let unmeasured = Ingredient {
+            amount: IngredientAmount::None,
+            ..milk
+        };
+        assert_eq!(format.amount(&unmeasured.amount), "");
+        assert_eq!(format.ingredient(&unmeasured), "milk");
+        assert_eq!(format.amount(&IngredientAmount::Count(2.)), "2");
+
+        assert_eq!(format.duration(Duration::from_secs(45)), "45 seconds");
+        assert_eq!(format.duration(Duration::from_secs(60)), "1 minute");
+        assert_eq!(format.duration(Duration::from_secs(86400 * 2)), "2 days");
+        assert_eq!(SHORT.duration(Duration::from_secs(1800)), "30 min");
+    }
+}
@@ -0,0 +1,733 @@
+//! Converting recipes to and from the schema.org `Recipe` JSON-LD that most recipe websites
+//! embed in their pages, see <https://schema.org/Recipe>
+
+use std::{fs, io, path::Path, sync::Arc, time::Duration};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use druid::im::Vector;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use super::{
+    db::{atomic, RecipeId},
+    format::{Unit, UnitFormat},
+    measure::{Mass, MassUnit, Volume, VolumeSystem, VolumeUnit},
+    recipe::{Ingredient, IngredientAmount, Recipe},
+};
+
+/// Name given to imported recipes that have no name
+const UNNAMED: &str = "Imported Recipe";
+
+/// Written after the names of optional ingredients when exporting, and removed when importing
+const OPTIONAL: &str = "(optional)";
+
+/// Units of mass that ingredient amounts are read in
+const MASS_UNITS: [MassUnit; 5] = [
+    MassUnit::Gram,
+    MassUnit::Kilogram,
+    MassUnit::Milligram,
+    MassUnit::Ounce,
+    MassUnit::Pound,
+];
+
+/// Units of volume without a measurement system that ingredient amounts are read in
+const VOLUME_UNITS: [VolumeUnit; 9] = [
+    VolumeUnit::Cup,
+    VolumeUnit::Liter,
+    VolumeUnit::Milliliter,
+    VolumeUnit::Teaspoon,
+    VolumeUnit::Tablespoon,
+    VolumeUnit::Pint,
+    VolumeUnit::Quart,
+    VolumeUnit::Gallon,
+    VolumeUnit::FluidOz,
+];
+
+/// Enumeration defining all errors that can occur when importing or exporting JSON-LD
+#[derive(Debug, Error)]
+pub enum JsonLdError {
+    #[error("Input/output error: {}", .0)]
+    Io(#[from] io::Error),
+
+    #[error("Failed to read or write JSON: {}", .0)]
+    Json(#[from] serde_json::Error),
+
+    #[error("File does not contain a schema.org Recipe")]
+    NotRecipe,
+}
+
+/// Check if a file is JSON-LD. Files with the `.jsonld` extension always are, and files with the
+/// `.json` extension are only if they contain a schema.org `Recipe`, so that other JSON files are
+/// not imported as recipes
+pub fn is_json_ld(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("jsonld") => true,
+        Some(ext) if ext.eq_ignore_ascii_case("json") => read(path)
+            .ok()
+            .is_some_and(|value| find_recipe(&value).is_some()),
+        _ => false,
+    }
+}
+
+/// Read the first schema.org `Recipe` in a JSON-LD file as a new recipe
+pub fn import(path: &Path) -> Result<Recipe, JsonLdError> {
+    find_recipe(&read(path)?)
+        .map(from_json_ld)
+        .ok_or(JsonLdError::NotRecipe)
+}
+
+/// Read and parse a JSON file
+fn read(path: &Path) -> Result<Value, JsonLdError> {
+    Ok(serde_json::from_slice::<Value>(&fs::read(path)?)?)
+}
+
+/// Write a recipe to a file as a schema.org `Recipe` in JSON-LD, replacing the file only once
+/// the recipe is completely written
+pub fn export(recipe: &Recipe, path: &Path) -> Result<(), JsonLdError> {
+    let value = to_json_ld(recipe);
+    let valid = |data: &[u8]| serde_json::from_slice::<Value>(data).is_ok();
+    atomic::write(path, valid, |writer| {
+        serde_json::to_writer_pretty(writer, &value).map_err(Into::into)
+    })?;
+    Ok(())
+}
+
+/// Convert a schema.org `Recipe` object to a new recipe. Instructions are numbered steps after
+/// the description, and the recipe takes its `totalTime` or else its `prepTime` and `cookTime`
+/// added together
+pub fn from_json_ld(value: &Value) -> Recipe {
+    let name = value
+        .get("name")
+        .and_then(text)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| UNNAMED.to_owned());
+
+    let created_on = ["dateCreated", "datePublished"]
+        .iter()
+        .filter_map(|key| value.get(key).and_then(text))
+        .find_map(|date| parse_date(&date))
+        .unwrap_or_else(Utc::now);
+
+    let ingredients = value
+        .get("recipeIngredient")
+        .or_else(|| value.get("ingredients"))
+        .map(list)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(text)
+        .filter(|ingredient| !ingredient.is_empty())
+        .map(|ingredient| parse_ingredient(&ingredient))
+        .collect::<Vector<_>>();
+
+    let mut body = value
+        .get("description")
+        .and_then(text)
+        .filter(|description| !description.is_empty())
+        .map(|description| format!("{}\n\n", description))
+        .unwrap_or_default();
+    let mut steps = Vec::new();
+    if let Some(instructions) = value.get("recipeInstructions") {
+        read_steps(instructions, &mut steps);
+    }
+    let mut number = 0;
+    for step in steps {
+        match step {
+            Step::Section(name) => body.push_str(&format!("\n{}\n", name)),
+            Step::Text(text) => {
+                number += 1;
+                body.push_str(&format!("{}. {}\n", number, text));
+            }
+        }
+    }
+
+    let time = |key: &str| {
+        value
+            .get(key)
+            .and_then(text)
+            .and_then(|t| parse_duration(&t))
+    };
+    let time = time("totalTime").or_else(|| match (time("prepTime"), time("cookTime")) {
+        (Some(prep), Some(cook)) => Some(prep + cook),
+        (prep, cook) => prep.or(cook),
+    });
+
+    Recipe {
+        id: RecipeId::new(),
+        name: Arc::from(name),
+        created_on,
+        servings: value.get("recipeYield").and_then(parse_yield),
+        ingredients,
+        body: Arc::from(body.trim()),
+        time,
+    }
+}
+
+/// Convert a recipe to a schema.org `Recipe` object. Each line of the recipe's instructions is
+/// written as one step
+pub fn to_json_ld(recipe: &Recipe) -> Value {
+    let format = UnitFormat::default();
+    let mut object = Map::new();
+    object.insert("@context".to_owned(), json!("https://schema.org"));
+    object.insert("@type".to_owned(), json!("Recipe"));
+    object.insert("name".to_owned(), json!(recipe.name.as_ref()));
+    object.insert(
+        "dateCreated".to_owned(),
+        json!(recipe.created_on.to_rfc3339()),
+    );
+    if let Some(servings) = recipe.servings {
+        object.insert("recipeYield".to_owned(), json!(format.number(servings)));
+    }
+    if let Some(time) = recipe.time {
+        object.insert("totalTime".to_owned(), json!(format_duration(time)));
+    }
+
+    let ingredients = recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| {
+            let amount = format.amount(&ingredient.amount);
+            let mut text = match amount.is_empty() {
+                true => ingredient.name.to_string(),
+                false => format!("{} {}", amount, ingredient.name),
+            };
+            if ingredient.optional {
+                text.push_str(&format!(" {}", OPTIONAL));
+            }
+            json!(text)
+        })
+        .collect::<Vec<_>>();
+    object.insert("recipeIngredient".to_owned(), json!(ingredients));
+
+    let steps = recipe
+        .body
+        .lines()
+        .map(|line| strip_number(line.trim()))
+        .filter(|line| !line.is_empty())
+        .map(|line| json!({ "@type": "HowToStep", "text": line }))
+        .collect::<Vec<_>>();
+    object.insert("recipeInstructions".to_owned(), json!(steps));
+    Value::Object(object)
+}
+
+/// A line of instructions read from JSON-LD
+enum Step {
+    /// The name of a section of steps
+    Section(String),
+    /// One step
+    Text(String),
+}
+
+/// Find the first object with the `Recipe` type in a JSON-LD document, looking through arrays
+/// and `@graph` lists
+fn find_recipe(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(items) => items.iter().find_map(find_recipe),
+        Value::Object(_) if has_type(value, "Recipe") => Some(value),
+        Value::Object(object) => object.get("@graph").and_then(find_recipe),
+        _ => None,
+    }
+}
+
+/// Check if a JSON-LD object has a type, which can be one type or a list of types
+fn has_type(value: &Value, ty: &str) -> bool {
+    value
+        .get("@type")
+        .map(list)
+        .unwrap_or_default()
+        .iter()
+        .any(|t| t.as_str() == Some(ty))
+}
+
+/// Get the items of a JSON array, or a list of one item for any other value
+fn list(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    }
+}
+
+/// Get the trimmed text of a string or number value, with common HTML entities decoded
+fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    let text = [
+        ("&nbsp;", " "),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+        ("&apos;", "'"),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&amp;", "&"),
+    ]
+    .iter()
+    .fold(text, |text, (entity, c)| text.replace(entity, c));
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Read the steps of `recipeInstructions`, which can be text, a list of text, `HowToStep`
+/// objects, or `HowToSection` objects holding more steps
+fn read_steps(value: &Value, steps: &mut Vec<Step>) {
+    match value {
+        Value::String(instructions) => steps.extend(
+            instructions
+                .lines()
+                .filter_map(|line| text(&Value::String(line.to_owned())))
+                .map(|line| strip_number(&line).to_owned())
+                .filter(|line| !line.is_empty())
+                .map(Step::Text),
+        ),
+        Value::Array(items) => items.iter().for_each(|item| read_steps(item, steps)),
+        Value::Object(_) if has_type(value, "HowToSection") => {
+            if let Some(name) = value.get("name").and_then(text) {
+                steps.push(Step::Section(name));
+            }
+            if let Some(items) = value.get("itemListElement") {
+                read_steps(items, steps);
+            }
+        }
+        Value::Object(_) => {
+            if let Some(step) = value
+                .get("text")
+                .or_else(|| value.get("name"))
+                .and_then(text)
+                .map(|step| strip_number(&step).to_owned())
+                .filter(|step| !step.is_empty())
+            {
+                steps.push(Step::Text(step));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Remove a step number like "1." or "2)" from the start of a line
+fn strip_number(line: &str) -> &str {
+    let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    match rest.len() < line.len() {
+        true => rest
+            .strip_prefix(|c| c == '.' || c == ')')
+            .map_or(line, str::trim_start),
+        false => line,
+    }
+}
+
+/// Parse a date in RFC 3339 format or a plain date like "2022-03-14"
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()?;
+            Some(DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+        })
+}
+
+/// Read the number of servings from `recipeYield`, like `4`, "4 servings", or a list of these
+fn parse_yield(value: &Value) -> Option<f32> {
+    list(value)
+        .into_iter()
+        .filter_map(text)
+        .find_map(|servings| parse_quantity(&servings).map(|(servings, _)| servings))
+        .filter(|servings| *servings > 0.)
+}
+
+/// Parse an ISO 8601 duration like "PT1H30M". Years and months are not used for recipes and
+/// are taken as 365 and 30 days
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let text = text.strip_prefix('P').or_else(|| text.strip_prefix('p'))?;
+    let (mut secs, mut time, mut number, mut any) = (0f64, false, String::new(), false);
+    for c in text.chars() {
+        match c {
+            'T' | 't' if number.is_empty() => time = true,
+            '0'..='9' | '.' => number.push(c),
+            ',' => number.push('.'),
+            unit => {
+                let value = number.parse::<f64>().ok()?;
+                number.clear();
+                secs += value
+                    * match (unit.to_ascii_uppercase(), time) {
+                        ('Y', false) => 365. * 86400.,
+                        ('M', false) => 30. * 86400.,
+                        ('W', false) => 7. * 86400.,
+                        ('D', false) => 86400.,
+                        ('H', true) => 3600.,
+                        ('M', true) => 60.,
+                        ('S', true) => 1.,
+                        _ => return None,
+                    };
+                any = true;
+            }
+        }
+    }
+    //Huge numbers from malformed data can overflow to infinity or past the largest duration
+    match any && number.is_empty() && secs.is_finite() {
+        true => Duration::try_from_secs_f64(secs).ok(),
+        false => None,
+    }
+}
+
+/// Format a duration as an ISO 8601 duration like "PT1H30M", rounded to the second
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64().round() as u64;
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut text = "PT".to_owned();
+    if hours > 0 {
+        text.push_str(&format!("{}H", hours));
+    }
+    if minutes > 0 {
+        text.push_str(&format!("{}M", minutes));
+    }
+    if seconds > 0 || secs == 0 {
+        text.push_str(&format!("{}S", seconds));
+    }
+    text
+}
+
+/// Parse an ingredient like "1 1/2 cups flour" into an amount and a name
+fn parse_ingredient(text: &str) -> Ingredient {
+    let (text, optional) = match text.strip_suffix(OPTIONAL) {
+        Some(text) => (text.trim_end(), true),
+        None => (text, false),
+    };
+    let (amount, name) = match parse_quantity(text) {
+        Some((val, rest)) => match parse_unit(rest) {
+            Some((UnitName::Mass(unit), name)) => {
+                (IngredientAmount::Mass(Mass::new(unit, val)), name)
+            }
+            Some((UnitName::Volume(unit), name)) => {
+                (IngredientAmount::Volume(Volume::new(unit, val)), name)
+            }
+            None => (IngredientAmount::Count(val), rest),
+        },
+        None => (IngredientAmount::None, text),
+    };
+
+    //Keep the whole text as the name rather than leaving an ingredient without a name
+    let name = name.trim_start_matches(|c: char| c.is_whitespace() || c == '.');
+    let (amount, name) = match name.is_empty() {
+        true => (IngredientAmount::None, text),
+        false => (amount, name),
+    };
+    Ingredient {
+        name: Arc::from(name.trim()),
+        amount,
+        optional,
+    }
+}
+
+/// Parse a number at the start of text, like "2", "1.5", "1/2", "1 1/2", "1½", or the first
+/// number of a range like "2-3", returning the number and the rest of the text
+fn parse_quantity(text: &str) -> Option<(f32, &str)> {
+    let text = text.trim_start();
+    let (word, rest) = split_word(text);
+    let word = word.split(['-', '–']).next().unwrap_or(word);
+    let value = parse_number(word)?;
+
+    //A whole number can be followed by a fraction, like "1 1/2"
+    let (next, after) = split_word(rest.trim_start());
+    match parse_number(next) {
+        Some(fraction) if fraction < 1. && value.fract() == 0. && is_fraction(next) => {
+            Some((value + fraction, after))
+        }
+        _ => Some((value, rest)),
+    }
+}
+
+/// Split the first word off of text
+fn split_word(text: &str) -> (&str, &str) {
+    text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()))
+}
+
+/// Check if a word is written as a fraction
+fn is_fraction(word: &str) -> bool {
+    word.contains('/') || word.chars().any(|c| vulgar_fraction(c).is_some())
+}
+
+/// Parse one number like "2", "1,5", "1/2", "½", or "1½"
+fn parse_number(word: &str) -> Option<f32> {
+    if word.is_empty() {
+        return None;
+    }
+    if let Some((numerator, denominator)) = word.split_once('/') {
+        let (numerator, denominator) = (
+            numerator.parse::<f32>().ok()?,
+            denominator.parse::<f32>().ok()?,
+        );
+        return (denominator != 0.).then(|| numerator / denominator);
+    }
+    match word.chars().last().and_then(vulgar_fraction) {
+        Some(fraction) => {
+            let whole = &word[..word.len() - word.chars().last()?.len_utf8()];
+            match whole.is_empty() {
+                true => Some(fraction),
+                false => Some(whole.parse::<f32>().ok()? + fraction),
+            }
+        }
+        None => word.replace(',', ".").parse::<f32>().ok(),
+    }
+}
+
+/// Get the value of a unicode fraction character like '½'
+fn vulgar_fraction(c: char) -> Option<f32> {
+    Some(match c {
+        '¼' => 0.25,
+        '½' => 0.5,
+        '¾' => 0.75,
+        '⅓' => 1. / 3.,
+        '⅔' => 2. / 3.,
+        '⅛' => 0.125,
+        '⅜' => 0.375,
+        '⅝' => 0.625,
+        '⅞' => 0.875,
+        _ => return None,
+    })
+}
+
+/// A unit found at the start of an ingredient's name
+enum UnitName {
+    Mass(MassUnit),
+    Volume(VolumeUnit),
+}
+
+/// Get every unit of volume that ingredient amounts are read in, including the units of each
+/// measurement system that recipes are exported with
+fn volume_units() -> impl Iterator<Item = VolumeUnit> {
+    VOLUME_UNITS
+        .iter()
+        .copied()
+        .chain(VolumeSystem::ALL.iter().flat_map(|&system| {
+            [
+                VolumeUnit::RegionalCup(system),
+                VolumeUnit::RegionalPint(system),
+                VolumeUnit::RegionalQuart(system),
+                VolumeUnit::RegionalGallon(system),
+                VolumeUnit::RegionalTeaspoon(system),
+                VolumeUnit::RegionalTablespoon(system),
+                VolumeUnit::RegionalFluidOz(system),
+            ]
+        }))
+}
+
+/// Parse a unit name at the start of text, like "cups" or "tbsp", returning the unit and the
+/// rest of the text. The longest matching name is used
+fn parse_unit(text: &str) -> Option<(UnitName, &str)> {
+    let text = text.trim_start();
+    let names = |unit: &dyn Unit| [unit.plural(), unit.singular(), unit.abbreviation()];
+    MASS_UNITS
+        .iter()
+        .flat_map(|unit| names(unit).map(|name| (name, UnitName::Mass(*unit))))
+        .chain(
+            volume_units().flat_map(|unit| names(&unit).map(|name| (name, UnitName::Volume(unit)))),
+        )
+        .filter(|(name, _)| {
+            text.get(..name.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(name))
+                && text[name.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c.is_whitespace() || c == '.')
+        })
+        .max_by_key(|(name, _)| name.len())
+        .map(|(name, unit)| (unit, &text[name.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use druid::im::vector;
+
+    fn ingredient(name: &str, amount: IngredientAmount, optional: bool) -> Ingredient {
+        Ingredient {
+            name: Arc::from(name),
+            amount,
+            optional,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let recipe = Recipe {
+            id: RecipeId::new(),
+            name: "Pancakes".into(),
+            created_on: Utc.ymd(2022, 3, 14).and_hms(9, 26, 53),
+            servings: Some(4.),
+            ingredients: vector![
+                ingredient("eggs", IngredientAmount::Count(2.), false),
+                ingredient(
+                    "flour",
+                    IngredientAmount::Mass(Mass::new(MassUnit::Gram, 250.)),
+                    false
+                ),
+                ingredient(
+                    "milk",
+                    IngredientAmount::Volume(Volume::new(VolumeUnit::Cup, 1.5)),
+                    false
+                ),
+                ingredient(
+                    "butter",
+                    IngredientAmount::Volume(Volume::new(
+                        VolumeUnit::RegionalTablespoon(VolumeSystem::Imperial),
+                        1.
+                    )),
+                    false
+                ),
+                ingredient("salt", IngredientAmount::None, true),
+            ],
+            body: "1. Mix everything together.\n2. Fry in a hot pan.".into(),
+            time: Some(Duration::from_secs(5400)),
+        };
+
+        let value = to_json_ld(&recipe);
+        assert_eq!(value["totalTime"], "PT1H30M");
+        assert_eq!(value["recipeIngredient"][2], "1.5 cups milk");
+        assert_eq!(value["recipeIngredient"][4], "salt (optional)");
+        assert_eq!(value["recipeInstructions"][1]["text"], "Fry in a hot pan.");
+
+        let imported = from_json_ld(&value);
+        assert_ne!(imported.id, recipe.id);
+        assert_eq!(
+            imported,
+            Recipe {
+                id: imported.id,
+                ..recipe
+            }
+        );
+    }
+
+    #[test]
+    fn import_from_graph() {
+        let value = json!({
+            "@context": "https://schema.org",
+            "@graph": [
+                { "@type": "WebPage", "name": "Not a recipe" },
+                {
+                    "@type": ["Recipe", "Thing"],
+                    "name": "Mac &amp; Cheese",
+                    "datePublished": "2021-11-10",
+                    "recipeYield": ["6 servings"],
+                    "prepTime": "PT10M",
+                    "cookTime": "PT20M",
+                    "recipeIngredient": ["1 lb macaroni", ""],
+                    "recipeInstructions": [{
+                        "@type": "HowToSection",
+                        "name": "Pasta",
+                        "itemListElement": [
+                            { "@type": "HowToStep", "text": "1) Boil   the pasta." }
+                        ]
+                    }]
+                }
+            ]
+        });
+
+        let recipe = from_json_ld(find_recipe(&value).unwrap());
+        assert_eq!(recipe.name.as_ref(), "Mac & Cheese");
+        assert_eq!(recipe.created_on, Utc.ymd(2021, 11, 10).and_hms(0, 0, 0));
+        assert_eq!(recipe.servings, Some(6.));
+        assert_eq!(recipe.time, Some(Duration::from_secs(1800)));
+        assert_eq!(
+            recipe.ingredients,
+            vector![ingredient(
+                "macaroni",
+                IngredientAmount::Mass(Mass::new(MassUnit::Pound, 1.)),
+                false
+            )]
+        );
+        assert_eq!(recipe.body.as_ref(), "Pasta\n1. Boil the pasta.");
+
+        assert!(find_recipe(&json!({ "@type": "WebPage" })).is_none());
+        assert_eq!(from_json_ld(&json!({})).name.as_ref(), UNNAMED);
+    }
+
+    #[test]
+    fn durations() {
+        let mins = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(parse_duration("PT1H30M"), mins(90));
+        assert_eq!(parse_duration(" pt15m "), mins(15));
+        assert_eq!(parse_duration("PT1,5H"), mins(90));
+        assert_eq!(parse_duration("PT0.5M"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("P1DT2H"), mins(26 * 60));
+        assert_eq!(parse_duration("P1W"), mins(7 * 24 * 60));
+
+        assert_eq!(format_duration(Duration::ZERO), "PT0S");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "PT1H30M");
+        assert_eq!(format_duration(Duration::from_millis(61_600)), "PT1M2S");
+        assert_eq!(
+            parse_duration(&format_duration(Duration::from_secs(93_784))),
+            Some(Duration::from_secs(93_784))
+        );
+    }
+
+    #[test]
+    fn malformed_durations() {
+        for text in [
+            "", "P", "PT", "1H", "T1H", "PT5", "PTM", "PT1X", "P1H", "PT1D", "PT1..5M",
+        ] {
+            assert_eq!(parse_duration(text), None, "{:?}", text);
+        }
+        //Too large to fit in a duration, or to even be a finite number
+        assert_eq!(parse_duration("P99999999999999999999999Y"), None);
+        assert_eq!(parse_duration(&format!("PT{}S", "9".repeat(400))), None);
+    }
+
+    #[test]
+    fn ingredients() {
+        let volume = |unit, val| IngredientAmount::Volume(Volume::new(unit, val));
+        let cases = [
+            ("1 1/2 cups flour", "flour", volume(VolumeUnit::Cup, 1.5)),
+            ("½ tsp. salt", "salt", volume(VolumeUnit::Teaspoon, 0.5)),
+            (
+                "1½ US cups milk",
+                "milk",
+                volume(VolumeUnit::RegionalCup(VolumeSystem::UsCustomary), 1.5),
+            ),
+            ("2-3 eggs", "eggs", IngredientAmount::Count(2.)),
+            (
+                "0,5 kg potatoes",
+                "potatoes",
+                IngredientAmount::Mass(Mass::new(MassUnit::Kilogram, 0.5)),
+            ),
+            ("3 gramsalt", "gramsalt", IngredientAmount::Count(3.)),
+            ("pepper", "pepper", IngredientAmount::None),
+        ];
+        for (text, name, amount) in cases.iter() {
+            assert_eq!(
+                parse_ingredient(text),
+                ingredient(name, *amount, false),
+                "{}",
+                text
+            );
+        }
+
+        assert_eq!(
+            parse_ingredient("2 limes (optional)"),
+            ingredient("limes", IngredientAmount::Count(2.), true)
+        );
+    }
+
+    #[test]
+    fn malformed_ingredients() {
+        //Text with nothing after the amount is kept whole as the name
+        assert_eq!(
+            parse_ingredient("3"),
+            ingredient("3", IngredientAmount::None, false)
+        );
+        assert_eq!(
+            parse_ingredient("2 cups"),
+            ingredient("2 cups", IngredientAmount::None, false)
+        );
+        assert_eq!(
+            parse_ingredient("1/0 cup sugar"),
+            ingredient("1/0 cup sugar", IngredientAmount::None, false)
+        );
+        assert_eq!(parse_number("1/0"), None);
+        assert_eq!(parse_number("a/2"), None);
+        assert_eq!(parse_number("x½"), None);
+        assert_eq!(parse_yield(&json!("a few")), None);
+        assert_eq!(parse_yield(&json!(0)), None);
+    }
+}
@@ -3,7 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use super::recipe::IngredientAmount;
+use super::{format::Unit, recipe::IngredientAmount};
 
 /// Units of time a user can pick
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -14,17 +14,24 @@ pub enum TimeUnit {
     Day,
 }
 
-impl fmt::Display for TimeUnit {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl TimeUnit {
+    /// Get the number of seconds in one of this unit
+    pub const fn seconds(&self) -> f32 {
         match self {
-            Self::Second => write!(f, "seconds"),
-            Self::Minute => write!(f, "minutes"),
-            Self::Hour => write!(f, "hours"),
-            Self::Day => write!(f, "days"),
+            Self::Second => 1.,
+            Self::Minute => 60.,
+            Self::Hour => 3600.,
+            Self::Day => 86400.,
         }
     }
 }
 
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_unit(f, self)
+    }
+}
+
 /// A unit of mass like kg, pound, ounce, etc.
 ///
 /// The value of the enum variant is the conversion factor to grams
@@ -39,13 +46,7 @@ pub enum MassUnit {
 
 impl fmt::Display for MassUnit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Gram => write!(f, "gram"),
-            Self::Kilogram => write!(f, "kilogram"),
-            Self::Milligram => write!(f, "milligram"),
-            Self::Ounce => write!(f, "ounce"),
-            Self::Pound => write!(f, "pound"),
-        }
+        write_unit(f, self)
     }
 }
 
@@ -94,25 +95,118 @@ impl Mass {
     }
 }
 
-impl fmt::Display for Mass {
+/// A system of measurement that defines how large cups, pints, quarts, gallons, spoons, and
+/// fluid ounces are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, druid::Data)]
+pub enum VolumeSystem {
+    /// United States customary units, where a cup is 236.6 mL and a pint is 16 US fluid ounces
+    #[default]
+    UsCustomary,
+    /// United States legal units used for nutrition labeling, where a cup is 240 mL, a
+    /// tablespoon is 15 mL, and larger units are the same as in US customary
+    UsLegal,
+    /// British imperial units, where a cup is 284.1 mL and a pint is 20 imperial fluid ounces
+    Imperial,
+    /// Metric units used in Australia, New Zealand, and Canada, where a cup is 250 mL, a
+    /// tablespoon is 15 mL, and larger units are the same as in imperial
+    Metric,
+}
+
+impl VolumeSystem {
+    /// All volume systems in the order they are cycled through
+    pub const ALL: [Self; 4] = [
+        Self::UsCustomary,
+        Self::UsLegal,
+        Self::Imperial,
+        Self::Metric,
+    ];
+
+    /// Get the system after this one in [ALL](Self::ALL), wrapping around to the first
+    pub fn next(self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|system| *system == self)
+            .unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Get the size of one teaspoon in liters
+    pub const fn teaspoon(&self) -> f32 {
+        match self {
+            Self::UsCustomary => 0.004_928_92,
+            Self::UsLegal | Self::Metric => 0.005,
+            Self::Imperial => 0.005_919_39,
+        }
+    }
+
+    /// Get the size of one tablespoon in liters
+    pub const fn tablespoon(&self) -> f32 {
+        match self {
+            Self::UsCustomary => 0.014_786_8,
+            Self::UsLegal | Self::Metric => 0.015,
+            Self::Imperial => 0.017_758_2,
+        }
+    }
+
+    /// Get the size of one fluid ounce in liters
+    pub const fn fluid_oz(&self) -> f32 {
+        match self {
+            Self::UsCustomary => 0.029_573_5,
+            Self::UsLegal => 0.03,
+            Self::Imperial | Self::Metric => 0.028_413_1,
+        }
+    }
+
+    /// Get the size of one cup in liters
+    pub const fn cup(&self) -> f32 {
+        match self {
+            Self::UsCustomary => 0.236_588,
+            Self::UsLegal => 0.24,
+            Self::Imperial => 0.284_131,
+            Self::Metric => 0.25,
+        }
+    }
+
+    /// Get the size of one pint in liters
+    pub const fn pint(&self) -> f32 {
+        match self {
+            Self::UsCustomary | Self::UsLegal => 0.473_176,
+            Self::Imperial | Self::Metric => 0.568_261,
+        }
+    }
+
+    /// Get the size of one quart in liters
+    pub const fn quart(&self) -> f32 {
+        match self {
+            Self::UsCustomary | Self::UsLegal => 0.946_353,
+            Self::Imperial | Self::Metric => 1.136_52,
+        }
+    }
+
+    /// Get the size of one gallon in liters
+    pub const fn gallon(&self) -> f32 {
+        match self {
+            Self::UsCustomary | Self::UsLegal => 3.785_41,
+            Self::Imperial | Self::Metric => 4.546_09,
+        }
+    }
+}
+
+impl fmt::Display for VolumeSystem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} {}{}",
-            self.val,
-            self.unit,
-            if (self.val - 1.0).abs() < f32::EPSILON {
-                "s"
-            } else {
-                ""
-            }
-        )
+        match self {
+            Self::UsCustomary => write!(f, "US customary"),
+            Self::UsLegal => write!(f, "US legal"),
+            Self::Imperial => write!(f, "imperial"),
+            Self::Metric => write!(f, "metric"),
+        }
     }
 }
 
 /// A volume of a substance, like cups, liters, etc.
 ///
-/// The value of this enum as an f64 is the conversion factor from measurement to liters
+/// Spoons, fluid ounces, cups, pints, quarts, and gallons without a [VolumeSystem] are measured in
+/// the system chosen in the config, which is passed to every conversion
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, druid::Data)]
 pub enum VolumeUnit {
     Cup,
@@ -124,48 +218,76 @@ pub enum VolumeUnit {
     Quart,
     Gallon,
     FluidOz,
+    /// A cup of the given measurement system
+    RegionalCup(VolumeSystem),
+    /// A pint of the given measurement system
+    RegionalPint(VolumeSystem),
+    /// A quart of the given measurement system
+    RegionalQuart(VolumeSystem),
+    /// A gallon of the given measurement system
+    RegionalGallon(VolumeSystem),
+    /// A teaspoon of the given measurement system
+    RegionalTeaspoon(VolumeSystem),
+    /// A tablespoon of the given measurement system
+    RegionalTablespoon(VolumeSystem),
+    /// A fluid ounce of the given measurement system
+    RegionalFluidOz(VolumeSystem),
 }
 
 impl VolumeUnit {
-    /// Get the conversion factor to liters for this unit
-    pub const fn conversion_factor(&self) -> f32 {
-        match self {
-            Self::Cup => 0.24,
+    /// Get the conversion factor to liters for this unit, measuring units without a system in
+    /// `system`
+    pub fn conversion_factor(&self, system: VolumeSystem) -> f32 {
+        match self.resolve(system) {
             Self::Liter => 1.,
             Self::Milliliter => 0.001,
-            Self::Teaspoon => 0.00492892,
-            Self::Tablespoon => 0.014787,
-            Self::Pint => 0.473176,
-            Self::Quart => 0.946353,
-            Self::Gallon => 3.78541,
-            Self::FluidOz => 0.0295735,
+            Self::RegionalTeaspoon(system) => system.teaspoon(),
+            Self::RegionalTablespoon(system) => system.tablespoon(),
+            Self::RegionalFluidOz(system) => system.fluid_oz(),
+            Self::RegionalCup(system) => system.cup(),
+            Self::RegionalPint(system) => system.pint(),
+            Self::RegionalQuart(system) => system.quart(),
+            Self::RegionalGallon(system) => system.gallon(),
+            Self::Cup
+            | Self::Pint
+            | Self::Quart
+            | Self::Gallon
+            | Self::Teaspoon
+            | Self::Tablespoon
+            | Self::FluidOz => unreachable!("Resolved volume unit has no measurement system"),
         }
     }
+
+    /// Get the unit that this unit is measured as, giving units without a system the given
+    /// [VolumeSystem]
+    pub fn resolve(&self, system: VolumeSystem) -> Self {
+        match self {
+            Self::Cup => Self::RegionalCup(system),
+            Self::Pint => Self::RegionalPint(system),
+            Self::Quart => Self::RegionalQuart(system),
+            Self::Gallon => Self::RegionalGallon(system),
+            Self::Teaspoon => Self::RegionalTeaspoon(system),
+            Self::Tablespoon => Self::RegionalTablespoon(system),
+            Self::FluidOz => Self::RegionalFluidOz(system),
+            other => *other,
+        }
+    }
+
     /// Convert a measurement in `self` units to liters
-    pub fn to_liters(&self, val: f32) -> f32 {
-        val * self.conversion_factor()
+    pub fn to_liters(&self, val: f32, system: VolumeSystem) -> f32 {
+        val * self.conversion_factor(system)
     }
 
     /// Convert a measurement in liters to a measurement in units of
     /// `self`
-    pub fn from_liters(&self, val: f32) -> f32 {
-        val / self.conversion_factor()
+    pub fn from_liters(&self, val: f32, system: VolumeSystem) -> f32 {
+        val / self.conversion_factor(system)
     }
 }
 
 impl fmt::Display for VolumeUnit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Cup => write!(f, "cup"),
-            Self::Liter => write!(f, "liter"),
-            Self::Milliliter => write!(f, "milliliter"),
-            Self::Teaspoon => write!(f, "teaspoon"),
-            Self::Tablespoon => write!(f, "tablespoon"),
-            Self::Pint => write!(f, "pint"),
-            Self::Quart => write!(f, "quart"),
-            Self::Gallon => write!(f, "gallon"),
-            Self::FluidOz => write!(f, "fluid ounce"),
-        }
+        write_unit(f, self)
     }
 }
 
@@ -184,35 +306,20 @@ impl Volume {
         Self { unit, val }
     }
 
-    /// Convert this volume measurement into a measurement of the given unit
-    pub fn convert(&self, unit: VolumeUnit) -> Self {
+    /// Convert this volume measurement into a measurement of the given unit, measuring units
+    /// without a system in `system`
+    pub fn convert(&self, unit: VolumeUnit, system: VolumeSystem) -> Self {
         Self {
-            val: unit.from_liters(self.unit.to_liters(self.val)),
+            val: unit.from_liters(self.unit.to_liters(self.val, system), system),
             unit,
         }
     }
 }
 
-impl fmt::Display for Volume {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} {}{}",
-            self.val,
-            self.unit,
-            if (self.val - 1.0).abs() < f32::EPSILON {
-                ""
-            } else {
-                "s"
-            }
-        )
-    }
-}
-
 /// Enumeration for how an ingredient's amount's unit is stored
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, druid::Data)]
 pub enum AmountUnit {
-    /// A raw number, displayed as x{n}
+    /// A raw number of an ingredient
     Count,
     /// A measurement of volume in cups, liters, etc.
     Volume(VolumeUnit),
@@ -243,3 +350,11 @@ impl fmt::Display for AmountUnit {
         }
     }
 }
+
+/// Write the singular name of a unit, or its abbreviation when formatted with `{:#}`
+fn write_unit(f: &mut fmt::Formatter<'_>, unit: &impl Unit) -> fmt::Result {
+    match f.alternate() {
+        true => f.write_str(unit.abbreviation()),
+        false => f.write_str(unit.singular()),
+    }
+}
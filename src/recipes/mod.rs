@@ -1,3 +1,5 @@
 pub mod db;
+pub mod format;
+pub mod jsonld;
 pub mod measure;
 pub mod recipe;
@@ -2,7 +2,7 @@
 use chrono::{DateTime, Local, TimeZone, Utc};
 use druid::im::{vector, Vector};
 use serde::{Deserialize, Serialize};
-use std::{fmt, sync::Arc, time};
+use std::{sync::Arc, time};
 
 use super::{
     db::RecipeId,
@@ -18,20 +18,13 @@ pub struct Ingredient {
     #[data(ignore)]
     pub amount: IngredientAmount,
     /// If the ingredient is optional
-    #[serde(default)]
     pub optional: bool,
 }
 
-impl fmt::Display for Ingredient {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.name, self.amount)
-    }
-}
-
 /// Enumeration for how an ingredient's amount is displayed
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 pub enum IngredientAmount {
-    /// A raw number, displayed as x{n}
+    /// A raw number of the ingredient
     Count(f32),
     /// A measurement of volume in cups, liters, etc.
     Volume(Volume),
@@ -52,17 +45,6 @@ impl IngredientAmount {
     }
 }
 
-impl fmt::Display for IngredientAmount {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Count(amt) => write!(f, "x{}", amt),
-            Self::Volume(vol) => vol.fmt(f),
-            Self::Mass(mass) => mass.fmt(f),
-            Self::None => Ok(()),
-        }
-    }
-}
-
 /// Immutable struct containing all data a user can add to a recipe
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, druid::Lens)]
 pub struct Recipe {
@@ -71,18 +53,14 @@ pub struct Recipe {
     /// The name of the recipe
     pub name: Arc<str>,
     /// When this recipe was created
-    #[serde(default = "chrono::Utc::now")]
     pub created_on: DateTime<Utc>,
     /// How many servings a recipe makes
-    #[serde(default)]
     pub servings: Option<f32>,
     /// A list of ingredients in the recipe
-    #[serde(default)]
     pub ingredients: Vector<Ingredient>,
     /// The recipe's instructions
     pub body: Arc<str>,
     /// The time that the recipe takes to make
-    #[serde(default)]
     pub time: Option<time::Duration>,
 }
 